@@ -0,0 +1,371 @@
+//! On-disk cache of provider responses.
+//!
+//! Data parsed from responses is stored as CSV files (see `crate::frame` for
+//! conversions), under `<cache dir>/<provider>/<TICKER>/<endpoint>.csv`. Each kind of data has
+//! its own time to live, as share prices change within a day while financial reports are
+//! published quarterly.
+
+use chrono::prelude::*;
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::frame::{self, Endpoint};
+use crate::provider::{CompanyDetails, DividendDataProvider, FinancialReport, Quote};
+use crate::{DataSource, DividendSeries, Error, SplitEvent};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+/// How long cached response of given kind is considered fresh
+pub fn ttl(endpoint: Endpoint) -> Duration {
+    match endpoint {
+        Endpoint::Quote => Duration::from_secs(4 * HOUR),
+        Endpoint::Dividends => Duration::from_secs(3 * DAY),
+        Endpoint::Splits => Duration::from_secs(3 * DAY),
+        Endpoint::Details => Duration::from_secs(3 * DAY),
+        Endpoint::Financials => Duration::from_secs(14 * DAY),
+    }
+}
+
+/// Usage of cache during current run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Responses served from cache
+    pub hits: usize,
+    /// Responses not found in cache
+    pub misses: usize,
+    /// Responses found in cache, but too old to be used
+    pub expired: usize,
+    /// Responses fetched again as refresh was requested
+    pub refreshed: usize,
+    /// Cache files that could not be read or written
+    pub errors: usize,
+}
+
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cache: {} hits, {} misses, {} expired, {} refreshed, {} errors",
+            self.hits, self.misses, self.expired, self.refreshed, self.errors
+        )
+    }
+}
+
+/// Location and settings of cache. Clones share statistics
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    refresh: bool,
+    stats: Arc<Mutex<CacheStats>>,
+}
+
+impl Cache {
+    /// Cache under given directory. With `refresh` set cached responses are never used,
+    /// but are replaced with fresh ones
+    pub fn new(dir: &Path, refresh: bool) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            refresh,
+            stats: Arc::new(Mutex::new(CacheStats::default())),
+        }
+    }
+
+    /// $XDG_CACHE_HOME/investments-forecasting or ~/.cache/investments-forecasting
+    pub fn default_dir() -> PathBuf {
+        let base = match std::env::var("XDG_CACHE_HOME") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => match std::env::var("HOME") {
+                Ok(home) => PathBuf::from(home).join(".cache"),
+                Err(_) => std::env::temp_dir(),
+            },
+        };
+        base.join("investments-forecasting")
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    fn count(&self, update: impl FnOnce(&mut CacheStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            update(&mut stats);
+        }
+    }
+
+    fn path(
+        &self,
+        source: DataSource,
+        endpoint: Endpoint,
+        symbol: &str,
+        as_of: Option<NaiveDate>,
+    ) -> PathBuf {
+        match as_of {
+            // Responses depending on date are cached separately for every date
+            Some(as_of) => self.dir.join(source.to_string()).join(symbol).join(format!(
+                "{}-{}.csv",
+                endpoint.name(),
+                as_of.format("%Y-%m-%d")
+            )),
+            None => endpoint.path(&self.dir.join(source.to_string()), symbol),
+        }
+    }
+
+    /// Cached response if present and still fresh
+    fn lookup(&self, path: &Path, endpoint: Endpoint) -> Option<DataFrame> {
+        if self.refresh {
+            if path.exists() {
+                self.count(|s| s.refreshed += 1);
+            } else {
+                self.count(|s| s.misses += 1);
+            }
+            return None;
+        }
+        let age = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .unwrap_or_default()
+            });
+        match age {
+            None => {
+                self.count(|s| s.misses += 1);
+                None
+            }
+            Some(age) if age > ttl(endpoint) => {
+                log::info!("Cache: {} is expired", path.display());
+                self.count(|s| s.expired += 1);
+                None
+            }
+            Some(_) => match frame::read_df(path) {
+                Ok(df) => {
+                    self.count(|s| s.hits += 1);
+                    df
+                }
+                Err(e) => {
+                    log::warn!("Cache: {e}");
+                    self.count(|s| s.errors += 1);
+                    None
+                }
+            },
+        }
+    }
+
+    fn store(&self, path: &Path, df: PolarsResult<DataFrame>) {
+        let stored = df
+            .map_err(|e| Error::parse("response to be cached", e))
+            .and_then(|df| frame::write_df(path, df));
+        if let Err(e) = stored {
+            log::warn!("Cache: {e}");
+            self.count(|s| s.errors += 1);
+        }
+    }
+}
+
+/// Provider serving fresh responses from cache and asking wrapped provider otherwise
+pub struct CachingProvider {
+    inner: Box<dyn DividendDataProvider>,
+    cache: Cache,
+}
+
+impl CachingProvider {
+    pub fn new(inner: Box<dyn DividendDataProvider>, cache: Cache) -> Self {
+        Self { inner, cache }
+    }
+
+    fn cached<T>(
+        &self,
+        endpoint: Endpoint,
+        symbol: &str,
+        as_of: Option<NaiveDate>,
+        fetch: impl FnOnce() -> Result<T, Error>,
+        to_df: impl Fn(&T) -> PolarsResult<DataFrame>,
+        from_df: impl Fn(&DataFrame) -> PolarsResult<Option<T>>,
+    ) -> Result<T, Error> {
+        let path = self
+            .cache
+            .path(self.inner.source(), endpoint, symbol, as_of);
+        if let Some(df) = self.cache.lookup(&path, endpoint) {
+            match from_df(&df) {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => (),
+                Err(e) => {
+                    log::warn!("Cache: unable to use {}: {e}", path.display());
+                    self.cache.count(|s| s.errors += 1);
+                }
+            }
+        }
+        let value = fetch()?;
+        self.cache.store(&path, to_df(&value));
+        Ok(value)
+    }
+}
+
+impl DividendDataProvider for CachingProvider {
+    fn source(&self) -> DataSource {
+        self.inner.source()
+    }
+
+    fn quote(&self, symbol: &str, as_of: NaiveDate) -> Result<Quote, Error> {
+        self.cached(
+            Endpoint::Quote,
+            symbol,
+            Some(as_of),
+            || self.inner.quote(symbol, as_of),
+            frame::quote_to_df,
+            frame::quote_from_df,
+        )
+    }
+
+    fn dividend_history(&self, symbol: &str) -> Result<DividendSeries, Error> {
+        self.cached(
+            Endpoint::Dividends,
+            symbol,
+            None,
+            || self.inner.dividend_history(symbol),
+            |div_history| frame::dividends_to_df(div_history),
+            |df| frame::dividends_from_df(df).map(Some),
+        )
+    }
+
+    fn splits(&self, symbol: &str) -> Result<Vec<SplitEvent>, Error> {
+        self.cached(
+            Endpoint::Splits,
+            symbol,
+            None,
+            || self.inner.splits(symbol),
+            |splits| frame::splits_to_df(splits),
+            |df| frame::splits_from_df(df).map(Some),
+        )
+    }
+
+    fn dividends_and_splits(
+        &self,
+        symbol: &str,
+    ) -> Result<(DividendSeries, Vec<SplitEvent>), Error> {
+        // Both are cached separately, but wrapped provider may download them together
+        let mut downloaded = None;
+        let div_history = self.cached(
+            Endpoint::Dividends,
+            symbol,
+            None,
+            || {
+                let (div_history, splits) = self.inner.dividends_and_splits(symbol)?;
+                downloaded = Some(splits);
+                Ok(div_history)
+            },
+            |div_history| frame::dividends_to_df(div_history),
+            |df| frame::dividends_from_df(df).map(Some),
+        )?;
+        let splits = self.cached(
+            Endpoint::Splits,
+            symbol,
+            None,
+            || match downloaded.take() {
+                Some(splits) => Ok(splits),
+                None => self.inner.splits(symbol),
+            },
+            |splits| frame::splits_to_df(splits),
+            |df| frame::splits_from_df(df).map(Some),
+        )?;
+        Ok((div_history, splits))
+    }
+
+    fn company_details(&self, symbol: &str) -> Result<CompanyDetails, Error> {
+        self.cached(
+            Endpoint::Details,
+            symbol,
+            None,
+            || self.inner.company_details(symbol),
+            frame::details_to_df,
+            |df| frame::details_from_df(df).map(Some),
+        )
+    }
+
+    fn financials(&self, symbol: &str) -> Result<Vec<FinancialReport>, Error> {
+        self.cached(
+            Endpoint::Financials,
+            symbol,
+            None,
+            || self.inner.financials(symbol),
+            |reports| frame::financials_to_df(reports),
+            |df| frame::financials_from_df(df).map(Some),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DividendEvent;
+
+    struct FixedProvider;
+
+    impl DividendDataProvider for FixedProvider {
+        fn source(&self) -> DataSource {
+            DataSource::Yahoo
+        }
+
+        fn quote(&self, _symbol: &str, _as_of: NaiveDate) -> Result<Quote, Error> {
+            Ok(Quote {
+                price: 31.2,
+                currency: Some("USD".to_owned()),
+            })
+        }
+
+        fn dividend_history(&self, _symbol: &str) -> Result<DividendSeries, Error> {
+            Ok(DividendSeries::new(vec![DividendEvent::new(
+                NaiveDate::from_ymd_opt(2023, 1, 5).unwrap(),
+                0.22,
+            )]))
+        }
+
+        fn splits(&self, _symbol: &str) -> Result<Vec<SplitEvent>, Error> {
+            Ok(vec![])
+        }
+
+        fn company_details(&self, _symbol: &str) -> Result<CompanyDetails, Error> {
+            Ok(CompanyDetails::default())
+        }
+
+        fn financials(&self, _symbol: &str) -> Result<Vec<FinancialReport>, Error> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn test_cache_hits_and_refresh() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("cache-{}", std::process::id()));
+        let cache = Cache::new(&dir, false);
+        let as_of = NaiveDate::from_ymd_opt(2023, 3, 1).unwrap();
+        let provider = CachingProvider::new(Box::new(FixedProvider), cache.clone());
+
+        let quote = provider.quote("ABM", as_of).map_err(|e| e.to_string())?;
+        assert_eq!(provider.quote("ABM", as_of), Ok(quote));
+        let div_history = provider
+            .dividend_history("ABM")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(provider.dividend_history("ABM"), Ok(div_history));
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                ..Default::default()
+            }
+        );
+
+        // Refresh ignores cached responses, but updates them
+        let refreshing = Cache::new(&dir, true);
+        let provider = CachingProvider::new(Box::new(FixedProvider), refreshing.clone());
+        provider.quote("ABM", as_of).map_err(|e| e.to_string())?;
+        assert_eq!(refreshing.stats().refreshed, 1);
+
+        std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
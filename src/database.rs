@@ -0,0 +1,686 @@
+//! Database of analysis results kept between runs of divanalysis.
+//!
+//! Every row holds metrics of one company as of one date. Storing company analysed as of
+//! the same date again replaces its row, while analysis as of another date adds a new one.
+//! Column names do not depend on analysis options (target yield, DGR estimator, kinds of
+//! yield), those are stored as values instead:
+//!
+//! ```text
+//! symbol,as_of,source,currency,share_price,recent_div,forward_div,yield_kind,div_yield,...
+//! ABR,2024-01-15,Polygon,USD,13.2,0.43,1.72,forward,13.03,...
+//! ```
+//!
+//! Database is a CSV file, or Parquet file if its name ends with ".parquet". Every row carries
+//! version of schema it was written with, and files written with older schema are migrated
+//! when opened. CSV files written by divanalysis before this module existed (with headers
+//! like "Years of\nconsecutive Div growth") are treated as version 0.
+//!
+//! Rows of the same company are snapshots of its metrics, so `AnalysisDatabase::history`
+//! shows how they changed between analyses.
+
+use chrono::prelude::*;
+use polars::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::frame::{f64_column, str_column};
+use crate::{AnalysisOptions, DividendProfile, Error, YieldKind};
+
+/// Version of schema written by this version of crate
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// Type of values of database column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnKind {
+    Text,
+    Float,
+    Integer,
+}
+
+impl ColumnKind {
+    fn dtype(&self) -> DataType {
+        match self {
+            ColumnKind::Text => DataType::Utf8,
+            ColumnKind::Float => DataType::Float64,
+            ColumnKind::Integer => DataType::Int64,
+        }
+    }
+}
+
+/// Columns of current schema in order they are stored
+const COLUMNS: &[(&str, ColumnKind)] = &[
+    ("symbol", ColumnKind::Text),
+    ("as_of", ColumnKind::Text),
+    ("source", ColumnKind::Text),
+    ("currency", ColumnKind::Text),
+    ("share_price", ColumnKind::Float),
+    ("recent_div", ColumnKind::Float),
+    ("forward_div", ColumnKind::Float),
+    ("yield_kind", ColumnKind::Text),
+    ("div_yield", ColumnKind::Float),
+    ("forward_yield", ColumnKind::Float),
+    ("ttm_yield", ColumnKind::Float),
+    ("last_fy_yield", ColumnKind::Float),
+    ("target_yield", ColumnKind::Float),
+    ("target_price", ColumnKind::Float),
+    ("distance_to_target", ColumnKind::Float),
+    ("annual_frequency", ColumnKind::Integer),
+    ("payment_schedule", ColumnKind::Text),
+    ("dgr_estimator", ColumnKind::Text),
+    ("dgr_1y_ttm", ColumnKind::Float),
+    ("dgr_1y", ColumnKind::Float),
+    ("dgr_3y", ColumnKind::Float),
+    ("dgr_5y", ColumnKind::Float),
+    ("dgr_10y", ColumnKind::Float),
+    ("dgr_10y_r_squared", ColumnKind::Float),
+    ("years_of_growth", ColumnKind::Integer),
+    ("years_without_cut", ColumnKind::Integer),
+    ("years_of_payment", ColumnKind::Integer),
+    ("dividend_list", ColumnKind::Text),
+    ("payout_ratio", ColumnKind::Float),
+    ("payout_periods", ColumnKind::Text),
+    ("fcf_payout_ratio", ColumnKind::Float),
+    ("eps_payout_ratio", ColumnKind::Float),
+    ("fcf_coverage", ColumnKind::Float),
+    ("special_div", ColumnKind::Float),
+    ("sector", ColumnKind::Text),
+    ("schema_version", ColumnKind::Integer),
+];
+
+/// Format of file database is stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseFormat {
+    Csv,
+    Parquet,
+}
+
+impl DatabaseFormat {
+    /// Format according to extension of file: Parquet for ".parquet", CSV otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("parquet") => DatabaseFormat::Parquet,
+            _ => DatabaseFormat::Csv,
+        }
+    }
+}
+
+/// Metric of company which changes can be followed between snapshots
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Metric {
+    /// Dividend yield[%] of kind chosen at time of analysis
+    #[default]
+    DivYield,
+    /// Payout ratio[%] of operating cash flow or as reported by provider
+    PayoutRatio,
+    /// Dividend growth rate over last five years[%]
+    #[value(name = "dgr-5y")]
+    Dgr5y,
+    SharePrice,
+    /// Distance[%] of share price to price yielding target yield of analysis
+    DistanceToTarget,
+}
+
+impl Metric {
+    /// Name shown in column headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::DivYield => "Div Yield[%]",
+            Metric::PayoutRatio => "Payout ratio[%]",
+            Metric::Dgr5y => "DGR 5Y[%]",
+            Metric::SharePrice => "Share Price",
+            Metric::DistanceToTarget => "Distance to Target[%]",
+        }
+    }
+
+    /// Column of database holding metric
+    fn column(&self) -> &'static str {
+        match self {
+            Metric::DivYield => "div_yield",
+            Metric::PayoutRatio => "payout_ratio",
+            Metric::Dgr5y => "dgr_5y",
+            Metric::SharePrice => "share_price",
+            Metric::DistanceToTarget => "distance_to_target",
+        }
+    }
+}
+
+/// Name of payment schedule, marked if it is not followed consistently
+pub fn schedule_name(profile: &DividendProfile) -> Option<String> {
+    profile.payment_frequency.map(|f| {
+        if f.confident {
+            f.frequency.name().to_owned()
+        } else {
+            format!("{} (uncertain)", f.frequency.name())
+        }
+    })
+}
+
+/// Single row of database holding given profile together with price at which company would
+/// yield `target_yield`[%] and distance[%] of current price to it
+pub fn profile_to_df(
+    profile: &DividendProfile,
+    options: &AnalysisOptions,
+    target_yield: f64,
+    target_price: Option<f64>,
+    distance: Option<f64>,
+) -> PolarsResult<DataFrame> {
+    let text = |name: &str, value: Option<&str>| Series::new(name, &[value]);
+    let float = |name: &str, value: Option<f64>| Series::new(name, &[value]);
+    let integer = |name: &str, value: Option<i64>| Series::new(name, &[value]);
+    let payout_periods =
+        (!profile.payout_periods.is_empty()).then(|| profile.payout_periods.join(", "));
+    let source = profile.source.to_string();
+    let as_of = profile.as_of.format("%Y-%m-%d").to_string();
+    DataFrame::new(vec![
+        text("symbol", Some(profile.symbol.as_str())),
+        text("as_of", Some(as_of.as_str())),
+        text("source", Some(source.as_str())),
+        text("currency", profile.currency.as_deref()),
+        float("share_price", Some(profile.share_price)),
+        float("recent_div", profile.recent_div),
+        float("forward_div", profile.forward_div),
+        text("yield_kind", Some(options.yield_kind.name())),
+        float("div_yield", profile.div_yield),
+        float("forward_yield", profile.forward_yield),
+        float("ttm_yield", profile.ttm_yield),
+        float("last_fy_yield", profile.last_fy_yield),
+        float("target_yield", Some(target_yield)),
+        float("target_price", target_price),
+        float("distance_to_target", distance),
+        integer("annual_frequency", profile.frequency),
+        text("payment_schedule", schedule_name(profile).as_deref()),
+        text("dgr_estimator", Some(options.dgr_estimator.name())),
+        float("dgr_1y_ttm", profile.dgr_1y_ttm),
+        float("dgr_1y", profile.dgr_1y),
+        float("dgr_3y", profile.dgr_3y),
+        float("dgr_5y", profile.dgr_5y),
+        float("dgr_10y", profile.dgr_10y),
+        float("dgr_10y_r_squared", profile.dgr_10y_r_squared),
+        integer("years_of_growth", profile.years_of_growth),
+        integer("years_without_cut", profile.years_without_cut),
+        integer("years_of_payment", profile.years_of_payment),
+        text("dividend_list", profile.dividend_list.map(|l| l.name())),
+        float("payout_ratio", profile.payout_ratio),
+        text("payout_periods", payout_periods.as_deref()),
+        float("fcf_payout_ratio", profile.fcf_payout_ratio),
+        float("eps_payout_ratio", profile.eps_payout_ratio),
+        float("fcf_coverage", profile.fcf_coverage),
+        float("special_div", profile.special_div),
+        text("sector", profile.sector.as_deref()),
+        integer("schema_version", Some(SCHEMA_VERSION)),
+    ])
+}
+
+/// Database with no rows
+fn empty() -> PolarsResult<DataFrame> {
+    conform(&DataFrame::default())
+}
+
+/// Columns of current schema in their order and types. Missing columns are filled with nulls
+/// and unknown ones are dropped
+fn conform(df: &DataFrame) -> PolarsResult<DataFrame> {
+    let names = df.get_column_names();
+    let columns = COLUMNS
+        .iter()
+        .map(|(name, kind)| {
+            if names.contains(name) {
+                df.column(name)?.cast(&kind.dtype())
+            } else {
+                Ok(Series::full_null(name, df.height(), &kind.dtype()))
+            }
+        })
+        .collect::<PolarsResult<Vec<Series>>>()?;
+    DataFrame::new(columns)
+}
+
+/// Version of schema data frame was written with
+fn schema_version(df: &DataFrame) -> PolarsResult<i64> {
+    if df.get_column_names().contains(&"schema_version") {
+        Ok(df
+            .column("schema_version")?
+            .cast(&DataType::Int64)?
+            .i64()?
+            .max()
+            .unwrap_or(SCHEMA_VERSION))
+    } else if df.get_column_names().contains(&"Symbol") {
+        Ok(0)
+    } else {
+        Err(PolarsError::ComputeError(
+            "neither \"schema_version\" nor \"Symbol\" column found".into(),
+        ))
+    }
+}
+
+/// Name of column of version 1 a column of version 0 was renamed to
+fn v0_column_name(header: &str) -> Option<&'static str> {
+    // DGR headers were followed by name of estimator e.g. "DGR 5Y[%]\n(CAGR)"
+    let header = header.split('\n').next().unwrap_or_default();
+    Some(match header {
+        "Symbol" => "symbol",
+        "Share Price" => "share_price",
+        "Recent Div" => "recent_div",
+        "Forward Div" => "forward_div",
+        "Annual Frequency" => "annual_frequency",
+        "Payment" => "payment_schedule",
+        "DGR 1Y TTM[%]" => "dgr_1y_ttm",
+        "DGR 1Y[%]" => "dgr_1y",
+        "DGR 3Y[%]" => "dgr_3y",
+        "DGR 5Y[%]" => "dgr_5y",
+        "DGR 10Y[%]" => "dgr_10y",
+        "DGR 10Y R²" => "dgr_10y_r_squared",
+        "Years without" => "years_without_cut",
+        "Distance" => "distance_to_target",
+        "Payout ratio[%]" => "payout_ratio",
+        "Payout" => "payout_periods",
+        "FCF Payout[%]" => "fcf_payout_ratio",
+        "EPS Payout[%]" => "eps_payout_ratio",
+        "FCF coverage" => "fcf_coverage",
+        "Special Div" => "special_div",
+        "Dividend list" => "dividend_list",
+        "Industry Desc" => "sector",
+        _ => return None,
+    })
+}
+
+/// Text within parentheses on the second line of header e.g. "CAGR" of "DGR 5Y[%]\n(CAGR)"
+fn header_detail(header: &str) -> Option<&str> {
+    header
+        .split_once("\n(")
+        .and_then(|(_, detail)| detail.strip_suffix(')'))
+}
+
+/// Copy of column under another name
+fn renamed(column: &Series, name: &str) -> Series {
+    let mut column = column.clone();
+    column.rename(name);
+    column
+}
+
+/// Migrate CSV written by divanalysis with headers meant for display. It had no as-of date,
+/// so `as_of` is used for all rows, and repeated rows of a company (stacked by every run)
+/// cannot be told apart, so only the last one is kept
+fn migrate_v0(df: DataFrame, as_of: NaiveDate) -> PolarsResult<DataFrame> {
+    let height = df.height();
+    let mut columns: Vec<Series> = vec![];
+    let mut constant = |name: &str, value: Option<String>| {
+        columns.push(Series::new(name, vec![value; height]));
+    };
+    constant("as_of", Some(as_of.format("%Y-%m-%d").to_string()));
+    let mut columns_v0: Vec<Series> = vec![];
+    for column in df.get_columns() {
+        let header = column.name();
+        let detail = header_detail(header).map(|d| d.to_owned());
+        if let Some(yield_) = header
+            .strip_prefix("Target Price\n(Div yield ")
+            .and_then(|y| y.strip_suffix("%)"))
+        {
+            constant("target_yield", Some(yield_.to_owned()));
+            columns_v0.push(renamed(column, "target_price"));
+        } else if header.starts_with("Div Yield[%]") {
+            // The first yield column was the one target price was computed from
+            let kind = [YieldKind::Forward, YieldKind::Ttm, YieldKind::LastFy]
+                .into_iter()
+                .find(|k| detail.as_deref() == Some(k.name()));
+            if !columns_v0.iter().any(|c| c.name() == "div_yield") {
+                constant("yield_kind", kind.map(|k| k.name().to_owned()));
+                columns_v0.push(renamed(column, "div_yield"));
+            }
+            let name = match kind {
+                Some(YieldKind::Forward) => "forward_yield",
+                Some(YieldKind::Ttm) => "ttm_yield",
+                Some(YieldKind::LastFy) => "last_fy_yield",
+                None => continue,
+            };
+            columns_v0.push(renamed(column, name));
+        } else if let Some(name) = v0_column_name(header) {
+            if name == "dgr_1y" {
+                constant("dgr_estimator", detail);
+            }
+            columns_v0.push(renamed(column, name));
+        } else if header.starts_with("Years of\nconsecutive") {
+            columns_v0.push(renamed(column, "years_of_growth"));
+        } else if header.starts_with("Years of\nDiv payments") {
+            columns_v0.push(renamed(column, "years_of_payment"));
+        } else {
+            log::warn!("Column {header:?} of analysis database is not known, dropping it");
+        }
+    }
+    columns.extend(columns_v0);
+    let df = DataFrame::new(columns)?;
+    let df = df.unique_stable(Some(&["symbol".to_owned()]), UniqueKeepStrategy::Last, None)?;
+    if df.height() < height {
+        log::warn!(
+            "Analysis database had {} repeated rows of the same companies, kept the last ones",
+            height - df.height()
+        );
+    }
+    let mut df = conform(&df)?;
+    df.with_column(Series::new("schema_version", vec![1i64; df.height()]))?;
+    Ok(df)
+}
+
+/// Migrations from version equal to index to the next one
+const MIGRATIONS: &[fn(DataFrame, NaiveDate) -> PolarsResult<DataFrame>] = &[migrate_v0];
+
+/// Data frame written with given version of schema in current version. `legacy_as_of` is
+/// as-of date of rows written before the date was stored
+fn migrate(mut df: DataFrame, version: i64, legacy_as_of: NaiveDate) -> Result<DataFrame, Error> {
+    if version > SCHEMA_VERSION {
+        return Err(Error::Config(format!(
+            "Analysis database was written with schema version {version}, newer than supported {SCHEMA_VERSION}"
+        )));
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        log::info!(
+            "Migrating analysis database from schema version {from} to {}",
+            from + 1
+        );
+        df = migration(df, legacy_as_of)
+            .map_err(|e| Error::parse(&format!("analysis database of version {from}"), e))?;
+    }
+    Ok(df)
+}
+
+/// Analysis results of companies stored as of dates they were made
+pub struct AnalysisDatabase {
+    path: PathBuf,
+    format: DatabaseFormat,
+    df: DataFrame,
+}
+
+impl AnalysisDatabase {
+    /// Read database from given file, migrating it to current schema. Database is empty if
+    /// there is no such file yet
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let format = DatabaseFormat::from_path(path);
+        let what = format!("analysis database {}", path.display());
+        let df = if path.exists() {
+            log::info!("Reading {what}");
+            let df = match format {
+                DatabaseFormat::Csv => {
+                    CsvReader::from_path(path).and_then(|reader| reader.has_header(true).finish())
+                }
+                DatabaseFormat::Parquet => std::fs::File::open(path)
+                    .map_err(PolarsError::from)
+                    .and_then(|file| ParquetReader::new(file).finish()),
+            }
+            .map_err(|e| Error::parse(&what, e))?;
+            let version = schema_version(&df).map_err(|e| Error::parse(&what, e))?;
+            // Rows of version 0 are assumed to be as of the last time file was written
+            let modified: DateTime<Local> = std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .map(|t| t.into())
+                .unwrap_or_else(|_| Local::now());
+            let df = migrate(df, version, modified.date_naive())?;
+            conform(&df).map_err(|e| Error::parse(&what, e))?
+        } else {
+            log::info!("Creating {what}");
+            empty().map_err(|e| Error::parse(&what, e))?
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            format,
+            df,
+        })
+    }
+
+    /// All stored rows
+    pub fn data(&self) -> &DataFrame {
+        &self.df
+    }
+
+    /// Store given rows, replacing ones of the same symbol and as-of date
+    pub fn upsert(&mut self, rows: &DataFrame) -> Result<(), Error> {
+        let what = format!("analysis database {}", self.path.display());
+        let upsert = || -> PolarsResult<DataFrame> {
+            let keys = |df: &DataFrame| -> PolarsResult<Vec<(Option<String>, Option<String>)>> {
+                Ok(str_column(df, "symbol")?
+                    .into_iter()
+                    .zip(str_column(df, "as_of")?)
+                    .collect())
+            };
+            let new_keys: HashSet<_> = keys(rows)?.into_iter().collect();
+            let keep: Vec<bool> = keys(&self.df)?
+                .iter()
+                .map(|key| !new_keys.contains(key))
+                .collect();
+            self.df
+                .filter(&BooleanChunked::from_slice("", &keep))?
+                .vstack(&conform(rows)?)
+        };
+        self.df = upsert().map_err(|e| Error::parse(&what, e))?;
+        Ok(())
+    }
+
+    /// Write database to its file sorted by symbol and as-of date. File is replaced only once
+    /// it is written completely
+    pub fn save(&self) -> Result<(), Error> {
+        let what = format!("analysis database {}", self.path.display());
+        let mut df = self
+            .df
+            .sort(["symbol", "as_of"], vec![false, false], false)
+            .map_err(|e| Error::parse(&what, e))?;
+        let mut partial = self.path.clone().into_os_string();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let mut file = std::fs::File::create(&partial)
+            .map_err(|e| Error::Config(format!("Unable to create {what}: {e}")))?;
+        match self.format {
+            DatabaseFormat::Csv => CsvWriter::new(&mut file).has_header(true).finish(&mut df),
+            DatabaseFormat::Parquet => ParquetWriter::new(&mut file).finish(&mut df).map(|_| ()),
+        }
+        .map_err(|e| Error::Config(format!("Unable to write {what}: {e}")))?;
+        std::fs::rename(&partial, &self.path)
+            .map_err(|e| Error::Config(format!("Unable to write {what}: {e}")))?;
+        log::info!("{what} was written");
+        Ok(())
+    }
+
+    /// Values of metric of given companies, one row per as-of date any of them was analysed
+    /// as of ("As of" column) and one column per company. Companies missing from database
+    /// are reported as error
+    pub fn history(&self, symbols: &[String], metric: Metric) -> Result<DataFrame, Error> {
+        let what = format!("analysis database {}", self.path.display());
+        let rows = || -> PolarsResult<Vec<(String, String, Option<f64>)>> {
+            Ok(str_column(&self.df, "symbol")?
+                .into_iter()
+                .zip(str_column(&self.df, "as_of")?)
+                .zip(f64_column(&self.df, metric.column())?)
+                .filter_map(|((symbol, as_of), value)| Some((symbol?, as_of?, value)))
+                .filter(|(symbol, _, _)| symbols.contains(symbol))
+                .collect())
+        };
+        let rows = rows().map_err(|e| Error::parse(&what, e))?;
+
+        if let Some(missing) = symbols
+            .iter()
+            .find(|symbol| !rows.iter().any(|(s, _, _)| s == *symbol))
+        {
+            return Err(Error::missing(&format!("snapshots in {what}")).with_ticker(missing));
+        }
+
+        let mut dates: Vec<&str> = rows.iter().map(|(_, as_of, _)| as_of.as_str()).collect();
+        dates.sort();
+        dates.dedup();
+        let mut columns = vec![Series::new("As of", &dates)];
+        for symbol in symbols {
+            let values: Vec<Option<f64>> = dates
+                .iter()
+                .map(|date| {
+                    rows.iter()
+                        .find(|(s, as_of, _)| s == symbol && as_of.as_str() == *date)
+                        .and_then(|(_, _, value)| *value)
+                })
+                .collect();
+            columns.push(Series::new(symbol, values));
+        }
+        DataFrame::new(columns).map_err(|e| Error::parse(&what, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataSource;
+
+    fn profile(symbol: &str, as_of: NaiveDate, share_price: f64) -> DividendProfile {
+        let mut profile = DividendProfile::new(symbol, DataSource::Polygon, as_of);
+        profile.share_price = share_price;
+        profile.div_yield = Some(5.0);
+        profile.forward_yield = Some(5.0);
+        profile.years_of_growth = Some(12);
+        profile
+    }
+
+    #[test]
+    fn test_upsert_and_save() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("analysis-db-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let options = AnalysisOptions::default();
+        let row = |symbol, as_of, price, target_yield| {
+            profile_to_df(
+                &profile(symbol, as_of, price),
+                &options,
+                target_yield,
+                None,
+                None,
+            )
+            .map_err(|e| e.to_string())
+        };
+
+        for name in ["analysis.csv", "analysis.parquet"] {
+            let path = dir.join(name);
+            let mut db = AnalysisDatabase::open(&path).map_err(|e| e.to_string())?;
+            assert_eq!(db.data().height(), 0);
+            db.upsert(&row("ABR", date(2024, 1, 15), 13.0, 4.0)?)
+                .map_err(|e| e.to_string())?;
+            db.upsert(&row("KO", date(2024, 1, 15), 60.0, 4.0)?)
+                .map_err(|e| e.to_string())?;
+            // The same date replaces row, other target yield does not change schema
+            db.upsert(&row("ABR", date(2024, 1, 15), 14.0, 6.0)?)
+                .map_err(|e| e.to_string())?;
+            db.upsert(&row("ABR", date(2024, 2, 15), 15.0, 6.0)?)
+                .map_err(|e| e.to_string())?;
+            db.save().map_err(|e| e.to_string())?;
+
+            let db = AnalysisDatabase::open(&path).map_err(|e| e.to_string())?;
+            assert_eq!(db.data().height(), 3);
+            let text = |name| str_column(db.data(), name).map_err(|e| e.to_string());
+            assert_eq!(
+                text("symbol")?,
+                vec![
+                    Some("ABR".to_owned()),
+                    Some("ABR".to_owned()),
+                    Some("KO".to_owned())
+                ]
+            );
+            assert_eq!(
+                text("as_of")?,
+                vec![
+                    Some("2024-01-15".to_owned()),
+                    Some("2024-02-15".to_owned()),
+                    Some("2024-01-15".to_owned())
+                ]
+            );
+            assert_eq!(
+                f64_column(db.data(), "share_price").map_err(|e| e.to_string())?,
+                vec![Some(14.0), Some(15.0), Some(60.0)]
+            );
+            assert_eq!(
+                f64_column(db.data(), "target_yield").map_err(|e| e.to_string())?[0],
+                Some(6.0)
+            );
+            assert_eq!(text("yield_kind")?[0], Some("forward".to_owned()));
+        }
+
+        std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_history() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("analysis-history-{}", std::process::id()));
+        let mut db =
+            AnalysisDatabase::open(&dir.join("analysis.csv")).map_err(|e| e.to_string())?;
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let options = AnalysisOptions::default();
+        for (symbol, as_of, price) in [
+            ("ABR", date(2024, 2, 15), 15.0),
+            ("ABR", date(2024, 1, 15), 13.0),
+            ("KO", date(2024, 2, 15), 60.0),
+        ] {
+            let row = profile_to_df(&profile(symbol, as_of, price), &options, 4.0, None, None)
+                .map_err(|e| e.to_string())?;
+            db.upsert(&row).map_err(|e| e.to_string())?;
+        }
+        let symbols = ["ABR".to_owned(), "KO".to_owned()];
+        let history = db
+            .history(&symbols, Metric::SharePrice)
+            .map_err(|e| e.to_string())?;
+        let expected = df!(
+            "As of" => &["2024-01-15", "2024-02-15"],
+            "ABR" => &[Some(13.0), Some(15.0)],
+            "KO" => &[None, Some(60.0)]
+        )
+        .map_err(|e| e.to_string())?;
+        assert!(history.frame_equal_missing(&expected), "{history}");
+
+        assert_eq!(
+            db.history(&["T".to_owned()], Metric::DivYield)
+                .err()
+                .map(|e| e.ticker().map(|t| t.to_owned())),
+            Some(Some("T".to_owned()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_v0() -> Result<(), String> {
+        let df = df!(
+            "Symbol" => &["ABR", "KO", "ABR"],
+            "Share Price" => &[13.0, 60.0, 14.0],
+            "Target Price\n(Div yield 4.5%)" => &[Some(40.0), None, Some(41.0)],
+            "Div Yield[%]\n(TTM)" => &[12.0, 3.0, 11.5],
+            "DGR 5Y[%]\n(CAGR)" => &[8.0, 4.0, 8.0],
+            "DGR 1Y[%]\n(CAGR)" => &[10.0, 5.0, 10.0],
+            "Years of\nconsecutive Div growth" => &[3i64, 60, 3],
+            "Industry Desc" => &["REIT", "Beverages", "REIT"]
+        )
+        .map_err(|e| e.to_string())?;
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let version = schema_version(&df).map_err(|e| e.to_string())?;
+        assert_eq!(version, 0);
+        let df = migrate(df, version, date).map_err(|e| e.to_string())?;
+        let df = conform(&df)
+            .and_then(|df| df.sort(["symbol"], vec![false], false))
+            .map_err(|e| e.to_string())?;
+
+        assert_eq!(df.height(), 2);
+        assert_eq!(
+            df.get_column_names(),
+            COLUMNS.iter().map(|(name, _)| *name).collect::<Vec<_>>()
+        );
+        let text = |name| str_column(&df, name).map_err(|e| e.to_string());
+        let float = |name| f64_column(&df, name).map_err(|e| e.to_string());
+        assert_eq!(
+            text("symbol")?,
+            vec![Some("ABR".to_owned()), Some("KO".to_owned())]
+        );
+        assert_eq!(text("as_of")?, vec![Some("2024-01-15".to_owned()); 2]);
+        assert_eq!(float("share_price")?, vec![Some(14.0), Some(60.0)]);
+        assert_eq!(float("target_yield")?, vec![Some(4.5); 2]);
+        assert_eq!(float("target_price")?, vec![Some(41.0), None]);
+        assert_eq!(float("div_yield")?, vec![Some(11.5), Some(3.0)]);
+        assert_eq!(float("ttm_yield")?, vec![Some(11.5), Some(3.0)]);
+        assert_eq!(text("yield_kind")?, vec![Some("TTM".to_owned()); 2]);
+        assert_eq!(text("dgr_estimator")?, vec![Some("CAGR".to_owned()); 2]);
+        assert_eq!(float("dgr_5y")?, vec![Some(8.0), Some(4.0)]);
+        assert_eq!(text("sector")?[1], Some("Beverages".to_owned()));
+
+        // Files of unknown future versions are not touched
+        let df = df!("schema_version" => &[SCHEMA_VERSION + 1]).map_err(|e| e.to_string())?;
+        assert!(migrate(df, SCHEMA_VERSION + 1, date).is_err());
+        Ok(())
+    }
+}
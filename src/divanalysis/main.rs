@@ -1,16 +1,27 @@
-use calamine::{open_workbook, Xlsx};
-use clap::Parser;
-use indicatif::ProgressBar;
+use chrono::prelude::*;
+use clap::{Parser, Subcommand};
+use gnuplot::{AxesCommon, Caption, Color, Figure};
+use investments_forecasting::cache::Cache;
+use investments_forecasting::database::{self, AnalysisDatabase, Metric};
+use investments_forecasting::fetch::{self, RateLimiter};
+use investments_forecasting::fx::FxRates;
+use investments_forecasting::list;
+use investments_forecasting::provider::{DividendDataProvider, ProviderKind, ProviderOptions};
+use investments_forecasting::retry::RetryPolicy;
+use investments_forecasting::{
+    AnalysisOptions, DgrEstimator, DividendBasis, DividendList, DividendProfile, PayoutBasis,
+    YieldKind,
+};
 use polars::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
-// TODO: Make progressbar
 // TODO: convert dividends derived elements into TTM data
-// TODO: Add support for Revenue and FCF
-// TODO: Make payout ratio based on FCF
+// TODO: Add support for Revenue
 // TODO: fix all companies list
 // TODO: make downloading all companies data
 // TODO: Get polygon companies list (multiple pages) (next_url + api key reqwest has to be done)
-// TODO: add ignoring non-complete data
 // TODO: Make UK list supported
 // TODO: Change to Result fully in get_polygon_data.
 
@@ -18,13 +29,95 @@ use polars::prelude::*;
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Data in XLSX format (Fetch from https://moneyzine.com/investments/dividend-champions/)
+    /// Dividend list in XLSX, ODS or CSV format
+    /// (Fetch from https://moneyzine.com/investments/dividend-champions/)
     #[arg(long)]
     data: Option<String>,
 
-    /// Name of the list with companies increasing dividends. Possible values: "Champions", "Contenders", "Challengers", "All"
-    #[arg(long, default_value = "Champions")]
-    list: String,
+    /// Source of companies data. When not given then "file" is used if "data" is provided,
+    /// otherwise "polygon" if POLYGON_AUTH_KEY is set or "yahoo"
+    #[arg(long, value_enum)]
+    provider: Option<ProviderKind>,
+
+    /// Store responses of provider under given directory (one subdirectory per provider and
+    /// ticker)
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Serve responses stored earlier with "record" instead of asking provider
+    #[arg(long)]
+    replay: Option<String>,
+
+    /// Directory of price and dividend CSV/JSON files (one subdirectory per ticker) served by
+    /// "local" provider, which is used when this argument is given
+    #[arg(long, conflicts_with = "replay")]
+    local_dir: Option<String>,
+
+    /// Directory of on-disk cache of provider responses
+    /// (default: $XDG_CACHE_HOME/investments-forecasting or ~/.cache/investments-forecasting)
+    #[arg(long)]
+    cache_dir: Option<String>,
+
+    /// Do not use on-disk cache of provider responses
+    #[arg(long, default_value_t = false)]
+    no_cache: bool,
+
+    /// Fetch all data from provider again, replacing cached responses
+    #[arg(long, default_value_t = false, conflicts_with = "no_cache")]
+    refresh: bool,
+
+    /// Maximal number of requests per minute sent to Polygon.io, as allowed by your plan
+    /// (default: unlimited)
+    #[arg(long)]
+    requests_per_minute: Option<u32>,
+
+    /// Maximal number of attempts of request to provider. Requests failing for transient
+    /// reasons (rate limit, server error, timeout) are repeated with growing delays
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Number of companies which data is fetched at the same time
+    #[arg(long, default_value_t = 4)]
+    concurrency: usize,
+
+    /// Date (YYYY-MM-DD) the analysis is made as of. Dividends, prices and financial reports
+    /// published after it are ignored (default: today)
+    #[arg(long)]
+    as_of: Option<NaiveDate>,
+
+    /// Date dividends are attributed to when grouping them into years
+    #[arg(long, value_enum, default_value_t = DividendBasis::ExDate)]
+    basis: DividendBasis,
+
+    /// Include special, supplemental and irregular dividends in dividend metrics
+    #[arg(long, default_value_t = false)]
+    include_special: bool,
+
+    /// Method of computing dividend growth rates
+    #[arg(long, value_enum, default_value_t = DgrEstimator::Average)]
+    dgr_estimator: DgrEstimator,
+
+    /// Dividend yields shown, comma separated. The first one is used for target price and
+    /// sorting
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [YieldKind::Forward])]
+    yields: Vec<YieldKind>,
+
+    /// Name of the list with companies increasing dividends. Possible values: "Champions", "Contenders", "Challengers", "All".
+    /// Sheet of "data" (default: "Champions"). Without "data" companies of Polygon.io are
+    /// classified by their years of dividend growth and only ones of given list are kept
+    /// (default: all of them)
+    #[arg(long)]
+    list: Option<String>,
+
+    /// Skip companies of dividend list lacking any value needed for screening (price, yield,
+    /// dividends, DGRs, cash flow per share)
+    #[arg(long, default_value_t = false)]
+    drop_incomplete: bool,
+
+    /// Skip companies of dividend list lacking value of any of given columns
+    /// e.g. "Ex-Date,P/E"
+    #[arg(long, value_delimiter = ',')]
+    require_columns: Vec<String>,
 
     /// List all available companies (from database if given or polygon in case of no given
     /// database
@@ -41,7 +134,8 @@ struct Args {
     #[arg(long = "continue", default_value_t = false, requires = "company")]
     cont: bool,
 
-    /// csv file to read DataFrame from and to write to
+    /// File storing analysis results between runs: CSV, or Parquet if its name ends with
+    /// ".parquet". Results are replaced when company is analysed again as of the same date
     #[arg(long, default_value = None)]
     database: Option<String>,
 
@@ -49,11 +143,11 @@ struct Args {
     #[arg(long, default_value_t = 3.4)]
     inflation: f64,
 
-    /// Minimum accepted Dividend Yield[%]
+    /// Minimum accepted Dividend Yield[%]. Spreadsheet data is screened by its forward yield
     #[arg(long, default_value_t = 4.7)]
     min_div_yield: f64,
 
-    /// Maximum accepted Dividend Yield[%]
+    /// Maximum accepted Dividend Yield[%]. Spreadsheet data is screened by its forward yield
     #[arg(long, default_value_t = 10.0)]
     max_div_yield: f64,
 
@@ -69,9 +163,50 @@ struct Args {
     #[arg(long, default_value_t = 75.0)]
     max_div_payout_rate: f64,
 
+    /// Payout ratio compared with "max-div-payout-rate". Spreadsheet data supports
+    /// operating-cash-flow (default) and earnings only. Companies fetched from provider are
+    /// screened only when it is given
+    #[arg(long, value_enum)]
+    payout_basis: Option<PayoutBasis>,
+
+    /// Currency share prices and dividends of companies fetched from provider are converted
+    /// into e.g. "PLN" (default: currency of every company)
+    #[arg(long)]
+    currency: Option<String>,
+
+    /// CSV file of exchange rates (columns base, quote, rate and optional date) used instead
+    /// of quoted ones
+    #[arg(long, requires = "currency")]
+    fx_overrides: Option<String>,
+
     /// Standard and Poor 500 list's average DIV Yield[%]
     #[arg(long, default_value_t = 1.61)]
     sp500_divy: f64,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show how metric of companies changed between snapshots stored in "database"
+    History {
+        /// Symbol names of companies
+        #[arg(long, required = true)]
+        company: Vec<String>,
+
+        /// Metric to show
+        #[arg(long, value_enum, default_value_t = Metric::DivYield)]
+        metric: Metric,
+
+        /// Show only given number of the most recent snapshots
+        #[arg(long)]
+        last: Option<usize>,
+
+        /// Plot history into given PNG file instead of printing it
+        #[arg(long)]
+        plot: Option<String>,
+    },
 }
 
 /// Calculate target share price when its div yield will reach our expected yield
@@ -97,6 +232,11 @@ fn calculate_target_price_and_distance(
     }
 }
 
+/// Header of column with dividend yield of given kind
+fn yield_header(kind: YieldKind) -> String {
+    format!("Div Yield[%]\n({})", kind.name())
+}
+
 fn analyze_div_yield(
     df: &DataFrame,
     sp500_divy: f64,
@@ -104,7 +244,8 @@ fn analyze_div_yield(
     min_divy: f64,
     max_divy: f64,
 ) -> Result<DataFrame, &'static str> {
-    // Dividend Yield should:
+    // Forward Dividend Yield ("Div Yield" of spreadsheet: current dividend annualized
+    // divided by price) should:
     // 1. Be higher than inflation rate
     // 2. be higher than 1.5*S&P500 Div Yield rate
     // 3. No More than 10% (over 10% is suspecious, check their cash flow)
@@ -140,17 +281,31 @@ fn analyze_div_yield(
 fn analyze_dividend_payout_rate(
     df: &DataFrame,
     max_threshold: f64,
+    basis: PayoutBasis,
 ) -> Result<DataFrame, &'static str> {
     // Dividend Payout rate
     // 1. Is Current Div / Cash flow per share e.g. 0.22 / 1.7  = 0.129412
+    //    or Annualized Div / EPS, where EPS is Price / (P/E)
     // 2. No more than 75%
 
-    let cols = df
-        .columns(&["Current Div", "CF/Share"])
-        .map_err(|_| "Current Div and/or CF/Share columns do not exist!")?;
-    let mask = (cols[0] / cols[1])
-        .lt(&Series::new("", &[max_threshold]))
-        .unwrap();
+    let payout = match basis {
+        PayoutBasis::OperatingCashFlow => {
+            let cols = df
+                .columns(&["Current Div", "CF/Share"])
+                .map_err(|_| "Current Div and/or CF/Share columns do not exist!")?;
+            cols[0] / cols[1]
+        }
+        PayoutBasis::Earnings => {
+            let cols = df
+                .columns(&["Annualized", "P/E", "Price"])
+                .map_err(|_| "Annualized, P/E and/or Price columns do not exist!")?;
+            &(cols[0] * cols[1]) / cols[2]
+        }
+        PayoutBasis::FreeCashFlow | PayoutBasis::DividendsPaid => {
+            return Err("Free cash flow is not available in spreadsheet data")
+        }
+    };
+    let mask = payout.lt(&Series::new("", &[max_threshold])).unwrap();
     let filtred_df = df.filter(&mask).expect("Error filtering");
 
     filtred_df
@@ -204,7 +359,7 @@ fn print_summary(df: &DataFrame, company: Option<&str>) -> Result<(), &'static s
         .map_err(|_| "Unable to select mentioned columns!")?;
     log::info!("Selected companies: {selected_df}");
 
-    let mut rate = dfs.column("Annualized").expect("No \"Current Div\" column")
+    let mut rate = dfs.column("Annualized").expect("No \"Annualized\" column")
         / dfs.column("CF/Share").expect("No \"CF/Share\" column")
         * 100.0;
     let rate = rate.rename("Div Payout Rate[%]");
@@ -215,6 +370,45 @@ fn print_summary(df: &DataFrame, company: Option<&str>) -> Result<(), &'static s
     Ok(())
 }
 
+/// Print companies of dividend list with missing values and leave out the ones lacking values
+/// of required columns
+fn check_completeness(
+    df: DataFrame,
+    drop_incomplete: bool,
+    require_columns: &[String],
+) -> Result<DataFrame, investments_forecasting::Error> {
+    let mut columns = list::required_columns();
+    for column in require_columns {
+        if !columns.contains(&column.as_str()) {
+            columns.push(column);
+        }
+    }
+    let incomplete = list::incomplete_rows(&df, &columns)?;
+    if !incomplete.is_empty() {
+        println!("Companies with missing data:");
+        for (symbol, missing) in &incomplete {
+            println!("  {symbol}: {}", missing.join(", "));
+        }
+    }
+
+    let required: Vec<&str> = match drop_incomplete {
+        true => columns,
+        false => require_columns.iter().map(|c| c.as_str()).collect(),
+    };
+    if required.is_empty() {
+        return Ok(df);
+    }
+    let complete = list::drop_incomplete(&df, &required)?;
+    if complete.height() < df.height() {
+        println!(
+            "Skipped {} of {} companies with missing data",
+            df.height() - complete.height(),
+            df.height()
+        );
+    }
+    Ok(complete)
+}
+
 fn configure_dataframes_format() {
     // Make sure to show all columns
     if std::env::var("POLARS_FMT_MAX_COLS").is_err() {
@@ -230,290 +424,433 @@ fn configure_dataframes_format() {
     }
 }
 
+/// Number of companies stored in analysis database between writes of its file
+const SAVE_EVERY: usize = 50;
+
+/// How companies fetched from provider are screened and shown
+struct Screening<'a> {
+    /// Dividend yield[%] target price is computed for
+    target_yield: f64,
+    /// Dividend yields shown, the first one is used for target price and sorting
+    yields: &'a [YieldKind],
+    /// Basis of payout ratio and its maximal value[%]
+    payout_filter: Option<(PayoutBasis, f64)>,
+    /// List companies have to belong to (all are kept if not given)
+    list: Option<DividendList>,
+}
+
 fn get_companies_data(
     companies: &[String],
     database: Option<String>,
-    target_yield: f64,
-) -> Result<(), &'static str> {
+    provider: &dyn DividendDataProvider,
+    currency: Option<(&FxRates, &str)>,
+    as_of: NaiveDate,
+    screening: &Screening,
+    concurrency: usize,
+    options: &AnalysisOptions,
+) -> Result<(), investments_forecasting::Error> {
     // If we have explicitly given companies then make progress bar with specific length
     // otherwise just make the one without length
-    let pb = if companies.is_empty() {
-        ProgressBar::no_length()
-    } else {
-        ProgressBar::new(companies.len() as u64)
+    let pb = fetch::progress_bar((!companies.is_empty()).then(|| companies.len() as u64));
+
+    // Profiles of companies passing screening, shown once all are fetched
+    let mut profiles: Vec<DividendProfile> = vec![];
+
+    // Results are written every few companies and once all are done (also when crawl fails
+    // or is interrupted with Ctrl-C), so they survive interrupted crawl
+    let mut database = database
+        .map(|path| AnalysisDatabase::open(Path::new(&path)))
+        .transpose()?;
+    let mut unsaved = 0;
+    let interrupted = match database {
+        Some(_) => Some(fetch::interrupt_flag()?),
+        None => None,
     };
 
-    let mut symbols: Vec<&str> = vec![];
-    let mut share_prices: Vec<f64> = vec![];
-    let mut curr_divs: Vec<Option<f64>> = vec![];
-    let mut target_prices: Vec<Option<f64>> = vec![];
-    let mut divys: Vec<Option<f64>> = vec![];
-    let mut freqs: Vec<Option<i64>> = vec![];
-    let mut dgrs: Vec<Option<f64>> = vec![];
-    let mut dgr5ys: Vec<Option<f64>> = vec![];
-    let mut dgr3ys: Vec<Option<f64>> = vec![];
-    let mut dgr1ys: Vec<Option<f64>> = vec![];
-    let mut dgr1y_ttms: Vec<Option<f64>> = vec![];
-    let mut years_growth: Vec<Option<i64>> = vec![];
-    let mut distances: Vec<Option<f64>> = vec![];
-    let mut payout_ratios: Vec<Option<f64>> = vec![];
-    let mut sectors: Vec<Option<String>> = vec![];
-
-    let s1 = Series::new("Symbol", &symbols);
-    let s2 = Series::new("Share Price", share_prices.clone());
-    let s3 = Series::new("Recent Div", curr_divs.clone());
-    let s4 = Series::new(
-        &format!("Target Price\n(Div yield {target_yield}%)"),
-        target_prices.clone(),
-    );
-    let s5 = Series::new("Annual Frequency", freqs.clone());
-    let s6 = Series::new("Div Yield[%]", divys.clone());
-    let s7 = Series::new("DGR 1Y TTM[%]", dgr1y_ttms.clone());
-    let s8 = Series::new("DGR 1Y[%]", dgr1ys.clone());
-    let s9 = Series::new("DGR 3Y[%]", dgrs.clone());
-    let s10 = Series::new("DGR 5Y[%]", dgrs.clone());
-    let s11 = Series::new("DGR 10Y[%]", dgrs.clone());
-    let s12 = Series::new("Years of\nconsecutive Div growth", years_growth.clone());
-    let s13 = Series::new("Distance\nto Target[%]", distances.clone());
-    let s14 = Series::new("Payout ratio[%]", payout_ratios.clone());
-    let s15 = Series::new("Industry Desc", sectors.clone());
-    let df: DataFrame = DataFrame::new(vec![
-        s1.clone(),
-        s2.clone(),
-        s3.clone(),
-        s4.clone(),
-        s5.clone(),
-        s6.clone(),
-        s7.clone(),
-        s8.clone(),
-        s9.clone(),
-        s10.clone(),
-        s11.clone(),
-        s12.clone(),
-        s13.clone(),
-        s14.clone(),
-        s15.clone(),
-    ])
-    .unwrap();
-
-    let start_df = if let Some(database) = database.clone() {
-        let file = std::fs::OpenOptions::new().read(true).open(&database);
-
-        let df = if let Ok(file) = file {
-            log::info!("Reading DataFrame from: {database} file");
-
-            let read_df = CsvReader::new(file)
-                .has_header(true)
-                .finish()
-                .map_err(|_| "Unable to read DataFrame from CSV file")?;
-
-            read_df
-                .vstack(&df)
-                .map_err(|_| "Unable to combine data frames")?
-        } else {
-            log::info!("Creating a CSV file: {database} file to store DataFrame");
-            df
-        };
-        df
-    } else {
-        df
+    // Companies which data could not be acquired are reported at the end
+    let mut failures: Vec<investments_forecasting::Error> = vec![];
+
+    // Data of companies is fetched concurrently, while results are collected one by one
+    let fetch_profile = |symbol: &String| {
+        let mut profile =
+            investments_forecasting::get_dividend_profile(provider, symbol, as_of, options)?;
+        if let Some((fx, currency)) = currency {
+            fx.convert_profile(&mut profile, currency)?;
+        }
+        Ok::<_, investments_forecasting::Error>(profile)
     };
+    let maybe_success =
+        fetch::for_each_concurrent(companies, concurrency, fetch_profile, |symbol, profile| {
+            if interrupted
+                .as_ref()
+                .map_or(false, |i| i.load(Ordering::Relaxed))
+            {
+                return Err(investments_forecasting::Error::Config(
+                    "Interrupted by user".to_owned(),
+                ));
+            }
+            let profile = match profile {
+                Ok(profile) => profile,
+                Err(e) => {
+                    log::error!("{e}");
+                    failures.push(e.with_ticker(symbol));
+                    pb.inc(1);
+                    return Ok(());
+                }
+            };
+
+            if screening.list.is_some() && profile.dividend_list != screening.list {
+                log::info!(
+                    "{symbol}: {:?} years of dividend growth, not in {:?}",
+                    profile.years_of_growth,
+                    screening.list
+                );
+                pb.inc(1);
+                return Ok(());
+            }
 
-    let use_polygon = std::env::var("POLYGON_AUTH_KEY").is_ok();
-    let mut provider = investments_forecasting::get_yahoo_connector()
-        .map_err(|_| "Error: Unable to create yahoo connector")?;
-
-    let maybe_success = companies.iter().try_for_each(|symbol| {
-        let (
-            share_price,
-            curr_div,
-            divy,
-            frequency,
-            dgr,
-            dgr5y,
-            dgr3y,
-            dgr1y,
-            dgr1y_ttm,
-            years_of_growth,
-            payout_ratio,
-            sector_desc,
-        ) = if use_polygon {
-            investments_forecasting::get_polygon_data(symbol)
-                .expect("Error: unable to get Data from polygon IO for forecasting")
-        } else {
-            let myref = provider.as_mut().unwrap();
-            investments_forecasting::get_yahoo_data(symbol, myref)
-                .expect("Error: unable to get Data from yahoo finance for forecasting")
-        };
+            if let Some((basis, max_payout)) = screening.payout_filter {
+                match profile.payout_of(basis) {
+                    Some(payout) if payout <= max_payout => (),
+                    payout => {
+                        log::info!(
+                            "{symbol}: {} payout ratio[%] {payout:.2?} does not meet {max_payout}",
+                            basis.name()
+                        );
+                        pb.inc(1);
+                        return Ok(());
+                    }
+                }
+            }
 
-        let (target_price, distance) =
-            calculate_target_price_and_distance(share_price, divy, target_yield);
-
-        share_prices.push(share_price);
-        target_prices.push(target_price);
-        curr_divs.push(curr_div);
-        divys.push(divy);
-        freqs.push(frequency);
-        dgr5ys.push(dgr5y);
-        dgr3ys.push(dgr3y);
-        dgr1ys.push(dgr1y);
-        dgr1y_ttms.push(dgr1y_ttm);
-        dgrs.push(dgr);
-        years_growth.push(years_of_growth);
-        distances.push(distance);
-        payout_ratios.push(payout_ratio);
-        symbols.push(symbol);
-        sectors.push(sector_desc);
-
-        if let Some(database) = database.clone() {
-            let s1 = Series::new("Symbol", &symbols);
-            let s2 = Series::new("Share Price", share_prices.clone());
-            let s3 = Series::new("Recent Div", curr_divs.clone());
-            let s4 = Series::new(
-                &format!("Target Price\n(Div yield {target_yield}%)"),
-                target_prices.clone(),
-            );
-            let s5 = Series::new("Annual Frequency", freqs.clone());
-            let s6 = Series::new("Div Yield[%]", divys.clone());
-            let s7 = Series::new("DGR 1Y TTM[%]", dgr1y_ttms.clone());
-            let s8 = Series::new("DGR 1Y[%]", dgr1ys.clone());
-            let s9 = Series::new("DGR 3Y[%]", dgr3ys.clone());
-            let s10 = Series::new("DGR 5Y[%]", dgr5ys.clone());
-            let s11 = Series::new("DGR 10Y[%]", dgrs.clone());
-            let s12 = Series::new("Years of\nconsecutive Div growth", years_growth.clone());
-            let s13 = Series::new("Distance\nto Target[%]", distances.clone());
-            let s14 = Series::new("Payout ratio[%]", payout_ratios.clone());
-            let s15 = Series::new("Industry Desc", sectors.clone());
-
-            let df: DataFrame = DataFrame::new(vec![
-                s1.clone(),
-                s2.clone(),
-                s3.clone(),
-                s4.clone(),
-                s5.clone(),
-                s6.clone(),
-                s7.clone(),
-                s8.clone(),
-                s9.clone(),
-                s10.clone(),
-                s11.clone(),
-                s12.clone(),
-                s13.clone(),
-                s14.clone(),
-                s15.clone(),
-            ])
-            .unwrap();
-
-            let df = start_df
-                .vstack(&df)
-                .map_err(|_| "Unable to combine data frames")?;
-
-            // Lets sort it so most important is years of growth and within..
-            // .. the same results we sort according to dividend yield..
-            // .. and then lastly according the DGR 3Y
-            let mut df = df
-                .sort(
-                    [
-                        "Years of\nconsecutive Div growth",
-                        "Div Yield[%]",
-                        "DGR 3Y[%]",
-                    ],
-                    vec![true, true, true],
-                    false,
+            if let Some(db) = database.as_mut() {
+                let (target_price, distance) = calculate_target_price_and_distance(
+                    profile.share_price,
+                    profile.div_yield,
+                    screening.target_yield,
+                );
+                let row = database::profile_to_df(
+                    &profile,
+                    options,
+                    screening.target_yield,
+                    target_price,
+                    distance,
                 )
-                .unwrap();
+                .map_err(|e| investments_forecasting::Error::parse("analysis database row", e))?;
+                db.upsert(&row)?;
+                unsaved += 1;
+                if unsaved >= SAVE_EVERY {
+                    db.save()?;
+                    unsaved = 0;
+                }
+            }
 
-            let mut file =
-                std::fs::File::create(&database).map_err(|_| "Unable to create CSV file")?;
+            profiles.push(profile);
+            pb.inc(1);
 
-            CsvWriter::new(&mut file)
-                .has_header(true)
-                .finish(&mut df)
-                .map_err(|_| "Unable to write DataFrame into CSV file")?;
-            log::info!("DataFrame was written to: {database} file");
-        }
+            Ok::<(), investments_forecasting::Error>(())
+        });
+    pb.finish();
 
-        pb.inc(1);
+    if let Some(db) = &database {
+        db.save()?;
+    }
+    let source = provider.source();
+    match maybe_success {
+        Ok(_) => log::info!("Acquiring of all companies via {source} succeeded!"),
+        Err(e) => log::info!("Acquiring of all companies via {source} failed! Error: {e} . Partial results are available"),
+    }
 
-        Ok::<(), &'static str>(())
-    });
+    let df = companies_table(&profiles, screening, options)
+        .map_err(|e| investments_forecasting::Error::parse("table of companies", e))?;
+    println!("{df}");
 
-    match maybe_success {
-        Ok(_) => log::info!("Acquiring of all companies via polygon succeeded!"),
-        Err(e) => log::info!("Acquiring of all companies via polygon failed! Error: {e} . Partial results are available"),
+    if !failures.is_empty() {
+        println!("Unable to acquire data of {} companies:", failures.len());
+        failures.iter().for_each(|e| println!("  {e}"));
     }
-    let s1 = Series::new("Symbol", &symbols);
-    let s2 = Series::new("Share Price", share_prices.clone());
-    let s3 = Series::new("Recent Div", curr_divs.clone());
-    let s4 = Series::new(
-        &format!("Target Price\n(Div yield {target_yield}%)"),
-        target_prices.clone(),
-    );
-    let s5 = Series::new("Annual Frequency", freqs.clone());
-    let s6 = Series::new("Div Yield[%]", divys.clone());
-    let s7 = Series::new("DGR 1Y TTM[%]", dgr1y_ttms.clone());
-    let s8 = Series::new("DGR 1Y[%]", dgr1ys.clone());
-    let s9 = Series::new("DGR 3Y[%]", dgr3ys.clone());
-    let s10 = Series::new("DGR 5Y[%]", dgr5ys.clone());
-    let s11 = Series::new("DGR 10Y[%]", dgrs.clone());
-    let s12 = Series::new("Years of\nconsecutive Div growth", years_growth.clone());
-    let s13 = Series::new("Distance\nto Target[%]", distances.clone());
-    let s14 = Series::new("Payout ratio[%]", payout_ratios.clone());
-    let s15 = Series::new("Industry Desc", sectors.clone());
-
-    let df: DataFrame = DataFrame::new(vec![
-        s1.clone(),
-        s2.clone(),
-        s3.clone(),
-        s4.clone(),
-        s5.clone(),
-        s6.clone(),
-        s7.clone(),
-        s8.clone(),
-        s9.clone(),
-        s10.clone(),
-        s11.clone(),
-        s12.clone(),
-        s13.clone(),
-        s14.clone(),
-        s15.clone(),
-    ])
-    .unwrap();
-
-    let df = start_df.vstack(&df).map_err(|e| {
-        println!("Error during combining data frames: {e}");
-        "Unable to combine data frames"
-    })?;
 
-    let df = df
-        .sort(
-            [
-                "Years of\nconsecutive Div growth",
-                "Div Yield[%]",
-                "DGR 3Y[%]",
+    Ok(())
+}
+
+/// Column of table with value of every profile
+fn column<'a, T>(
+    name: &str,
+    profiles: &'a [DividendProfile],
+    value: impl Fn(&'a DividendProfile) -> T,
+) -> Series
+where
+    Series: NamedFrom<Vec<T>, [T]>,
+{
+    Series::new(name, profiles.iter().map(value).collect::<Vec<T>>())
+}
+
+/// Table of companies shown to user, one row per profile. Companies with the most years of
+/// dividend growth come first, then ones of the highest main dividend yield and DGR 3Y
+fn companies_table(
+    profiles: &[DividendProfile],
+    screening: &Screening,
+    options: &AnalysisOptions,
+) -> PolarsResult<DataFrame> {
+    // DGRs of different estimators are not comparable, so estimator is named in headers
+    let [dgr_1y_header, dgr_3y_header, dgr_5y_header, dgr_10y_header] = ["1Y", "3Y", "5Y", "10Y"]
+        .map(|period| format!("DGR {period}[%]\n({})", options.dgr_estimator.name()));
+    let target = |p: &DividendProfile| {
+        calculate_target_price_and_distance(p.share_price, p.div_yield, screening.target_yield)
+    };
+    let target_header = format!("Target Price\n(Div yield {}%)", screening.target_yield);
+    let main_yield_header = yield_header(options.yield_kind);
+
+    let df = DataFrame::new(
+        [
+            vec![
+                column("Symbol", profiles, |p| p.symbol.as_str()),
+                column("Share Price", profiles, |p| p.share_price),
+                column("Recent Div", profiles, |p| p.recent_div),
+                column(&target_header, profiles, |p| target(p).0),
+                column("Annual Frequency", profiles, |p| p.frequency),
             ],
-            vec![true, true, true],
-            false,
-        )
-        .unwrap();
+            screening
+                .yields
+                .iter()
+                .map(|kind| column(&yield_header(*kind), profiles, |p| p.yield_of(*kind)))
+                .collect(),
+            vec![
+                column("DGR 1Y TTM[%]", profiles, |p| p.dgr_1y_ttm),
+                column(&dgr_1y_header, profiles, |p| p.dgr_1y),
+                column(&dgr_3y_header, profiles, |p| p.dgr_3y),
+                column(&dgr_5y_header, profiles, |p| p.dgr_5y),
+                column(&dgr_10y_header, profiles, |p| p.dgr_10y),
+                column("Years of\nconsecutive Div growth", profiles, |p| {
+                    p.years_of_growth
+                }),
+                column("Distance\nto Target[%]", profiles, |p| target(p).1),
+                column("Payout ratio[%]", profiles, |p| p.payout_ratio),
+                column("Special Div\n(previous year)", profiles, |p| p.special_div),
+                column("DGR 10Y R²", profiles, |p| p.dgr_10y_r_squared),
+                column("Forward Div", profiles, |p| p.forward_div),
+                column("Payment\nschedule", profiles, database::schedule_name),
+                column("FCF Payout[%]", profiles, |p| p.fcf_payout_ratio),
+                column("EPS Payout[%]", profiles, |p| p.eps_payout_ratio),
+                column("FCF coverage", profiles, |p| p.fcf_coverage),
+                // Periods of financial reports payout ratio was computed from, for auditing
+                column("Payout\nperiods", profiles, |p| {
+                    (!p.payout_periods.is_empty()).then(|| p.payout_periods.join(", "))
+                }),
+                column("Years without\nDiv cut", profiles, |p| p.years_without_cut),
+                column("Years of\nDiv payments", profiles, |p| p.years_of_payment),
+                column("Dividend list", profiles, |p| {
+                    p.dividend_list.map(|l| l.name())
+                }),
+                column("Industry Desc", profiles, |p| p.sector.as_deref()),
+            ],
+        ]
+        .concat(),
+    )?;
+
+    // Lets sort it so most important is years of growth and within..
+    // .. the same results we sort according to dividend yield..
+    // .. and then lastly according the DGR 3Y
+    df.sort(
+        [
+            "Years of\nconsecutive Div growth",
+            main_yield_header.as_str(),
+            dgr_3y_header.as_str(),
+        ],
+        vec![true, true, true],
+        false,
+    )
+}
 
-    println!("{df}");
+/// Print or plot values of metric of companies as of dates they were analysed as of
+fn show_history(
+    database: &str,
+    companies: &[String],
+    metric: Metric,
+    last: Option<usize>,
+    plot: Option<&str>,
+) -> Result<(), investments_forecasting::Error> {
+    let path = Path::new(database);
+    if !path.exists() {
+        return Err(investments_forecasting::Error::Config(format!(
+            "Analysis database {database} does not exist"
+        )));
+    }
+    let history = AnalysisDatabase::open(path)?.history(companies, metric)?;
+    let history = match last {
+        Some(last) => history.tail(Some(last)),
+        None => history,
+    };
+    match plot {
+        Some(file) => plot_history(&history, metric, file),
+        None => {
+            println!("{}:", metric.name());
+            println!("{history}");
+            Ok(())
+        }
+    }
+}
 
+/// Plot history of metric with days since the first snapshot on X axis
+fn plot_history(
+    history: &DataFrame,
+    metric: Metric,
+    file: &str,
+) -> Result<(), investments_forecasting::Error> {
+    let what = "history of analysis database";
+    let dates: Vec<Option<NaiveDate>> = history
+        .column("As of")
+        .and_then(|dates| dates.utf8())
+        .map_err(|e| investments_forecasting::Error::parse(what, e))?
+        .into_iter()
+        .map(|date| date.and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()))
+        .collect();
+    let first = dates
+        .iter()
+        .flatten()
+        .min()
+        .copied()
+        .unwrap_or(NaiveDate::MIN);
+
+    let colors: Vec<&str> = vec!["blue", "green", "navy", "web-green", "#127cc1", "#76B900"];
+    let mut fg = Figure::new();
+    fg.set_terminal("pngcairo size 1280,960", file);
+    let axes = fg
+        .axes2d()
+        .set_title(
+            &format!("{} history", metric.name()),
+            &[gnuplot::LabelOption::<&str>::Font("Arial", 15.0)],
+        )
+        .set_x_label(
+            &format!("time[days since {first}]"),
+            &[gnuplot::LabelOption::<&str>::Font("Arial", 12.0)],
+        )
+        .set_y_label(
+            metric.name(),
+            &[gnuplot::LabelOption::<&str>::Font("Arial", 12.0)],
+        );
+
+    for (i, column) in history.get_columns().iter().skip(1).enumerate() {
+        let values = column
+            .f64()
+            .map_err(|e| investments_forecasting::Error::parse(what, e))?;
+        let (days, values): (Vec<i64>, Vec<f64>) = dates
+            .iter()
+            .zip(values.into_iter())
+            .filter_map(|(date, value)| Some((((*date)? - first).num_days(), value?)))
+            .unzip();
+        axes.lines_points(
+            &days,
+            &values,
+            &[Caption(column.name()), Color(colors[i % colors.len()])],
+        );
+    }
+    fg.show().map_err(|e| {
+        investments_forecasting::Error::Config(format!("Unable to plot {file}: {e:?}"))
+    })?;
+    println!("{} history was plotted into {file}", metric.name());
     Ok(())
 }
 
-fn main() -> Result<(), &'static str> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     investments_forecasting::init_logging_infrastructure();
 
     configure_dataframes_format();
 
-    let args = Args::parse();
+    let mut args = Args::parse();
+    // Yield selected twice would make duplicate columns
+    let mut yields: Vec<YieldKind> = vec![];
+    args.yields.iter().for_each(|kind| {
+        if !yields.contains(kind) {
+            yields.push(*kind)
+        }
+    });
+    args.yields = yields;
+
+    if let Some(Command::History {
+        company,
+        metric,
+        last,
+        plot,
+    }) = &args.command
+    {
+        let database = args
+            .database
+            .as_deref()
+            .ok_or("Error: \"history\" requires \"database\" argument")?;
+        let mut companies: Vec<String> = company.iter().map(|x| x.to_uppercase()).collect();
+        companies.dedup();
+        show_history(database, &companies, *metric, *last, plot.as_deref())?;
+        return Ok(());
+    }
+    let as_of = args.as_of.unwrap_or_else(|| Utc::now().date_naive());
+    let options = AnalysisOptions {
+        basis: args.basis,
+        include_special: args.include_special,
+        dgr_estimator: args.dgr_estimator,
+        yield_kind: args.yields.first().copied().unwrap_or_default(),
+    };
+    let cache = (!args.no_cache).then(|| {
+        let dir = args
+            .cache_dir
+            .map(PathBuf::from)
+            .unwrap_or_else(Cache::default_dir);
+        Cache::new(&dir, args.refresh)
+    });
+
+    // Companies fetched from provider are screened by payout ratio only on request
+    let payout_filter = args
+        .payout_basis
+        .map(|basis| (basis, args.max_div_payout_rate));
+
+    let provider_kind = match args.provider {
+        Some(kind) => kind,
+        None if args.data.is_some() => ProviderKind::File,
+        None if args.local_dir.is_some() => ProviderKind::Local,
+        None => ProviderKind::from_env(),
+    };
+
+    let provider_options = ProviderOptions {
+        kind: provider_kind,
+        record_dir: args.record.map(PathBuf::from),
+        replay_dir: args.replay.map(PathBuf::from),
+        cache: cache.clone(),
+        rate_limiter: args
+            .requests_per_minute
+            .map(|requests| Arc::new(RateLimiter::per_minute(requests))),
+        retry: RetryPolicy {
+            max_attempts: args.max_attempts,
+            ..Default::default()
+        },
+        local_dir: args.local_dir.map(PathBuf::from),
+    };
+
+    // Companies are reported in their own currencies unless conversion is requested
+    let currency = args.currency.map(|c| c.to_uppercase());
+    let fx = match &currency {
+        Some(_) => {
+            let fx = FxRates::from_options(&provider_options)?;
+            Some(match &args.fx_overrides {
+                Some(path) => fx.with_overrides(Path::new(path))?,
+                None => fx,
+            })
+        }
+        None => None,
+    };
 
-    let data = if let Some(data_file) = args.data {
-        let mut excel: Xlsx<_> = open_workbook(data_file).map_err(|_| "Error: opening XLSX")?;
-        // Champions
-        let data = investments_forecasting::load_list(&mut excel, &args.list)?;
-        Some(data)
+    let data = if provider_kind == ProviderKind::File {
+        let data_file = args
+            .data
+            .ok_or("Error: \"file\" provider requires \"data\" argument")?;
+        let data = list::load_list_file(
+            Path::new(&data_file),
+            args.list.as_deref().unwrap_or("Champions"),
+        )?;
+        Some(check_completeness(
+            data,
+            args.drop_incomplete,
+            &args.require_columns,
+        )?)
     } else {
         None
     };
@@ -531,7 +868,8 @@ fn main() -> Result<(), &'static str> {
             match data {
                 Some(database) => print_summary(&database, None)?,
                 None => {
-                    let companies = investments_forecasting::get_polygon_companies_list()?;
+                    let companies =
+                        investments_forecasting::get_polygon_companies_list(&provider_options)?;
 
                     let mut symbols: Vec<String> = vec![];
                     let mut names: Vec<Option<String>> = vec![];
@@ -557,11 +895,15 @@ fn main() -> Result<(), &'static str> {
                         args.min_div_yield,
                         args.max_div_yield,
                     )?;
-                    log::info!("Champions Shortlisted by DivY: {}", data_shortlisted_dy);
+                    log::info!(
+                        "Champions Shortlisted by forward DivY: {}",
+                        data_shortlisted_dy
+                    );
 
                     let data_shortlisted_dy_dp = analyze_dividend_payout_rate(
                         &data_shortlisted_dy,
                         args.max_div_payout_rate / 100.0,
+                        args.payout_basis.unwrap_or_default(),
                     )?;
 
                     log::info!(
@@ -575,14 +917,31 @@ fn main() -> Result<(), &'static str> {
                     print_summary(&data_shortlisted_dy_dp_dg, None)?;
                 }
                 None => {
-                    let companies = investments_forecasting::get_polygon_companies_list()?;
+                    let companies =
+                        investments_forecasting::get_polygon_companies_list(&provider_options)?;
 
                     let mut symbols: Vec<String> = vec![];
 
                     companies.into_iter().for_each(|(s, _)| {
                         symbols.push(s);
                     });
-                    get_companies_data(&symbols, args.database, args.target_yield)?;
+                    let provider = provider_options.build()?;
+                    let screening = Screening {
+                        target_yield: args.target_yield,
+                        yields: &args.yields,
+                        payout_filter,
+                        list: args.list.as_deref().and_then(DividendList::from_name),
+                    };
+                    get_companies_data(
+                        &symbols,
+                        args.database,
+                        provider.as_ref(),
+                        fx.as_ref().zip(currency.as_deref()),
+                        as_of,
+                        &screening,
+                        args.concurrency,
+                        &options,
+                    )?;
                 }
             }
         }
@@ -598,7 +957,8 @@ fn main() -> Result<(), &'static str> {
                 // and start execution from company being a value of argument "company"
                 let companies = if args.cont {
                     let company_to_start = companies[0].clone();
-                    let companies = investments_forecasting::get_polygon_companies_list()?;
+                    let companies =
+                        investments_forecasting::get_polygon_companies_list(&provider_options)?;
                     let companies: Vec<String> = companies.iter().map(|(s, _)| s.clone()).collect();
                     let company_to_start_index = companies
                         .iter()
@@ -615,10 +975,29 @@ fn main() -> Result<(), &'static str> {
                 } else {
                     companies
                 };
-                get_companies_data(&companies, args.database, args.target_yield)?;
+                let provider = provider_options.build()?;
+                let screening = Screening {
+                    target_yield: args.target_yield,
+                    yields: &args.yields,
+                    payout_filter,
+                    list: None,
+                };
+                get_companies_data(
+                    &companies,
+                    args.database,
+                    provider.as_ref(),
+                    fx.as_ref().zip(currency.as_deref()),
+                    as_of,
+                    &screening,
+                    args.concurrency,
+                    &options,
+                )?;
             }
         }
     }
+    if let Some(cache) = &cache {
+        println!("{}", cache.stats());
+    }
     Ok(())
 }
 
@@ -711,12 +1090,42 @@ mod tests {
         let ref_df: DataFrame = DataFrame::new(vec![s1, s2, s3, s4]).unwrap();
         //print!("Ref DF: {ref_df}");
 
-        let result = analyze_dividend_payout_rate(&df, max_payout_rate).unwrap();
+        let result =
+            analyze_dividend_payout_rate(&df, max_payout_rate, PayoutBasis::OperatingCashFlow)
+                .unwrap();
         //print!("result DF: {result}");
         assert!(result.frame_equal(&ref_df));
         Ok(())
     }
 
+    #[test]
+    fn test_analyze_divy_dpy_earnings() -> Result<(), String> {
+        let max_payout_rate = 0.75;
+
+        let s1 = Series::new("Symbol", &["ABM", "INTC"]);
+        let s2 = Series::new("Div Yield", &[5.4, 2.5]);
+        let s3 = Series::new("Annualized", &[2.16, 0.5]);
+        let s4 = Series::new("P/E", &[20.0, 10.0]);
+        let s5 = Series::new("Price", &[40.0, 20.0]);
+        let df: DataFrame = DataFrame::new(vec![s1, s2, s3, s4, s5]).unwrap();
+
+        // ABM pays out 108% of its earnings
+        let result =
+            analyze_dividend_payout_rate(&df, max_payout_rate, PayoutBasis::Earnings).unwrap();
+        assert_eq!(result.height(), 1);
+        assert!(result
+            .column("Symbol")
+            .map_err(|e| e.to_string())?
+            .equal("INTC")
+            .map_err(|e| e.to_string())?
+            .all());
+
+        assert!(
+            analyze_dividend_payout_rate(&df, max_payout_rate, PayoutBasis::FreeCashFlow).is_err()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_target_price_and_distance() -> Result<(), String> {
         let share_price = 100.0;
@@ -1,7 +1,12 @@
-use calamine::{open_workbook, Xlsx};
+use chrono::prelude::*;
 use clap::Parser;
 use gnuplot::{AxesCommon, Caption, Color, Coordinate, Figure};
+use investments_forecasting::cache::Cache;
+use investments_forecasting::fx::FxRates;
+use investments_forecasting::provider::{DividendDataProvider, ProviderKind, ProviderOptions};
+use investments_forecasting::{AnalysisOptions, DgrEstimator, DividendBasis};
 use polars::prelude::*;
+use std::path::{Path, PathBuf};
 
 // TODO: for given company make both reinvesting and no-reinvesting scenarios
 
@@ -13,10 +18,60 @@ struct Args {
     #[arg(long, default_value = "dividend-investment-gains.png")]
     output: String,
 
-    /// Data in XLSX format (Fetch from https://moneyzine.com/investments/dividend-champions/)
+    /// Dividend list in XLSX, ODS or CSV format
+    /// (Fetch from https://moneyzine.com/investments/dividend-champions/)
     #[arg(long)]
     data: Option<String>,
 
+    /// Source of companies data. When not given then "file" is used if "data" is provided,
+    /// otherwise "polygon" if POLYGON_AUTH_KEY is set or "yahoo"
+    #[arg(long, value_enum)]
+    provider: Option<ProviderKind>,
+
+    /// Store responses of provider under given directory (one subdirectory per provider and
+    /// ticker)
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Serve responses stored earlier with "record" instead of asking provider
+    #[arg(long)]
+    replay: Option<String>,
+
+    /// Directory of price and dividend CSV/JSON files (one subdirectory per ticker) served by
+    /// "local" provider, which is used when this argument is given
+    #[arg(long, conflicts_with = "replay")]
+    local_dir: Option<String>,
+
+    /// Directory of on-disk cache of provider responses
+    /// (default: $XDG_CACHE_HOME/investments-forecasting or ~/.cache/investments-forecasting)
+    #[arg(long)]
+    cache_dir: Option<String>,
+
+    /// Do not use on-disk cache of provider responses
+    #[arg(long, default_value_t = false)]
+    no_cache: bool,
+
+    /// Fetch all data from provider again, replacing cached responses
+    #[arg(long, default_value_t = false, conflicts_with = "no_cache")]
+    refresh: bool,
+
+    /// Date (YYYY-MM-DD) the analysis is made as of. Dividends, prices and financial reports
+    /// published after it are ignored (default: today)
+    #[arg(long)]
+    as_of: Option<NaiveDate>,
+
+    /// Date dividends are attributed to when grouping them into years
+    #[arg(long, value_enum, default_value_t = DividendBasis::ExDate)]
+    basis: DividendBasis,
+
+    /// Include special, supplemental and irregular dividends in dividend metrics
+    #[arg(long, default_value_t = false)]
+    include_special: bool,
+
+    /// Method of computing dividend growth rates
+    #[arg(long, value_enum, default_value_t = DgrEstimator::Average)]
+    dgr_estimator: DgrEstimator,
+
     /// Symbol names of companies from dividend list as provided with "data" argument
     #[arg(long, default_values_t = &[] )]
     company: Vec<String>,
@@ -41,6 +96,16 @@ struct Args {
     #[arg(long, default_value_t = 10000.0)]
     capital: f64,
 
+    /// Currency of capital. Share prices and dividends of companies fetched from provider are
+    /// converted into it
+    #[arg(long, default_value = "USD")]
+    currency: String,
+
+    /// CSV file of exchange rates (columns base, quote, rate and optional date) used instead
+    /// of quoted ones
+    #[arg(long)]
+    fx_overrides: Option<String>,
+
     /// An Average shares price annual growth rate[%]
     #[arg(long, default_value_t = 7.4)]
     share_price_growth_rate: f64,
@@ -241,7 +306,12 @@ fn forecast_low_risk_instruments(base_capital: f64) {
 fn forecast_dividend_stocks(
     output_file_name: &str,
     base_capital: f64,
-    data: Option<String>,
+    data: Option<&DataFrame>,
+    provider: Option<&dyn DividendDataProvider>,
+    fx: Option<&FxRates>,
+    currency: &str,
+    as_of: NaiveDate,
+    options: &AnalysisOptions,
     companies: Vec<Target>,
     investment_years: u32,
     shares_price_growth_rate: f64,
@@ -309,47 +379,60 @@ fn forecast_dividend_stocks(
                 let company = Series::new("", vec![name_str]);
 
 
-                let (share_price, dy, dyg) = match data.clone() {
-                    Some(database) => {
-
-                        let mut excel: Xlsx<_> = open_workbook(database)
-                            .map_err(|_| "Error: opening XLSX")
-                            .expect("Could not open Dividends data file");
-                        let all = investments_forecasting::load_list(&mut excel, "All").expect("Unable to load Data");
-
-                        let mask = all.column("Symbol").unwrap().equal(&company).unwrap();
-                        let company_data = all.filter(&mask).expect("Unable to filter loaded data");
-
-                        let price_series = company_data.column("Price").unwrap();
-                        let price = price_series.get(0).expect("Unable to get Price of selected company");
-
-                        let dy_series = company_data.column("Div Yield").unwrap();
-                        let dy = dy_series.get(0).expect("Unable to get Div Yield of selected company");
-
-                        let dyg_series = company_data.column("DGR 5Y").unwrap();
-                        let dyg = dyg_series.get(0).expect("Unable to get DGR 5Y of selected company");
+                let (share_price, dy, dyg, dgr_label) = match data {
+                    Some(all) => {
+                        let company_data = match all.column("Symbol").and_then(|symbols| symbols.equal(&company)).and_then(|mask| all.filter(&mask)) {
+                            Ok(company_data) => company_data,
+                            Err(e) => {
+                                eprintln!("Skipping forecast of {name}: {}", investments_forecasting::Error::parse("loaded data", e).with_ticker(&name));
+                                return;
+                            }
+                        };
+                        // Value of given column, none if company is not on the list or the value is null
+                        let value = |column: &str| match company_data.column(column).ok()?.get(0).ok()? {
+                            AnyValue::Float64(value) => Some(value),
+                            _ => None,
+                        };
 
                         // Dividend list has percentages of values so we need to convert them from e.g. 1%
                         // to 0.01 etc.
-                        let (price,dy,dyg) = match (price,dy,dyg) {
-                            (AnyValue::Float64(valp),AnyValue::Float64(vald),AnyValue::Float64(valg)) => (valp,vald/100.0,valg/100.0),
-                            _ => panic!("Unable to get price value"),
-                        };
-                        (price, dy, dyg)
+                        match (value("Price"), value("Div Yield"), value("DGR 5Y")) {
+                            (Some(price), Some(dy), Some(dyg)) => (price, dy / 100.0, dyg / 100.0, "DGR 5Y".to_owned()),
+                            _ => {
+                                eprintln!("Skipping forecast of {name}: {}", investments_forecasting::Error::missing("Price, Div Yield or DGR 5Y").with_ticker(&name));
+                                return;
+                            }
+                        }
                     }
                     None => {
-                        let (share_price, _, divy, frequency,  dgr, _,_,_,_,_, _, _) =
-                        if std::env::var("POLYGON_AUTH_KEY").is_ok() { 
-                            investments_forecasting::get_polygon_data(&name).expect("Error: unable to get Data from polygon IO for forecasting")
-                        } else {
-                            let provider = investments_forecasting::get_yahoo_connector().expect("Unable to create yahoo connector");
-                            investments_forecasting::get_yahoo_data(&name,&mut provider.unwrap()).expect("Error: unable to get Data from yahoo finance for forecasting")
+                        let provider = provider.expect("Error: no provider of companies data");
+                        let mut profile = match investments_forecasting::get_dividend_profile(provider, &name, as_of, options) {
+                            Ok(profile) => profile,
+                            Err(e) => {
+                                eprintln!("Skipping forecast of {name}: {e}");
+                                return;
+                            }
                         };
-                        num_capitalizations = frequency.expect("Cannot forecast dividend gains as there is no dividend data") as u32;
-                        let divy = divy.expect("Cannot forecast dividend gains as there is no dividend data");
-                        let dgr = dgr.expect("Cannot forecast dividend gains as there is no dividend data");
-                        log::info!("Forcasting stock: {name} with params: share price({share_price}), Frequency(frequency), Div yield[%]({divy}), DGR5Y[%]({dgr})");
-                        (share_price, divy/100.0, dgr/100.0)
+                        // Number of shares bought depends on share price in currency of capital
+                        if let Some(Err(e)) = fx.map(|fx| fx.convert_profile(&mut profile, currency)) {
+                            eprintln!("Skipping forecast of {name}: {e}");
+                            return;
+                        }
+                        let share_price = profile.share_price;
+                        let (frequency, divy, dgr) = match (profile.frequency, profile.div_yield, profile.dgr_10y) {
+                            (Some(frequency), Some(divy), Some(dgr)) => (frequency, divy, dgr),
+                            _ => {
+                                eprintln!("Skipping forecast of {name}: {}", investments_forecasting::Error::missing("dividend data").with_ticker(&name).with_provider(profile.source));
+                                return;
+                            }
+                        };
+                        num_capitalizations = frequency as u32;
+                        if !profile.payment_frequency.map_or(false, |f| f.confident) {
+                            log::warn!("Payments of {name} do not follow a consistent schedule, assuming {frequency} payments per year");
+                        }
+                        let dgr_label = format!("DGR 10Y {}", options.dgr_estimator.name());
+                        log::info!("Forcasting stock: {name} with params: share price({share_price}), Frequency({frequency}), Div yield[%]({divy}), {dgr_label}[%]({dgr})");
+                        (share_price, divy/100.0, dgr/100.0, dgr_label)
                     },
                 };
 
@@ -370,7 +453,7 @@ fn forecast_dividend_stocks(
                             max_y = *x;
                         }
                         format!(
-                        "{name}(DIVY[%]: {:.2}, DYG 5G[%]: {:.2}, Price[$]: {:.2})\n (Stock[$]: {:.2}, Payout[$]: {:.2} ,Final DIVY[%]: {:.2}, Total Payout[$]: {:.2} )",dy*100.0,dyg*100.0,share_price, capital, final_payout,num_capitalizations as f64*(final_payout/base_capital)*100.0,x
+                        "{name}(DIVY[%]: {:.2}, {dgr_label}[%]: {:.2}, Price[$]: {:.2})\n (Stock[$]: {:.2}, Payout[$]: {:.2} ,Final DIVY[%]: {:.2}, Total Payout[$]: {:.2} )",dy*100.0,dyg*100.0,share_price, capital, final_payout,num_capitalizations as f64*(final_payout/base_capital)*100.0,x
                     )},
                     None => panic!("Error: No dividend data to plot!"),
                 };
@@ -453,6 +536,57 @@ fn main() {
 
     investments_forecasting::init_logging_infrastructure();
     let args = Args::parse();
+    let as_of = args.as_of.unwrap_or_else(|| Utc::now().date_naive());
+    let options = AnalysisOptions {
+        basis: args.basis,
+        include_special: args.include_special,
+        dgr_estimator: args.dgr_estimator,
+        ..Default::default()
+    };
+    let cache = (!args.no_cache).then(|| {
+        let dir = args
+            .cache_dir
+            .map(PathBuf::from)
+            .unwrap_or_else(Cache::default_dir);
+        Cache::new(&dir, args.refresh)
+    });
+
+    let provider_kind = match args.provider {
+        Some(kind) => kind,
+        None if args.data.is_some() => ProviderKind::File,
+        None if args.local_dir.is_some() => ProviderKind::Local,
+        None => ProviderKind::from_env(),
+    };
+    let (data, provider, fx) = if provider_kind == ProviderKind::File {
+        let data_file = args
+            .data
+            .unwrap_or_else(|| panic!("\nError: \"file\" provider requires \"data\" argument"));
+        // All companies are loaded once and shared by forecasts of all of them
+        let data = investments_forecasting::list::load_list_file(Path::new(&data_file), "All")
+            .unwrap_or_else(|e| panic!("\nError: {e}"));
+        (Some(data), None, None)
+    } else {
+        let provider_options = ProviderOptions {
+            kind: provider_kind,
+            record_dir: args.record.map(PathBuf::from),
+            replay_dir: args.replay.map(PathBuf::from),
+            cache: cache.clone(),
+            rate_limiter: None,
+            retry: Default::default(),
+            local_dir: args.local_dir.map(PathBuf::from),
+        };
+        let provider = provider_options
+            .build()
+            .unwrap_or_else(|e| panic!("\nError: {e}"));
+        let mut fx =
+            FxRates::from_options(&provider_options).unwrap_or_else(|e| panic!("\nError: {e}"));
+        if let Some(path) = &args.fx_overrides {
+            fx = fx
+                .with_overrides(Path::new(path))
+                .unwrap_or_else(|e| panic!("\nError: {e}"));
+        }
+        (None, Some(provider), Some(fx))
+    };
 
     forecast_low_risk_instruments(args.capital);
 
@@ -472,7 +606,12 @@ fn main() {
                 forecast_dividend_stocks(
                     args.output.as_ref(),
                     args.capital,
-                    args.data,
+                    data.as_ref(),
+                    provider.as_deref(),
+                    fx.as_ref(),
+                    &args.currency,
+                    as_of,
+                    &options,
                     targets,
                     args.years,
                     args.share_price_growth_rate,
@@ -485,13 +624,22 @@ fn main() {
         forecast_dividend_stocks(
             args.output.as_ref(),
             args.capital,
-            args.data,
+            data.as_ref(),
+            provider.as_deref(),
+            fx.as_ref(),
+            &args.currency,
+            as_of,
+            &options,
             targets,
             args.years,
             args.share_price_growth_rate,
             args.tax_rate,
         );
     }
+
+    if let Some(cache) = &cache {
+        println!("{}", cache.stats());
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,738 @@
+//! Dividend payments as reported by providers
+
+use chrono::prelude::*;
+use chrono::Months;
+use std::collections::BTreeMap;
+
+/// Type of dividend payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DividendKind {
+    /// Payment made according to company's dividend policy e.g. quarterly
+    Regular,
+    /// One-off payment
+    Special,
+    /// Payment declared in addition to regular one e.g. from excess earnings
+    Supplemental,
+    /// Payment not following any schedule e.g. capital gains distribution
+    Irregular,
+}
+
+impl DividendKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DividendKind::Regular => "regular",
+            DividendKind::Special => "special",
+            DividendKind::Supplemental => "supplemental",
+            DividendKind::Irregular => "irregular",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "regular" => Some(DividendKind::Regular),
+            "special" => Some(DividendKind::Special),
+            "supplemental" => Some(DividendKind::Supplemental),
+            "irregular" => Some(DividendKind::Irregular),
+            _ => None,
+        }
+    }
+}
+
+/// Schedule of regular dividend payments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentFrequency {
+    Monthly,
+    Quarterly,
+    SemiAnnual,
+    Annual,
+    /// Payments not following any of above schedules
+    Irregular,
+}
+
+impl PaymentFrequency {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaymentFrequency::Monthly => "monthly",
+            PaymentFrequency::Quarterly => "quarterly",
+            PaymentFrequency::SemiAnnual => "semi-annual",
+            PaymentFrequency::Annual => "annual",
+            PaymentFrequency::Irregular => "irregular",
+        }
+    }
+
+    pub fn payments_per_year(&self) -> Option<u32> {
+        match self {
+            PaymentFrequency::Monthly => Some(12),
+            PaymentFrequency::Quarterly => Some(4),
+            PaymentFrequency::SemiAnnual => Some(2),
+            PaymentFrequency::Annual => Some(1),
+            PaymentFrequency::Irregular => None,
+        }
+    }
+
+    fn from_payments_per_year(payments: u32) -> Self {
+        match payments {
+            12 => PaymentFrequency::Monthly,
+            4 => PaymentFrequency::Quarterly,
+            2 => PaymentFrequency::SemiAnnual,
+            1 => PaymentFrequency::Annual,
+            _ => PaymentFrequency::Irregular,
+        }
+    }
+
+    /// Schedule given number of days between payments fits into
+    fn from_gap(days: f64) -> Self {
+        match days {
+            d if (20.0..=45.0).contains(&d) => PaymentFrequency::Monthly,
+            d if (70.0..=110.0).contains(&d) => PaymentFrequency::Quarterly,
+            d if (150.0..=215.0).contains(&d) => PaymentFrequency::SemiAnnual,
+            d if (330.0..=400.0).contains(&d) => PaymentFrequency::Annual,
+            _ => PaymentFrequency::Irregular,
+        }
+    }
+}
+
+/// Payment frequency inferred from spacing of recent regular payments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrequencyEstimate {
+    pub frequency: PaymentFrequency,
+    /// Whether recent payments follow the schedule consistently. Frequency declared by
+    /// provider is used when they do not
+    pub confident: bool,
+}
+
+/// Date a dividend is attributed to when grouping dividends into years. Providers report
+/// different dates (Yahoo only ex-dividend date), so the same basis is applied to all of them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DividendBasis {
+    /// Ex-dividend date
+    #[default]
+    ExDate,
+    /// Payment date, or ex-dividend date if provider does not report it
+    PayDate,
+    /// Fiscal year of company the dividend was declared in. Declaration date falls back to
+    /// ex-dividend date
+    DeclaredFiscalYear,
+}
+
+/// Definition of dividend yield. Yields are computed from dividend history and share price
+/// the same way for all providers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum YieldKind {
+    /// Forward annualized dividend: latest regular payment times number of payments per year
+    #[default]
+    Forward,
+    /// Dividends that went ex-dividend within twelve months before as-of date
+    Ttm,
+    /// Dividends of the most recent complete year. It is a fiscal year with
+    /// declared-fiscal-year basis and a calendar year otherwise
+    LastFy,
+}
+
+impl YieldKind {
+    /// Name shown in column headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            YieldKind::Forward => "forward",
+            YieldKind::Ttm => "TTM",
+            YieldKind::LastFy => "last FY",
+        }
+    }
+}
+
+/// How dividends of a company are assigned to years
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attribution {
+    pub basis: DividendBasis,
+    /// Last month (1-12) of company's fiscal year. Fiscal year is named after the calendar
+    /// year it ends in
+    pub fiscal_year_end: u32,
+}
+
+impl Default for Attribution {
+    fn default() -> Self {
+        Self {
+            basis: DividendBasis::ExDate,
+            fiscal_year_end: 12,
+        }
+    }
+}
+
+impl Attribution {
+    pub fn new(basis: DividendBasis) -> Self {
+        Self {
+            basis,
+            ..Default::default()
+        }
+    }
+
+    /// Date of given dividend according to chosen basis
+    pub fn date(&self, event: &DividendEvent) -> NaiveDate {
+        match self.basis {
+            DividendBasis::ExDate => event.ex_date,
+            DividendBasis::PayDate => event.pay_date.unwrap_or(event.ex_date),
+            DividendBasis::DeclaredFiscalYear => event.declaration_date.unwrap_or(event.ex_date),
+        }
+    }
+
+    /// Year given dividend counts towards
+    pub fn year(&self, event: &DividendEvent) -> i32 {
+        let date = self.date(event);
+        match self.basis {
+            DividendBasis::DeclaredFiscalYear if date.month() > self.fiscal_year_end => {
+                date.year() + 1
+            }
+            _ => date.year(),
+        }
+    }
+}
+
+/// Single dividend payment. Providers fill in only dates they know of, except ex-dividend
+/// date which is always present
+#[derive(Debug, Clone, PartialEq)]
+pub struct DividendEvent {
+    pub ex_date: NaiveDate,
+    pub pay_date: Option<NaiveDate>,
+    pub record_date: Option<NaiveDate>,
+    pub declaration_date: Option<NaiveDate>,
+    /// Cash amount per share
+    pub amount: f64,
+    /// Currency of amount e.g. "USD"
+    pub currency: Option<String>,
+    /// Type of payment if reported by provider or already classified (see
+    /// `DividendSeries::classified`)
+    pub kind: Option<DividendKind>,
+    /// Number of payments per year as declared by company
+    pub declared_frequency: Option<u32>,
+    /// Number of shares a share had become due to splits made after the payment. `amount` is
+    /// already divided by it (see `DividendSeries::split_adjusted`), 1.0 for amounts as paid
+    pub split_factor: f64,
+}
+
+/// Stock split, e.g. 2-for-1 split is 1 share split into 2
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitEvent {
+    /// First day of trading at split adjusted price
+    pub date: NaiveDate,
+    pub from: f64,
+    pub to: f64,
+}
+
+impl SplitEvent {
+    /// Number of shares after split per single share before it
+    pub fn ratio(&self) -> f64 {
+        self.to / self.from
+    }
+}
+
+/// Payment larger than this many times the typical one is considered special
+const OUTLIER_RATIO: f64 = 2.0;
+/// Payment made sooner than this fraction of typical interval after another one is off-schedule
+const OFF_SCHEDULE_RATIO: f64 = 0.5;
+/// Number of payments before and after given one its amount is compared with
+const NEIGHBOURS: usize = 3;
+/// Fewer payments than that do not make a schedule
+const MIN_SCHEDULED: usize = 3;
+/// Number of most recent intervals between payments frequency is inferred from, so that
+/// change of schedule is followed
+const RECENT_GAPS: usize = 4;
+/// Fraction of recent intervals that must fit the schedule for frequency to be confident
+const MIN_CONFIDENCE: f64 = 0.75;
+
+pub(crate) fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
+
+impl DividendEvent {
+    /// Dividend of unknown type with only ex-dividend date known
+    pub fn new(ex_date: NaiveDate, amount: f64) -> Self {
+        Self {
+            ex_date,
+            pay_date: None,
+            record_date: None,
+            declaration_date: None,
+            amount,
+            currency: None,
+            kind: None,
+            declared_frequency: None,
+            split_factor: 1.0,
+        }
+    }
+}
+
+/// Dividend payments of a company ordered by attributed date
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DividendSeries {
+    events: Vec<DividendEvent>,
+    attribution: Attribution,
+}
+
+impl DividendSeries {
+    /// Series attributing dividends by ex-dividend date
+    pub fn new(events: Vec<DividendEvent>) -> Self {
+        Self::with_attribution(events, Attribution::default())
+    }
+
+    pub fn with_attribution(mut events: Vec<DividendEvent>, attribution: Attribution) -> Self {
+        events.sort_by_key(|e| attribution.date(e));
+        Self {
+            events,
+            attribution,
+        }
+    }
+
+    /// The same payments attributed in another way
+    pub fn attributed(self, attribution: Attribution) -> Self {
+        Self::with_attribution(self.events, attribution)
+    }
+
+    pub fn attribution(&self) -> Attribution {
+        self.attribution
+    }
+
+    /// Date of given dividend according to basis of series
+    pub fn date(&self, event: &DividendEvent) -> NaiveDate {
+        self.attribution.date(event)
+    }
+
+    /// Year given dividend counts towards according to basis of series
+    pub fn year(&self, event: &DividendEvent) -> i32 {
+        self.attribution.year(event)
+    }
+
+    pub fn events(&self) -> &[DividendEvent] {
+        &self.events
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, DividendEvent> {
+        self.events.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Most recent payment
+    pub fn last(&self) -> Option<&DividendEvent> {
+        self.events.last()
+    }
+
+    /// Payments matching given predicate
+    pub fn filter(&self, predicate: impl Fn(&DividendEvent) -> bool) -> Self {
+        Self {
+            events: self
+                .events
+                .iter()
+                .filter(|e| predicate(e))
+                .cloned()
+                .collect(),
+            attribution: self.attribution,
+        }
+    }
+
+    /// Payments with type determined. Type reported by provider is kept, remaining payments
+    /// are classified by heuristics: extra payment on the same day as another one is
+    /// supplemental, payment much larger than neighbouring ones is special and payment made
+    /// off the usual schedule is irregular
+    pub fn classified(&self) -> Self {
+        let mut events = self.events.clone();
+        // Schedule is determined by ex-dividend dates whatever the basis is
+        events.sort_by_key(|e| e.ex_date);
+
+        // Payments which may belong to regular schedule
+        let scheduled: Vec<usize> = (0..events.len())
+            .filter(|&i| events[i].kind.map_or(true, |k| k == DividendKind::Regular))
+            .collect();
+        let typical_gap = median(
+            scheduled
+                .windows(2)
+                .map(|w| (events[w[1]].ex_date - events[w[0]].ex_date).num_days() as f64)
+                .filter(|days| *days > 0.0)
+                .collect(),
+        );
+
+        let mut kinds: Vec<Option<DividendKind>> = events.iter().map(|e| e.kind).collect();
+        for (pos, &i) in scheduled.iter().enumerate() {
+            if events[i].kind.is_some() {
+                continue;
+            }
+            let (typical_amount, typical_gap) = match typical_gap {
+                Some(gap) if scheduled.len() >= MIN_SCHEDULED => {
+                    let from = pos.saturating_sub(NEIGHBOURS);
+                    let to = (pos + NEIGHBOURS + 1).min(scheduled.len());
+                    let amounts = (from..to)
+                        .filter(|&p| p != pos)
+                        .map(|p| events[scheduled[p]].amount)
+                        .collect();
+                    (median(amounts).unwrap_or(events[i].amount), gap)
+                }
+                _ => {
+                    kinds[i] = Some(DividendKind::Regular);
+                    continue;
+                }
+            };
+
+            // Of two payments close to each other the one of untypical amount is the extra one
+            let deviation = |j: usize| (events[j].amount - typical_amount).abs();
+            let mut same_day = false;
+            let mut off_schedule = false;
+            let neighbours = [pos.checked_sub(1), Some(pos + 1)];
+            for p in neighbours.into_iter().flatten() {
+                let Some(&j) = scheduled.get(p) else {
+                    continue;
+                };
+                let gap = (events[i].ex_date - events[j].ex_date).num_days().abs() as f64;
+                let is_extra =
+                    deviation(i) > deviation(j) || (deviation(i) == deviation(j) && i > j);
+                if gap == 0.0 && is_extra {
+                    same_day = true;
+                } else if gap < typical_gap * OFF_SCHEDULE_RATIO && is_extra {
+                    off_schedule = true;
+                }
+            }
+
+            kinds[i] = Some(if same_day {
+                DividendKind::Supplemental
+            } else if events[i].amount > OUTLIER_RATIO * typical_amount {
+                DividendKind::Special
+            } else if off_schedule {
+                DividendKind::Irregular
+            } else {
+                DividendKind::Regular
+            });
+        }
+
+        events
+            .iter_mut()
+            .zip(kinds)
+            .for_each(|(event, kind)| event.kind = kind);
+        Self::with_attribution(events, self.attribution)
+    }
+
+    /// Payments with amounts divided by ratios of splits made after them, so that they are
+    /// comparable with current ones
+    pub fn split_adjusted(&self, splits: &[SplitEvent]) -> Self {
+        let events = self
+            .raw()
+            .events
+            .into_iter()
+            .map(|mut e| {
+                e.split_factor = splits
+                    .iter()
+                    .filter(|s| s.date > e.ex_date && s.from > 0.0 && s.to > 0.0)
+                    .map(|s| s.ratio())
+                    .product();
+                e.amount /= e.split_factor;
+                e
+            })
+            .collect();
+        Self::with_attribution(events, self.attribution)
+    }
+
+    /// Payments with amounts as paid at the time
+    pub fn raw(&self) -> Self {
+        let events = self
+            .events
+            .iter()
+            .cloned()
+            .map(|mut e| {
+                e.amount *= e.split_factor;
+                e.split_factor = 1.0;
+                e
+            })
+            .collect();
+        Self::with_attribution(events, self.attribution)
+    }
+
+    /// Frequency of regular payments inferred from intervals between their ex-dividend
+    /// dates. `None` if there are fewer than two payments
+    pub fn frequency(&self) -> Option<FrequencyEstimate> {
+        let mut dates: Vec<NaiveDate> = self.regular().iter().map(|e| e.ex_date).collect();
+        dates.sort();
+        dates.dedup();
+        let gaps: Vec<f64> = dates
+            .windows(2)
+            .map(|w| (w[1] - w[0]).num_days() as f64)
+            .collect();
+        let recent = &gaps[gaps.len().saturating_sub(RECENT_GAPS)..];
+        let detected = PaymentFrequency::from_gap(median(recent.to_vec())?);
+        let matching = recent
+            .iter()
+            .filter(|gap| PaymentFrequency::from_gap(**gap) == detected)
+            .count();
+        let confident = detected != PaymentFrequency::Irregular
+            && recent.len() >= 2
+            && matching as f64 >= recent.len() as f64 * MIN_CONFIDENCE;
+        if confident {
+            return Some(FrequencyEstimate {
+                frequency: detected,
+                confident,
+            });
+        }
+
+        let declared = self
+            .regular()
+            .iter()
+            .max_by_key(|e| e.ex_date)
+            .and_then(|e| e.declared_frequency)
+            .map(PaymentFrequency::from_payments_per_year);
+        Some(FrequencyEstimate {
+            frequency: declared.unwrap_or(detected),
+            confident: false,
+        })
+    }
+
+    /// Latest regular payment times number of payments per year
+    pub fn forward_annualized(&self) -> Option<f64> {
+        let payments = self.frequency()?.frequency.payments_per_year()?;
+        let regular = self.regular();
+        let latest = regular.iter().max_by_key(|e| e.ex_date)?;
+        Some(latest.amount * payments as f64)
+    }
+
+    /// Sum of payments that went ex-dividend within twelve months before given date
+    pub fn trailing_twelve_months(&self, as_of: NaiveDate) -> f64 {
+        let start = as_of - Months::new(12);
+        self.events
+            .iter()
+            .filter(|e| e.ex_date > start && e.ex_date <= as_of)
+            .map(|e| e.amount)
+            .sum()
+    }
+
+    /// Regular payments only. Payments of unknown type are considered regular
+    pub fn regular(&self) -> Self {
+        self.filter(|e| e.kind.map_or(true, |k| k == DividendKind::Regular))
+    }
+
+    /// Special, supplemental and irregular payments
+    pub fn non_regular(&self) -> Self {
+        self.filter(|e| e.kind.map_or(false, |k| k != DividendKind::Regular))
+    }
+
+    /// Payments that went ex-dividend not later than given date
+    pub fn until(&self, as_of: NaiveDate) -> Self {
+        self.filter(|e| e.ex_date <= as_of)
+    }
+
+    /// Currency of payments if any provider reported it
+    pub fn currency(&self) -> Option<&str> {
+        self.events.iter().find_map(|e| e.currency.as_deref())
+    }
+
+    /// Sums of payments by year, for years from the oldest payment to the one before given
+    /// year. Years without payments have zero sum
+    pub fn annual(&self, before_year: i32) -> BTreeMap<i32, f64> {
+        let mut annual_div: BTreeMap<i32, f64> = BTreeMap::new();
+        self.events
+            .iter()
+            .filter(|e| self.year(e) < before_year)
+            .for_each(|e| *annual_div.entry(self.year(e)).or_insert(0.0) += e.amount);
+        if let Some(oldest_year) = annual_div.keys().next().copied() {
+            for year in oldest_year..before_year {
+                annual_div.entry(year).or_insert(0.0);
+            }
+        }
+        annual_div
+    }
+}
+
+impl FromIterator<DividendEvent> for DividendSeries {
+    fn from_iter<I: IntoIterator<Item = DividendEvent>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a DividendSeries {
+    type Item = &'a DividendEvent;
+    type IntoIter = std::slice::Iter<'a, DividendEvent>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribution_basis() -> Result<(), String> {
+        let mut event = DividendEvent::new(NaiveDate::from_ymd_opt(2022, 12, 20).unwrap(), 0.5);
+        event.pay_date = NaiveDate::from_ymd_opt(2023, 1, 10);
+        event.declaration_date = NaiveDate::from_ymd_opt(2022, 10, 5);
+
+        assert_eq!(Attribution::new(DividendBasis::ExDate).year(&event), 2022);
+        assert_eq!(Attribution::new(DividendBasis::PayDate).year(&event), 2023);
+        // Fiscal year ending in September
+        let fiscal = Attribution {
+            basis: DividendBasis::DeclaredFiscalYear,
+            fiscal_year_end: 9,
+        };
+        assert_eq!(fiscal.year(&event), 2023);
+        // Yahoo does not report declaration date
+        event.declaration_date = None;
+        assert_eq!(fiscal.year(&event), 2023);
+        event.ex_date = NaiveDate::from_ymd_opt(2022, 9, 20).unwrap();
+        assert_eq!(fiscal.year(&event), 2022);
+        Ok(())
+    }
+
+    #[test]
+    fn test_annual_sums() -> Result<(), String> {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let div_history = DividendSeries::new(vec![
+            DividendEvent::new(date("2020-03-10"), 0.25),
+            DividendEvent::new(date("2020-09-10"), 0.25),
+            DividendEvent::new(date("2022-03-10"), 0.3),
+            DividendEvent::new(date("2024-03-10"), 0.35),
+        ]);
+        // Year without payments has zero sum, given year and later ones are left out
+        assert_eq!(
+            div_history.annual(2024),
+            BTreeMap::from([(2020, 0.5), (2021, 0.0), (2022, 0.3), (2023, 0.0)])
+        );
+        assert_eq!(DividendSeries::default().annual(2024), BTreeMap::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_adjustment() -> Result<(), String> {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let raw = DividendSeries::new(vec![
+            DividendEvent::new(date("2020-06-10"), 0.75),
+            DividendEvent::new(date("2021-06-10"), 1.0),
+            DividendEvent::new(date("2022-06-10"), 0.55),
+            DividendEvent::new(date("2023-06-10"), 0.6),
+        ]);
+        let splits = [
+            SplitEvent {
+                date: date("2020-12-01"),
+                from: 2.0,
+                to: 3.0,
+            },
+            SplitEvent {
+                date: date("2022-01-03"),
+                from: 1.0,
+                to: 2.0,
+            },
+        ];
+
+        let adjusted = raw.split_adjusted(&splits);
+        let amounts: Vec<f64> = adjusted.iter().map(|e| e.amount).collect();
+        assert_eq!(amounts, vec![0.25, 0.5, 0.55, 0.6]);
+        assert_eq!(adjusted.raw(), raw);
+        // Adjusting again does not adjust twice
+        assert_eq!(adjusted.split_adjusted(&splits), adjusted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_frequency_detection() -> Result<(), String> {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        // Semi-annual payments changed into quarterly ones
+        let mut events: Vec<DividendEvent> = ["2020-03-10", "2020-09-10", "2021-03-10"]
+            .iter()
+            .map(|d| DividendEvent::new(date(d), 0.5))
+            .collect();
+        for d in [
+            "2021-06-10",
+            "2021-09-10",
+            "2021-12-10",
+            "2022-03-10",
+            "2022-06-10",
+        ] {
+            events.push(DividendEvent::new(date(d), 0.3));
+        }
+        let div_history = DividendSeries::new(events.clone());
+        assert_eq!(
+            div_history.frequency(),
+            Some(FrequencyEstimate {
+                frequency: PaymentFrequency::Quarterly,
+                confident: true,
+            })
+        );
+        assert_eq!(div_history.forward_annualized(), Some(1.2));
+
+        // Special payment does not count as part of schedule
+        let mut special = DividendEvent::new(date("2022-07-20"), 2.0);
+        special.kind = Some(DividendKind::Special);
+        events.push(special);
+        assert_eq!(DividendSeries::new(events).forward_annualized(), Some(1.2));
+
+        // Provider declared frequency is used when spacing is not conclusive
+        let mut events: Vec<DividendEvent> = ["2021-01-15", "2021-05-20", "2021-06-30"]
+            .iter()
+            .map(|d| DividendEvent::new(date(d), 0.1))
+            .collect();
+        let div_history = DividendSeries::new(events.clone());
+        assert_eq!(div_history.frequency().map(|f| f.confident), Some(false));
+        assert_eq!(div_history.forward_annualized(), Some(0.4));
+        events
+            .iter_mut()
+            .for_each(|e| e.declared_frequency = Some(12));
+        assert_eq!(
+            DividendSeries::new(events).frequency(),
+            Some(FrequencyEstimate {
+                frequency: PaymentFrequency::Monthly,
+                confident: false,
+            })
+        );
+        assert_eq!(DividendSeries::new(vec![]).frequency(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_classification() -> Result<(), String> {
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let mut reported = DividendEvent::new(date("2021-12-28"), 0.1);
+        reported.kind = Some(DividendKind::Irregular);
+        let div_history = DividendSeries::new(vec![
+            DividendEvent::new(date("2021-03-10"), 0.5),
+            DividendEvent::new(date("2021-06-10"), 0.5),
+            DividendEvent::new(date("2021-06-10"), 0.15),
+            DividendEvent::new(date("2021-09-10"), 0.5),
+            DividendEvent::new(date("2021-12-10"), 0.55),
+            reported,
+            DividendEvent::new(date("2022-01-20"), 0.6),
+            DividendEvent::new(date("2022-03-10"), 0.55),
+            DividendEvent::new(date("2022-06-10"), 2.0),
+            DividendEvent::new(date("2022-09-10"), 0.55),
+        ])
+        .classified();
+
+        let kinds: Vec<DividendKind> = div_history.iter().filter_map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DividendKind::Regular,
+                DividendKind::Regular,
+                DividendKind::Supplemental,
+                DividendKind::Regular,
+                DividendKind::Regular,
+                // Type reported by provider is kept
+                DividendKind::Irregular,
+                DividendKind::Irregular,
+                DividendKind::Regular,
+                DividendKind::Special,
+                DividendKind::Regular,
+            ]
+        );
+        assert_eq!(div_history.regular().len(), 6);
+        assert_eq!(div_history.non_regular().len(), 4);
+        Ok(())
+    }
+}
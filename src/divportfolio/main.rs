@@ -1,9 +1,72 @@
+use chrono::prelude::*;
 use clap::Parser;
+use indicatif::ProgressBar;
+use investments_forecasting::cache::Cache;
+use investments_forecasting::fx::FxRates;
+use investments_forecasting::provider::{DividendDataProvider, ProviderKind, ProviderOptions};
+use investments_forecasting::{AnalysisOptions, Attribution, DividendBasis, Error, SplitEvent};
 use polars::prelude::*;
 use std::collections::BTreeMap;
-use time::OffsetDateTime;
-use yahoo_finance_api as yahoo;
-use indicatif::ProgressBar;
+use std::path::{Path, PathBuf};
+
+/// Program to summarize dividend portfolio
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Source of companies data. When not given then "polygon" is used if POLYGON_AUTH_KEY is
+    /// set, otherwise "yahoo"
+    #[arg(long, value_enum)]
+    provider: Option<ProviderKind>,
+
+    /// Store responses of provider under given directory (one subdirectory per provider and
+    /// ticker)
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Serve responses stored earlier with "record" instead of asking provider
+    #[arg(long)]
+    replay: Option<String>,
+
+    /// Directory of price and dividend CSV/JSON files (one subdirectory per ticker) served by
+    /// "local" provider, which is used when this argument is given
+    #[arg(long, conflicts_with = "replay")]
+    local_dir: Option<String>,
+
+    /// Directory of on-disk cache of provider responses
+    /// (default: $XDG_CACHE_HOME/investments-forecasting or ~/.cache/investments-forecasting)
+    #[arg(long)]
+    cache_dir: Option<String>,
+
+    /// Do not use on-disk cache of provider responses
+    #[arg(long, default_value_t = false)]
+    no_cache: bool,
+
+    /// Fetch all data from provider again, replacing cached responses
+    #[arg(long, default_value_t = false, conflicts_with = "no_cache")]
+    refresh: bool,
+
+    /// Date (YYYY-MM-DD) the analysis is made as of. Dividends, prices and financial reports
+    /// published after it are ignored (default: today)
+    #[arg(long)]
+    as_of: Option<NaiveDate>,
+
+    /// Date dividends are attributed to when grouping them into years
+    #[arg(long, value_enum, default_value_t = DividendBasis::ExDate)]
+    basis: DividendBasis,
+
+    /// Include special, supplemental and irregular dividends in dividend metrics
+    #[arg(long, default_value_t = false)]
+    include_special: bool,
+
+    /// Currency dividends of all companies are summarized in
+    #[arg(long, default_value = "PLN")]
+    currency: String,
+
+    /// CSV file of exchange rates (columns base, quote, rate and optional date) used instead
+    /// of quoted ones
+    #[arg(long)]
+    fx_overrides: Option<String>,
+}
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum Exchange {
@@ -43,6 +106,14 @@ impl Currency {
         }
     }
 
+    fn code(&self) -> &'static str {
+        match self {
+            Currency::EUR(_) => "EUR",
+            Currency::PLN(_) => "PLN",
+            Currency::USD(_) => "USD",
+        }
+    }
+
     fn print(&self) -> String {
         match self {
             Currency::EUR(val) => format!("{:.2} EUR", val),
@@ -52,150 +123,110 @@ impl Currency {
     }
 }
 
-fn print_monthly_dividends_distribution(stocks: &[Stock]) {
+fn print_monthly_dividends_distribution(
+    stocks: &[Stock],
+    fx: &FxRates,
+    currency: &str,
+    as_of: NaiveDate,
+) -> Result<(), Error> {
     println!("Dividend distribution per month:");
     let mut monthly_distribution = BTreeMap::new();
 
-    // USD / PLN
-    let mut exchange_rate_usd_pln = 0.0;
-    let provider = yahoo::YahooConnector::new().unwrap();
-    match provider.get_latest_quotes("USDPLN=X", "1d") {
-        Ok(response) => {
-            if let Ok(quotes) = response.quotes() {
-                println!("Kurs USD/PLN: {:?}", quotes.last().unwrap().close);
-                exchange_rate_usd_pln = quotes.last().unwrap().close;
-            }
-        }
-        Err(e) => eprintln!("Error: {:?}", e),
-    }
-    // EUR / PLN
-    let mut exchange_rate_eur_pln = 0.0;
-    let provider = yahoo::YahooConnector::new().unwrap();
-    match provider.get_latest_quotes("EURPLN=X", "1d") {
-        Ok(response) => {
-            if let Ok(quotes) = response.quotes() {
-                println!("Kurs USD/PLN: {:?}", quotes.last().unwrap().close);
-                exchange_rate_eur_pln = quotes.last().unwrap().close;
-            }
-        }
-        Err(e) => eprintln!("Error: {:?}", e),
-    }
-
-    // convert all dividends into PLN
+    // convert all dividends into currency of summary
+    let mut rates: BTreeMap<&str, f64> = BTreeMap::new();
     for stock in stocks {
+        let code = stock.current_value.code();
+        let rate = match rates.get(code) {
+            Some(rate) => *rate,
+            None => {
+                let rate = fx.rate(code, currency, as_of)?;
+                if code != currency {
+                    println!("Kurs {code}/{currency}: {rate:.4}");
+                }
+                rates.insert(code, rate);
+                rate
+            }
+        };
         for (month, amount) in &stock.monthly_dividends {
-            let amount_pln = match stock.current_value {
-                Currency::USD(_) => amount * exchange_rate_usd_pln,
-                Currency::EUR(_) => amount * exchange_rate_eur_pln,
-                Currency::PLN(_) => *amount,
-            };
-            *monthly_distribution.entry(month.clone()).or_insert(0.0) += amount_pln;
+            *monthly_distribution.entry(month.clone()).or_insert(0.0) += amount * rate;
         }
     }
 
     monthly_distribution.iter().for_each(|(m, v)| {
-        println!("{m} : {v:0.2} PLN ");
+        println!("{m} : {v:0.2} {currency} ");
     });
+    Ok(())
 }
 
-fn print_summary(data: &[Stock]) {
-    let mut total_investement = 0.0;
-    let mut annual_dividend = 0.0;
-    let mut portfolio_value = 0.0;
-    let mut total_investement_eur = 0.0;
-    let mut annual_dividend_eur = 0.0;
-    let mut portfolio_value_eur = 0.0;
-    let mut total_investement_pln = 0.0;
-    let mut annual_dividend_pln = 0.0;
-    let mut portfolio_value_pln = 0.0;
+/// Investment, value and annual dividend of holdings
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    invested: f64,
+    value: f64,
+    annual_dividend: f64,
+}
 
-    data.iter().for_each(|e| {
-        match e.invested_value {
-            Currency::USD(val) => total_investement += val,
-            Currency::EUR(val) => total_investement_eur += val,
-            Currency::PLN(val) => total_investement_pln += val,
-        }
-        match e.current_value {
-            Currency::USD(val) => portfolio_value += val,
-            Currency::EUR(val) => portfolio_value_eur += val,
-            Currency::PLN(val) => portfolio_value_pln += val,
-        }
-        match e.annualized_dividend {
-            Currency::USD(val) => annual_dividend += val,
-            Currency::EUR(val) => annual_dividend_eur += val,
-            Currency::PLN(val) => annual_dividend_pln += val,
-        }
-    });
-    if total_investement > 0.0 {
-        println!("Total investement[$]: {:.2}", total_investement);
-        println!("Total portfolio value [$]: {:.2}", portfolio_value);
-        println!("Total annual dividend[$]: {:.2}", annual_dividend);
+impl Totals {
+    fn print(&self, currency: &str) {
+        println!("Total investement[{currency}]: {:.2}", self.invested);
+        println!("Total portfolio value [{currency}]: {:.2}", self.value);
         println!(
-            "Portoflio yield[%]: {:.2}\n",
-            annual_dividend / total_investement * 100.0
-        )
-    }
-    if total_investement_eur > 0.0 {
-        println!("Total investement[EUR]: {:.2}", total_investement_eur);
-        println!("Total portfolio value [EUR]: {:.2}", portfolio_value_eur);
-        println!("Total annual dividend[EUR]: {:.2}", annual_dividend_eur);
+            "Total annual dividend[{currency}]: {:.2}",
+            self.annual_dividend
+        );
         println!(
             "Portoflio yield[%]: {:.2}\n",
-            annual_dividend_eur / total_investement_eur * 100.0
-        )
-    }
-    if total_investement_pln > 0.0 {
-        println!("Total investement[PLN]: {:.2}", total_investement_pln);
-        println!("Total portfolio value [PLN]: {:.2}", portfolio_value_pln);
-        println!("Total annual dividend[PLN]: {:.2}", annual_dividend_pln);
-        println!(
-            "Portoflio yield[%]: {:.2}\n",
-            annual_dividend_pln / total_investement_pln * 100.0
+            self.annual_dividend / self.invested * 100.0
         )
     }
+}
 
-    let mut exchange_rate_usd_pln = 0.0;
-    let provider = yahoo::YahooConnector::new().unwrap();
-    match provider.get_latest_quotes("USDPLN=X", "1d") {
-        Ok(response) => {
-            if let Ok(quotes) = response.quotes() {
-                println!("Kurs USD/PLN: {:?}", quotes.last().unwrap().close);
-                exchange_rate_usd_pln = quotes.last().unwrap().close;
-            }
+/// Print totals of holdings of every currency and of all of them converted into currency of
+/// summary
+fn print_summary(
+    data: &[Stock],
+    fx: &FxRates,
+    currency: &str,
+    as_of: NaiveDate,
+) -> Result<(), Error> {
+    // Investment, value and dividend of holding are all in currency of investment
+    let mut totals: BTreeMap<&str, Totals> = BTreeMap::new();
+    data.iter().for_each(|e| {
+        let totals = totals.entry(e.invested_value.code()).or_default();
+        totals.invested += e.invested_value.value();
+        totals.value += e.current_value.value();
+        totals.annual_dividend += e.annualized_dividend.value();
+    });
+    totals
+        .iter()
+        .filter(|(_, totals)| totals.invested > 0.0)
+        .for_each(|(code, totals)| totals.print(code));
+
+    let mut combined = Totals::default();
+    for (code, totals) in &totals {
+        let rate = fx.rate(code, currency, as_of)?;
+        if *code != currency {
+            println!("Kurs {code}/{currency}: {rate:.4}");
         }
-        Err(e) => eprintln!("Error: {:?}", e),
+        combined.invested += totals.invested * rate;
+        combined.value += totals.value * rate;
+        combined.annual_dividend += totals.annual_dividend * rate;
     }
-
-    if total_investement_eur > 0.0 && total_investement > 0.0 {
-        // Get current USD exchange rate and EUR exchange rate
-        let provider = yahoo::YahooConnector::new().unwrap();
-        match provider.get_latest_quotes("EURUSD=X", "1d") {
-            Ok(response) => {
-                if let Ok(quotes) = response.quotes() {
-                    println!("Kurs EUR/USD: {:?}", quotes.last().unwrap().close);
-                    let exchange_rate = quotes.last().unwrap().close;
-                    let eur = total_investement_eur * exchange_rate;
-                    let usd = total_investement;
-                    let total = eur + usd;
-                    let combined_yield = eur / total * annual_dividend_eur / total_investement_eur
-                        * 100.0
-                        + usd / total * annual_dividend / total_investement * 100.0;
-                    println!("Combined portfolio yield [%]: {:.2}", combined_yield);
-                    let combined_annual_dividend =
-                        annual_dividend_eur * exchange_rate + annual_dividend;
-                    println!(
-                        "Combined portfolio annual income [$]: {:.2}",
-                        combined_annual_dividend
-                    );
-                    println!(
-                        "Combined portfolio annual income [PLN]: {:.2}",
-                        combined_annual_dividend * exchange_rate_usd_pln
-                    );
-                }
-            }
-            Err(e) => eprintln!("Error: {:?}", e),
-        }
+    if combined.invested > 0.0 {
+        println!(
+            "Combined portfolio yield [%]: {:.2}",
+            combined.annual_dividend / combined.invested * 100.0
+        );
+        println!(
+            "Combined portfolio value [{currency}]: {:.2}",
+            combined.value
+        );
+        println!(
+            "Combined portfolio annual income [{currency}]: {:.2}",
+            combined.annual_dividend
+        );
     }
+    Ok(())
 }
 
 fn print_data_frame(data: &[Stock]) {
@@ -279,86 +310,22 @@ fn get_data<'a>(
     investement: Currency,
     num_shares: f64,
     div_yield: Option<f64>,
-    pb : &'a ProgressBar,
+    provider: &dyn DividendDataProvider,
+    as_of: NaiveDate,
+    options: &AnalysisOptions,
+    pb: &'a ProgressBar,
 ) -> Result<Stock<'a>, Box<dyn std::error::Error>> {
-    // Tworzenie providera Yahoo Finance
-    let mut provider = yahoo::YahooConnector::new()?;
-
-    // Pobieranie bieżącej ceny akcji
-    let response = provider.get_latest_quotes(symbol, "1d")?;
-
-    // Wyświetlenie danych o cenie
-    match response.last_quote() {
-        Ok(quote) => {
-            //         println!("=== current stock price {} ===", symbol);
-
-            let datetime = OffsetDateTime::from_unix_timestamp(quote.timestamp as i64)?;
-            //println!("Date: {}", datetime.date());
-            //println!();
-        }
-        Err(e) => {
-            println!("⚠️  Error fetching the data: {}", e);
-            println!();
-        }
-    }
-
-    // Pobieranie metadanych (zawierają dodatkowe informacje o akcji)
-    match response.metadata() {
-        Ok(metadata) => {
-            //if let Some(currency) = &metadata.currency {
-            //    println!("Currency: {}", currency);
-            //}
-
-            //if let Some(price) = metadata.regular_market_price {
-            //    println!("Market price: ${:.2}", price);
-            //}
-
-            //println!();
-        }
-        Err(e) => {
-            println!("⚠️  Error getting metadata of stock: {}", e);
-            println!();
-        }
-    }
+    let profile = investments_forecasting::get_dividend_profile(provider, symbol, as_of, options)?;
 
     let dividend_yield = match div_yield {
-        Some(yield_value) => {
-            //println!(
-            //    "Given dividend yield (from arg): {:.2}%",
-            //    yield_value * 100.0
-            //);
-            yield_value
-        }
-        None => {
-            let response_info = provider.get_ticker_info(symbol)?;
-            let mut yield_value = 0.0;
-            match response_info.quote_summary {
-                Some(info) => {
-                    // TODO: iterate
-                    if let Some(summary) = info.result {
-                        summary.iter().for_each(|item| {
-                            if let Some(dividend_yield) =
-                                item.summary_detail.as_ref().and_then(|d| d.dividend_yield)
-                            {
-                                // println!("Dividend yeild from metadata : {:.2}%", dividend_yield);
-                                yield_value = dividend_yield;
-                            }
-                        });
-                    } else {
-                        println!("⚠️ No info about stock");
-                    }
-                }
-                None => {
-                    println!("⚠️ No info about stock");
-                }
-            }
-            yield_value
-        }
+        Some(yield_value) => yield_value,
+        // Profile has dividend yield expressed in percents
+        None => profile.div_yield.unwrap_or(0.0) / 100.0,
     };
 
-    let value = investement.derive(response.last_quote()?.close * num_shares);
+    let value = investement.derive(profile.share_price * num_shares);
 
-    let monthly_dividends = get_dividend_history(symbol, num_shares)?;
+    let monthly_dividends = get_dividend_history(symbol, num_shares, provider, as_of, options)?;
 
     pb.inc(1);
 
@@ -374,45 +341,30 @@ fn get_data<'a>(
 fn get_dividend_history(
     symbol: &str,
     num_shares: f64,
+    provider: &dyn DividendDataProvider,
+    as_of: NaiveDate,
+    options: &AnalysisOptions,
 ) -> Result<BTreeMap<String, f64>, Box<dyn std::error::Error>> {
-    let provider = yahoo::YahooConnector::new()?;
-
     // Get scope of for dividends (previous year)
-    let now = OffsetDateTime::now_utc();
-    let prev_year = now.year() - 1;
-
-    let start = time::Date::from_calendar_date(prev_year, time::Month::January, 1)?
-        .with_hms(0, 0, 0)?
-        .assume_utc();
-    let end = time::Date::from_calendar_date(prev_year, time::Month::December, 31)?
-        .with_hms(23, 59, 59)?
-        .assume_utc();
-
-    let resp = provider.get_quote_history(symbol, start, end)?;
+    let prev_year = as_of.year() - 1;
 
     // Zbierz dywidendy per miesiac
     let mut monthly_dividends: BTreeMap<String, f64> = BTreeMap::new();
 
-    if let Ok(quotes) = resp.quotes() {
-        // quotes to ceny — szukamy eventow dywidendowych
-        // Yahoo finance zwraca dividendy w polu adjclose vs close diff
-        // Ale lepiej uzyc get_quote_history i sprawdzic eventy
-    }
-
-    // Probujemy wyciagnac dywidendy z odpowiedzi (pole events.dividends w Yahoo API)
-    // yahoo_finance_api parsuje to w strukt YResponse
-    if let Some(result) = resp.chart.result.as_ref() {
-        for r in result {
-            if let Some(events) = &r.events {
-                if let Some(dividends) = &events.dividends {
-                    for (_timestamp_str, div) in dividends {
-                        let dt = OffsetDateTime::from_unix_timestamp(div.date as i64)?;
-                        let month_key = format!("{:02}", dt.month() as u8);
-                        let dividend_income = div.amount * num_shares;
-                        *monthly_dividends.entry(month_key).or_insert(0.0) += dividend_income;
-                    }
-                }
-            }
+    let (div_history, splits) = provider
+        .dividends_and_splits(symbol)
+        .map_err(|e| e.with_ticker(symbol))?;
+    // Number of shares is the current one, so dividends paid before later splits are adjusted
+    let splits: Vec<SplitEvent> = splits.into_iter().filter(|s| s.date <= as_of).collect();
+    let div_history = div_history
+        .split_adjusted(&splits)
+        .attributed(Attribution::new(options.basis));
+    for event in &div_history {
+        let date = div_history.date(event);
+        if date.year() == prev_year {
+            let month_key = format!("{:02}", date.month());
+            let dividend_income = event.amount * num_shares;
+            *monthly_dividends.entry(month_key).or_insert(0.0) += dividend_income;
         }
     }
 
@@ -426,10 +378,50 @@ fn main() -> Result<(), String> {
         std::env::set_var("POLARS_FMT_MAX_ROWS", "-1")
     }
 
+    let args = Args::parse();
+    let as_of = args.as_of.unwrap_or_else(|| Utc::now().date_naive());
+    let options = AnalysisOptions {
+        basis: args.basis,
+        include_special: args.include_special,
+        ..Default::default()
+    };
+    let cache = (!args.no_cache).then(|| {
+        let dir = args
+            .cache_dir
+            .map(PathBuf::from)
+            .unwrap_or_else(Cache::default_dir);
+        Cache::new(&dir, args.refresh)
+    });
+    let provider_kind = match args.provider {
+        Some(kind) => kind,
+        None if args.local_dir.is_some() => ProviderKind::Local,
+        None => ProviderKind::from_env(),
+    };
+    if provider_kind == ProviderKind::File {
+        return Err(
+            "Error: divportfolio requires \"yahoo\", \"polygon\" or \"local\" provider".to_owned(),
+        );
+    }
+    let provider_options = ProviderOptions {
+        kind: provider_kind,
+        record_dir: args.record.map(PathBuf::from),
+        replay_dir: args.replay.map(PathBuf::from),
+        cache: cache.clone(),
+        rate_limiter: None,
+        retry: Default::default(),
+        local_dir: args.local_dir.map(PathBuf::from),
+    };
+    let provider = provider_options.build().map_err(|e| e.to_string())?;
+    let mut fx = FxRates::from_options(&provider_options).map_err(|e| e.to_string())?;
+    if let Some(path) = &args.fx_overrides {
+        fx = fx
+            .with_overrides(Path::new(path))
+            .map_err(|e| e.to_string())?;
+    }
+
     // Lets make some progress bar
     let pb = ProgressBar::no_length();
 
-
     // Get stock prices , get dividends data and get EUR/USD
 
     // List of companies in a format (symbol, invested financial resources, current value, current
@@ -441,27 +433,43 @@ fn main() -> Result<(), String> {
             Currency::USD(1210.0 + 11.20 + 800.0 + 11.91),
             827.09,
             None,
+            provider.as_ref(),
+            as_of,
+            &options,
             &pb,
         )
-        .unwrap(),
+        .map_err(|e| e.to_string())?,
     ];
 
     print_data_frame(&ania);
 
-    let jacek = vec![
-        get_data("AHOG.DE", Currency::EUR(5980.74), 179.11, None,&pb).unwrap(),
-    ];
+    let jacek = vec![get_data(
+        "AHOG.DE",
+        Currency::EUR(5980.74),
+        179.11,
+        None,
+        provider.as_ref(),
+        as_of,
+        &options,
+        &pb,
+    )
+    .map_err(|e| e.to_string())?];
     print_data_frame(&jacek);
 
+    let currency = args.currency.to_uppercase();
     println!("ANIA:");
-    print_monthly_dividends_distribution(&ania);
-    print_summary(&ania);
+    print_monthly_dividends_distribution(&ania, &fx, &currency, as_of)
+        .map_err(|e| e.to_string())?;
+    print_summary(&ania, &fx, &currency, as_of).map_err(|e| e.to_string())?;
 
     println!("JACEK:");
-    print_monthly_dividends_distribution(&jacek);
-    print_summary(&jacek);
+    print_monthly_dividends_distribution(&jacek, &fx, &currency, as_of)
+        .map_err(|e| e.to_string())?;
+    print_summary(&jacek, &fx, &currency, as_of).map_err(|e| e.to_string())?;
 
-    // Compute summary in PLN
+    if let Some(cache) = &cache {
+        println!("{}", cache.stats());
+    }
 
     Ok(())
 }
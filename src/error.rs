@@ -0,0 +1,186 @@
+//! Errors reported by the library
+
+use std::fmt;
+
+use crate::DataSource;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// Request to provider failed e.g. connection reset, timeout or unexpected HTTP status
+    Network {
+        provider: DataSource,
+        ticker: Option<String>,
+        status: Option<u16>,
+        cause: String,
+    },
+    /// Provider refused request as its rate limit was exceeded
+    RateLimited {
+        provider: DataSource,
+        ticker: Option<String>,
+    },
+    /// Data needed for analysis is not available
+    MissingData {
+        provider: Option<DataSource>,
+        ticker: Option<String>,
+        what: String,
+    },
+    /// Data could not be interpreted e.g. wrongly formatted date
+    Parse {
+        ticker: Option<String>,
+        what: String,
+        cause: String,
+    },
+    /// Any other failure reported by provider
+    Provider {
+        provider: DataSource,
+        ticker: Option<String>,
+        message: String,
+    },
+    /// Invalid setup e.g. missing API key or provider not usable for requested operation
+    Config(String),
+}
+
+impl Error {
+    pub fn missing(what: &str) -> Self {
+        Error::MissingData {
+            provider: None,
+            ticker: None,
+            what: what.to_owned(),
+        }
+    }
+
+    pub fn parse(what: &str, cause: impl fmt::Display) -> Self {
+        Error::Parse {
+            ticker: None,
+            what: what.to_owned(),
+            cause: cause.to_string(),
+        }
+    }
+
+    pub fn provider(provider: DataSource, message: impl fmt::Display) -> Self {
+        Error::Provider {
+            provider,
+            ticker: None,
+            message: message.to_string(),
+        }
+    }
+
+    /// Attach ticker to error unless it already refers to one
+    pub fn with_ticker(mut self, symbol: &str) -> Self {
+        match &mut self {
+            Error::Network { ticker, .. }
+            | Error::RateLimited { ticker, .. }
+            | Error::MissingData { ticker, .. }
+            | Error::Parse { ticker, .. }
+            | Error::Provider { ticker, .. } => {
+                if ticker.is_none() {
+                    *ticker = Some(symbol.to_owned());
+                }
+            }
+            Error::Config(_) => (),
+        }
+        self
+    }
+
+    /// Attach provider to missing data error unless it already refers to one
+    pub fn with_provider(mut self, source: DataSource) -> Self {
+        if let Error::MissingData { provider, .. } = &mut self {
+            if provider.is_none() {
+                *provider = Some(source);
+            }
+        }
+        self
+    }
+
+    /// Ticker the error refers to (if any)
+    pub fn ticker(&self) -> Option<&str> {
+        match self {
+            Error::Network { ticker, .. }
+            | Error::RateLimited { ticker, .. }
+            | Error::MissingData { ticker, .. }
+            | Error::Parse { ticker, .. }
+            | Error::Provider { ticker, .. } => ticker.as_deref(),
+            Error::Config(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let for_ticker = |ticker: &Option<String>| match ticker {
+            Some(ticker) => format!(" for {ticker}"),
+            None => "".to_owned(),
+        };
+        match self {
+            Error::Network {
+                provider,
+                ticker,
+                status,
+                cause,
+            } => {
+                write!(f, "{provider}: request{} failed", for_ticker(ticker))?;
+                if let Some(status) = status {
+                    write!(f, " (HTTP {status})")?;
+                }
+                write!(f, ": {cause}")
+            }
+            Error::RateLimited { provider, ticker } => {
+                write!(f, "{provider}: rate limit exceeded{}", for_ticker(ticker))
+            }
+            Error::MissingData {
+                provider,
+                ticker,
+                what,
+            } => {
+                if let Some(provider) = provider {
+                    write!(f, "{provider}: ")?;
+                }
+                write!(f, "missing {what}{}", for_ticker(ticker))
+            }
+            Error::Parse {
+                ticker,
+                what,
+                cause,
+            } => write!(f, "unable to parse {what}{}: {cause}", for_ticker(ticker)),
+            Error::Provider {
+                provider,
+                ticker,
+                message,
+            } => write!(f, "{provider}{}: {message}", for_ticker(ticker)),
+            Error::Config(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_context() -> Result<(), String> {
+        let err = Error::missing("share price")
+            .with_ticker("ABR")
+            .with_provider(DataSource::Polygon);
+        assert_eq!(err.ticker(), Some("ABR"));
+        assert_eq!(err.to_string(), "Polygon: missing share price for ABR");
+
+        // Context given first is kept
+        let err = err.with_ticker("ABM");
+        assert_eq!(err.ticker(), Some("ABR"));
+
+        let err = Error::Network {
+            provider: DataSource::Yahoo,
+            ticker: None,
+            status: Some(503),
+            cause: "Service Unavailable".to_owned(),
+        }
+        .with_ticker("CTBI");
+        assert_eq!(
+            err.to_string(),
+            "Yahoo: request for CTBI failed (HTTP 503): Service Unavailable"
+        );
+        Ok(())
+    }
+}
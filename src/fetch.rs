@@ -0,0 +1,262 @@
+//! Fetching data of many companies: shared async runtime and HTTP client, rate limiting of
+//! requests and processing of tickers by a bounded number of threads.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+static HTTP_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+// Yahoo refuses requests of clients that do not look like a browser
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// Async runtime shared by all requests of the process
+pub(crate) fn runtime() -> Result<&'static tokio::runtime::Runtime, Error> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| Error::Config(format!("Unable to create async runtime: {e}")))?;
+    // Runtime created concurrently by other thread is used instead, if any
+    let _ = RUNTIME.set(runtime);
+    RUNTIME
+        .get()
+        .ok_or_else(|| Error::Config("Unable to create async runtime".to_owned()))
+}
+
+/// HTTP client shared by all requests of the process, so connections are reused
+pub(crate) fn http_client() -> Result<&'static reqwest::Client, Error> {
+    if let Some(client) = HTTP_CLIENT.get() {
+        return Ok(client);
+    }
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .map_err(|e| Error::Config(format!("Unable to create HTTP client: {e}")))?;
+    // Client created concurrently by other thread is used instead, if any
+    let _ = HTTP_CLIENT.set(client);
+    HTTP_CLIENT
+        .get()
+        .ok_or_else(|| Error::Config("Unable to create HTTP client".to_owned()))
+}
+
+/// Flag set once user presses Ctrl-C, so that results acquired so far can be saved before
+/// exiting. Pressing Ctrl-C again exits immediately
+pub fn interrupt_flag() -> Result<Arc<AtomicBool>, Error> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    runtime()?.spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            log::warn!("Interrupted, finishing companies being fetched (press Ctrl-C again to exit immediately)");
+            flag.store(true, Ordering::Relaxed);
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+    Ok(interrupted)
+}
+
+/// Token bucket limiting number of requests sent per minute. Bucket is full at the start,
+/// so requests up to its capacity are sent without waiting
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    // Tokens available and the time they were counted at
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// Limiter allowing given number of requests per minute
+    pub fn per_minute(requests: u32) -> Self {
+        let capacity = requests.max(1) as f64;
+        Self {
+            capacity,
+            refill_per_sec: capacity / 60.0,
+            bucket: Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    /// Wait until request can be sent. Waiting does not block thread of async runtime, so
+    /// requests of other companies proceed meanwhile
+    pub async fn acquire(&self) {
+        loop {
+            match self.take(Instant::now()) {
+                Ok(()) => return,
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+
+    /// Take a token as of given time, or tell how long to wait for one
+    fn take(&self, now: Instant) -> Result<(), Duration> {
+        let mut bucket = match self.bucket.lock() {
+            Ok(bucket) => bucket,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let (tokens, counted_at) = *bucket;
+        let elapsed = now.saturating_duration_since(counted_at).as_secs_f64();
+        let tokens = (tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        if tokens >= 1.0 {
+            *bucket = (tokens - 1.0, now);
+            Ok(())
+        } else {
+            *bucket = (tokens, now);
+            Err(Duration::from_secs_f64(
+                (1.0 - tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+}
+
+/// Progress bar showing throughput and estimated time left
+pub fn progress_bar(len: Option<u64>) -> ProgressBar {
+    let (pb, template) = match len {
+        Some(len) => (
+            ProgressBar::new(len),
+            "{wide_bar} {pos}/{len} [{elapsed_precise}] {per_sec} ETA {eta}",
+        ),
+        None => (
+            ProgressBar::no_length(),
+            "{spinner} {pos} [{elapsed_precise}] {per_sec}",
+        ),
+    };
+    match ProgressStyle::with_template(template) {
+        Ok(style) => pb.with_style(style),
+        Err(_) => pb,
+    }
+}
+
+/// Run `work` on every item using up to `concurrency` threads and pass results to `consume`
+/// on the calling thread, in order of completion. Remaining items are skipped once `consume`
+/// fails
+pub fn for_each_concurrent<'a, T, R, E>(
+    items: &'a [T],
+    concurrency: usize,
+    work: impl Fn(&T) -> R + Sync,
+    mut consume: impl FnMut(&'a T, R) -> Result<(), E>,
+) -> Result<(), E>
+where
+    T: Sync,
+    R: Send,
+{
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, items.len().max(1)) {
+            let sender = sender.clone();
+            let (next, stop, work) = (&next, &stop, &work);
+            scope.spawn(move || loop {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let item = match items.get(index) {
+                    Some(item) => item,
+                    None => break,
+                };
+                if sender.send((index, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        // Receiving ends when all workers are done
+        drop(sender);
+
+        let mut outcome = Ok(());
+        for (index, result) in receiver {
+            if outcome.is_ok() {
+                outcome = consume(&items[index], result);
+                if outcome.is_err() {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        outcome
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() -> Result<(), String> {
+        // A token is added every second
+        let limiter = RateLimiter::per_minute(60);
+        let start = Instant::now();
+        for _ in 0..60 {
+            assert_eq!(limiter.take(start), Ok(()));
+        }
+        assert_eq!(limiter.take(start), Err(Duration::from_secs(1)));
+        let later = start + Duration::from_millis(500);
+        assert_eq!(limiter.take(later), Err(Duration::from_millis(500)));
+        assert_eq!(limiter.take(start + Duration::from_secs(1)), Ok(()));
+        // Unused tokens do not pile up above capacity
+        let much_later = start + Duration::from_secs(600);
+        for _ in 0..60 {
+            assert_eq!(limiter.take(much_later), Ok(()));
+        }
+        assert!(limiter.take(much_later).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limiter_acquire() -> Result<(), String> {
+        // A token is added every 100 ms
+        let limiter = RateLimiter::per_minute(600);
+        let start = Instant::now();
+        for _ in 0..600 {
+            assert_eq!(limiter.take(start), Ok(()));
+        }
+        runtime()
+            .map_err(|e| e.to_string())?
+            .block_on(limiter.acquire());
+        assert!(start.elapsed() >= Duration::from_millis(90));
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_each_concurrent() -> Result<(), String> {
+        let items: Vec<u32> = (1..=20).collect();
+        let mut sum = 0;
+        for_each_concurrent(
+            &items,
+            4,
+            |x| x * 2,
+            |_, doubled| {
+                sum += doubled;
+                Ok::<(), String>(())
+            },
+        )?;
+        assert_eq!(sum, 420);
+
+        // Failure of consumer stops processing
+        let mut consumed = 0;
+        let result = for_each_concurrent(
+            &items,
+            1,
+            |x| *x,
+            |_, x| {
+                consumed += 1;
+                if x == 3 {
+                    Err("failed")
+                } else {
+                    Ok(())
+                }
+            },
+        );
+        assert_eq!(result, Err("failed"));
+        assert_eq!(consumed, 3);
+        Ok(())
+    }
+}
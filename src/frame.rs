@@ -0,0 +1,343 @@
+//! Provider data as CSV data frames: files of on-disk cache (`crate::cache`) and ones served
+//! by "local" provider (`crate::local`) are converted with functions below, so both read
+//! back exactly what was parsed from responses of provider.
+
+use chrono::prelude::*;
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::provider::{CompanyDetails, FinancialReport, Quote, ReportTimeframe};
+use crate::{DividendEvent, DividendKind, DividendSeries, Error, SplitEvent};
+
+/// Kind of provider response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    Quote,
+    Dividends,
+    Splits,
+    Details,
+    Financials,
+}
+
+impl Endpoint {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Endpoint::Quote => "quote",
+            Endpoint::Dividends => "dividends",
+            Endpoint::Splits => "splits",
+            Endpoint::Details => "details",
+            Endpoint::Financials => "financials",
+        }
+    }
+
+    /// Location of stored response of given ticker
+    pub fn path(&self, dir: &Path, symbol: &str) -> PathBuf {
+        dir.join(symbol).join(format!("{}.csv", self.name()))
+    }
+}
+
+pub(crate) fn quote_to_df(quote: &Quote) -> PolarsResult<DataFrame> {
+    DataFrame::new(vec![
+        Series::new("price", &[quote.price]),
+        Series::new("currency", &[quote.currency.as_deref()]),
+    ])
+}
+
+pub(crate) fn quote_from_df(df: &DataFrame) -> PolarsResult<Option<Quote>> {
+    let price = f64_column(df, "price")?;
+    let currency = str_column(df, "currency")?;
+    Ok(match price.first() {
+        Some(Some(price)) => Some(Quote {
+            price: *price,
+            currency: currency.into_iter().next().flatten(),
+        }),
+        _ => None,
+    })
+}
+
+pub(crate) fn dividends_to_df(div_history: &DividendSeries) -> PolarsResult<DataFrame> {
+    let date_to_str = |date: NaiveDate| date.format("%Y-%m-%d").to_string();
+    let events = div_history.events();
+    DataFrame::new(vec![
+        Series::new(
+            "ex_date",
+            events
+                .iter()
+                .map(|e| date_to_str(e.ex_date))
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "pay_date",
+            events
+                .iter()
+                .map(|e| e.pay_date.map(date_to_str))
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "record_date",
+            events
+                .iter()
+                .map(|e| e.record_date.map(date_to_str))
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "declaration_date",
+            events
+                .iter()
+                .map(|e| e.declaration_date.map(date_to_str))
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "amount",
+            events.iter().map(|e| e.amount).collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "currency",
+            events
+                .iter()
+                .map(|e| e.currency.as_deref())
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "kind",
+            events
+                .iter()
+                .map(|e| e.kind.map(|k| k.name()))
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "declared_frequency",
+            events
+                .iter()
+                .map(|e| e.declared_frequency)
+                .collect::<Vec<_>>(),
+        ),
+    ])
+}
+
+/// Only "ex_date" and "amount" columns are required, remaining ones may be skipped
+pub(crate) fn dividends_from_df(df: &DataFrame) -> PolarsResult<DividendSeries> {
+    let parse_date =
+        |date: Option<String>| date.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok());
+    let ex_dates = str_column(df, "ex_date")?;
+    let amounts = f64_column(df, "amount")?;
+    let pay_dates = optional_column(df, "pay_date", str_column)?;
+    let record_dates = optional_column(df, "record_date", str_column)?;
+    let declaration_dates = optional_column(df, "declaration_date", str_column)?;
+    let currencies = optional_column(df, "currency", str_column)?;
+    let kinds = optional_column(df, "kind", str_column)?;
+    let frequencies = optional_column(df, "declared_frequency", f64_column)?;
+
+    let mut events = vec![];
+    for i in 0..df.height() {
+        let (ex_date, amount) = match (parse_date(ex_dates[i].clone()), amounts[i]) {
+            (Some(ex_date), Some(amount)) => (ex_date, amount),
+            _ => continue,
+        };
+        events.push(DividendEvent {
+            ex_date,
+            pay_date: parse_date(pay_dates[i].clone()),
+            record_date: parse_date(record_dates[i].clone()),
+            declaration_date: parse_date(declaration_dates[i].clone()),
+            amount,
+            currency: currencies[i].clone(),
+            kind: kinds[i].as_deref().and_then(DividendKind::from_name),
+            declared_frequency: frequencies[i].map(|f| f as u32),
+            split_factor: 1.0,
+        });
+    }
+    Ok(DividendSeries::new(events))
+}
+
+pub(crate) fn splits_to_df(splits: &[SplitEvent]) -> PolarsResult<DataFrame> {
+    DataFrame::new(vec![
+        Series::new(
+            "date",
+            splits
+                .iter()
+                .map(|s| s.date.format("%Y-%m-%d").to_string())
+                .collect::<Vec<_>>(),
+        ),
+        Series::new("from", splits.iter().map(|s| s.from).collect::<Vec<_>>()),
+        Series::new("to", splits.iter().map(|s| s.to).collect::<Vec<_>>()),
+    ])
+}
+
+pub(crate) fn splits_from_df(df: &DataFrame) -> PolarsResult<Vec<SplitEvent>> {
+    let dates = str_column(df, "date")?;
+    let from = f64_column(df, "from")?;
+    let to = f64_column(df, "to")?;
+    Ok(dates
+        .into_iter()
+        .zip(from)
+        .zip(to)
+        .filter_map(|((date, from), to)| {
+            let date = NaiveDate::parse_from_str(&date?, "%Y-%m-%d").ok()?;
+            Some(SplitEvent {
+                date,
+                from: from?,
+                to: to?,
+            })
+        })
+        .collect())
+}
+
+pub(crate) fn details_to_df(details: &CompanyDetails) -> PolarsResult<DataFrame> {
+    DataFrame::new(vec![
+        Series::new("name", &[details.name.as_deref()]),
+        Series::new("sector", &[details.sector.as_deref()]),
+        Series::new("div_yield", &[details.div_yield]),
+        Series::new("payout_ratio", &[details.payout_ratio]),
+    ])
+}
+
+pub(crate) fn details_from_df(df: &DataFrame) -> PolarsResult<CompanyDetails> {
+    Ok(CompanyDetails {
+        name: str_column(df, "name")?.into_iter().next().flatten(),
+        sector: str_column(df, "sector")?.into_iter().next().flatten(),
+        div_yield: f64_column(df, "div_yield")?.into_iter().next().flatten(),
+        payout_ratio: f64_column(df, "payout_ratio")?.into_iter().next().flatten(),
+    })
+}
+
+pub(crate) fn financials_to_df(reports: &[FinancialReport]) -> PolarsResult<DataFrame> {
+    let date_to_str = |date: Option<NaiveDate>| date.map(|d| d.format("%Y-%m-%d").to_string());
+    let fiscal_years: Vec<&str> = reports.iter().map(|r| r.fiscal_year.as_str()).collect();
+    let fiscal_periods: Vec<&str> = reports.iter().map(|r| r.fiscal_period.as_str()).collect();
+    let timeframes: Vec<&str> = reports
+        .iter()
+        .map(|r| match r.timeframe {
+            ReportTimeframe::Annual => "annual",
+            ReportTimeframe::Quarterly => "quarterly",
+        })
+        .collect();
+    let start_dates: Vec<Option<String>> =
+        reports.iter().map(|r| date_to_str(r.start_date)).collect();
+    let end_dates: Vec<Option<String>> = reports.iter().map(|r| date_to_str(r.end_date)).collect();
+    let net_cash_flows: Vec<Option<f64>> = reports
+        .iter()
+        .map(|r| r.net_cash_flow_from_operating_activities)
+        .collect();
+    let basic_average_shares: Vec<Option<f64>> =
+        reports.iter().map(|r| r.basic_average_shares).collect();
+    let values = |value: fn(&FinancialReport) -> Option<f64>| -> Vec<Option<f64>> {
+        reports.iter().map(value).collect()
+    };
+
+    DataFrame::new(vec![
+        Series::new("fiscal_year", fiscal_years),
+        Series::new("fiscal_period", fiscal_periods),
+        Series::new("timeframe", timeframes),
+        Series::new("start_date", start_dates),
+        Series::new("end_date", end_dates),
+        Series::new("net_cash_flow_from_operating_activities", net_cash_flows),
+        Series::new("basic_average_shares", basic_average_shares),
+        Series::new("capital_expenditure", values(|r| r.capital_expenditure)),
+        Series::new("net_income", values(|r| r.net_income)),
+        Series::new(
+            "diluted_earnings_per_share",
+            values(|r| r.diluted_earnings_per_share),
+        ),
+        Series::new("dividends_paid", values(|r| r.dividends_paid)),
+    ])
+}
+
+pub(crate) fn financials_from_df(df: &DataFrame) -> PolarsResult<Vec<FinancialReport>> {
+    let parse_date =
+        |date: Option<String>| date.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok());
+    let fiscal_years = str_column(df, "fiscal_year")?;
+    let fiscal_periods = str_column(df, "fiscal_period")?;
+    let timeframes = str_column(df, "timeframe")?;
+    let start_dates = str_column(df, "start_date")?;
+    let end_dates = str_column(df, "end_date")?;
+    let net_cash_flows = f64_column(df, "net_cash_flow_from_operating_activities")?;
+    let basic_average_shares = f64_column(df, "basic_average_shares")?;
+    // Columns added later, missing in older files
+    let capital_expenditures = optional_column(df, "capital_expenditure", f64_column)?;
+    let net_incomes = optional_column(df, "net_income", f64_column)?;
+    let diluted_eps = optional_column(df, "diluted_earnings_per_share", f64_column)?;
+    let dividends_paid = optional_column(df, "dividends_paid", f64_column)?;
+
+    let mut reports = vec![];
+    for i in 0..df.height() {
+        let timeframe = match timeframes[i].as_deref() {
+            Some("annual") => ReportTimeframe::Annual,
+            Some("quarterly") => ReportTimeframe::Quarterly,
+            _ => continue,
+        };
+        reports.push(FinancialReport {
+            fiscal_year: fiscal_years[i].clone().unwrap_or_default(),
+            fiscal_period: fiscal_periods[i].clone().unwrap_or_default(),
+            timeframe,
+            start_date: parse_date(start_dates[i].clone()),
+            end_date: parse_date(end_dates[i].clone()),
+            net_cash_flow_from_operating_activities: net_cash_flows[i],
+            basic_average_shares: basic_average_shares[i],
+            capital_expenditure: capital_expenditures[i],
+            net_income: net_incomes[i],
+            diluted_earnings_per_share: diluted_eps[i],
+            dividends_paid: dividends_paid[i],
+        });
+    }
+    Ok(reports)
+}
+
+/// Values of column or nulls if there is no such column
+fn optional_column<T: Clone>(
+    df: &DataFrame,
+    name: &str,
+    column: impl Fn(&DataFrame, &str) -> PolarsResult<Vec<Option<T>>>,
+) -> PolarsResult<Vec<Option<T>>> {
+    if df.get_column_names().contains(&name) {
+        column(df, name)
+    } else {
+        Ok(vec![None; df.height()])
+    }
+}
+
+pub(crate) fn f64_column(df: &DataFrame, name: &str) -> PolarsResult<Vec<Option<f64>>> {
+    // Columns with no values at all are read back as strings
+    Ok(df
+        .column(name)?
+        .cast(&DataType::Float64)?
+        .f64()?
+        .into_iter()
+        .collect())
+}
+
+pub(crate) fn str_column(df: &DataFrame, name: &str) -> PolarsResult<Vec<Option<String>>> {
+    Ok(df
+        .column(name)?
+        .cast(&DataType::Utf8)?
+        .utf8()?
+        .into_iter()
+        .map(|x| x.map(|s| s.to_owned()))
+        .collect())
+}
+
+/// Store response as CSV file, creating directories as needed
+pub(crate) fn write_df(path: &Path, mut df: DataFrame) -> Result<(), Error> {
+    let what = format!("response file {}", path.display());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| Error::Config(format!("Unable to create {what}: {e}")))?;
+    }
+    let mut file = std::fs::File::create(path)
+        .map_err(|e| Error::Config(format!("Unable to create {what}: {e}")))?;
+    CsvWriter::new(&mut file)
+        .has_header(true)
+        .finish(&mut df)
+        .map_err(|e| Error::Config(format!("Unable to write {what}: {e}")))
+}
+
+/// Read stored response. None if it was never stored
+pub(crate) fn read_df(path: &Path) -> Result<Option<DataFrame>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let what = format!("response file {}", path.display());
+    CsvReader::from_path(path)
+        .and_then(|reader| reader.has_header(true).finish())
+        .map(Some)
+        .map_err(|e| Error::parse(&what, e))
+}
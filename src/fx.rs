@@ -0,0 +1,344 @@
+//! Exchange rates of currencies.
+//!
+//! `FxRates` converts amounts between any two currencies as of any date. Rates are taken from
+//! file of manual overrides first e.g. rates of central bank table used for tax settlement.
+//! Rate with empty date is used for days without rate of their own:
+//!
+//! ```text
+//! base,quote,rate,date
+//! USD,PLN,3.9432,2024-01-15
+//! EUR,PLN,4.35,
+//! ```
+//!
+//! Other rates are closes of pairs quoted by provider as Yahoo tickers e.g. "EURPLN=X" (the
+//! last one before as-of date), so they are cached, recorded and replayed like share prices.
+//! "local" provider serves them from `<dir>/EURPLN=X/prices.csv`. Inverse pair is used if the
+//! requested one is not quoted, and pairs not quoted at all are crossed via US dollar.
+//!
+//! Every pair is asked for only once per date and run. Rate which cannot be found is reported
+//! as `Error::MissingData`, never replaced with zero.
+
+use chrono::prelude::*;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::frame::{f64_column, str_column};
+use crate::provider::{DividendDataProvider, ProviderKind, ProviderOptions};
+use crate::{DividendProfile, Error};
+
+/// Rate of pair given in file of overrides
+#[derive(Debug, Clone, PartialEq)]
+struct Override {
+    base: String,
+    quote: String,
+    /// Day rate is used for, any day if None
+    date: Option<NaiveDate>,
+    rate: f64,
+}
+
+/// Code of currency amounts are converted through, with multiplier converting amounts into
+/// it. Prices of some exchanges are quoted in minor units e.g. "GBp" (pence) of London
+fn major_currency(code: &str) -> (String, f64) {
+    match code {
+        "GBp" | "GBX" => ("GBP".to_owned(), 0.01),
+        "ZAc" | "ZAC" => ("ZAR".to_owned(), 0.01),
+        "ILA" => ("ILS".to_owned(), 0.01),
+        code => (code.to_uppercase(), 1.0),
+    }
+}
+
+/// Exchange rates of currencies, see module documentation
+pub struct FxRates {
+    source: Option<Box<dyn DividendDataProvider>>,
+    overrides: Vec<Override>,
+    /// Rates already found per (base, quote, as-of date)
+    rates: Mutex<HashMap<(String, String, NaiveDate), f64>>,
+}
+
+impl FxRates {
+    /// Rates quoted by given provider. Without provider only overrides are known
+    pub fn new(source: Option<Box<dyn DividendDataProvider>>) -> Self {
+        Self {
+            source,
+            overrides: vec![],
+            rates: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rates quoted by Yahoo, or served from files of "local" provider, with the same
+    /// caching, recording, replaying and retries as companies data
+    pub fn from_options(options: &ProviderOptions) -> Result<Self, Error> {
+        let kind = match options.kind {
+            ProviderKind::Local => ProviderKind::Local,
+            _ => ProviderKind::Yahoo,
+        };
+        let source = ProviderOptions {
+            kind,
+            ..options.clone()
+        }
+        .build()?;
+        Ok(Self::new(Some(source)))
+    }
+
+    /// Prefer rates of given CSV file (columns base, quote, rate and optional date) to quoted
+    /// ones
+    pub fn with_overrides(mut self, path: &Path) -> Result<Self, Error> {
+        let what = format!("exchange rate overrides file {}", path.display());
+        let df = CsvReader::from_path(path)
+            .and_then(|reader| reader.has_header(true).finish())
+            .map_err(|e| Error::parse(&what, e))?;
+        let overrides = || -> PolarsResult<Vec<Override>> {
+            let dates = if df.get_column_names().contains(&"date") {
+                str_column(&df, "date")?
+            } else {
+                vec![None; df.height()]
+            };
+            str_column(&df, "base")?
+                .into_iter()
+                .zip(str_column(&df, "quote")?)
+                .zip(f64_column(&df, "rate")?)
+                .zip(dates)
+                .enumerate()
+                .map(|(row, (((base, quote), rate), date))| {
+                    let invalid = |what: &str| {
+                        PolarsError::ComputeError(format!("{what} at row {}", row + 1).into())
+                    };
+                    let date = match date.filter(|d| !d.trim().is_empty()) {
+                        Some(date) => Some(
+                            NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                                .map_err(|_| invalid("invalid date"))?,
+                        ),
+                        None => None,
+                    };
+                    match (base, quote, rate) {
+                        (Some(base), Some(quote), Some(rate)) if rate > 0.0 => Ok(Override {
+                            base: major_currency(base.trim()).0,
+                            quote: major_currency(quote.trim()).0,
+                            date,
+                            rate,
+                        }),
+                        _ => Err(invalid("missing currency or positive rate")),
+                    }
+                })
+                .collect()
+        };
+        self.overrides = overrides().map_err(|e| Error::parse(&what, e))?;
+        log::info!("{} exchange rates read from {what}", self.overrides.len());
+        Ok(self)
+    }
+
+    /// Amount of `quote` currency worth one unit of `base` currency as of given date
+    pub fn rate(&self, base: &str, quote: &str, as_of: NaiveDate) -> Result<f64, Error> {
+        let (base, base_multiplier) = major_currency(base);
+        let (quote, quote_multiplier) = major_currency(quote);
+        let rate = if base == quote {
+            1.0
+        } else {
+            self.major_rate(&base, &quote, as_of)?
+        };
+        Ok(rate * base_multiplier / quote_multiplier)
+    }
+
+    /// The most recent rate, as of today
+    pub fn spot(&self, base: &str, quote: &str) -> Result<f64, Error> {
+        self.rate(base, quote, Utc::now().date_naive())
+    }
+
+    /// Amount in `from` currency converted into `to` currency with rate of given date
+    pub fn convert(
+        &self,
+        amount: f64,
+        from: &str,
+        to: &str,
+        as_of: NaiveDate,
+    ) -> Result<f64, Error> {
+        Ok(amount * self.rate(from, to, as_of)?)
+    }
+
+    /// Convert share price and dividends of profile into given currency with rate of date
+    /// profile was made as of. Yields, growth rates and payout ratios do not depend on currency.
+    /// Profile of unknown currency (e.g. company without dividends of provider not reporting
+    /// currency of quotes) cannot be converted
+    pub fn convert_profile(
+        &self,
+        profile: &mut DividendProfile,
+        currency: &str,
+    ) -> Result<(), Error> {
+        let from = profile.currency.as_deref().ok_or_else(|| {
+            Error::missing("currency")
+                .with_ticker(&profile.symbol)
+                .with_provider(profile.source)
+        })?;
+        let rate = self
+            .rate(from, currency, profile.as_of)
+            .map_err(|e| e.with_ticker(&profile.symbol))?;
+        profile.share_price *= rate;
+        for amount in [
+            &mut profile.recent_div,
+            &mut profile.forward_div,
+            &mut profile.special_div,
+        ] {
+            if let Some(amount) = amount {
+                *amount *= rate;
+            }
+        }
+        profile.currency = Some(major_currency(currency).0);
+        Ok(())
+    }
+
+    /// Rate of two different currencies given by their major units
+    fn major_rate(&self, base: &str, quote: &str, as_of: NaiveDate) -> Result<f64, Error> {
+        let key = (base.to_owned(), quote.to_owned(), as_of);
+        if let Some(rate) = self.rates.lock().ok().and_then(|r| r.get(&key).copied()) {
+            return Ok(rate);
+        }
+        let rate = match self.overridden(base, quote, as_of) {
+            Some(rate) => rate,
+            None => self.quoted(base, quote, as_of)?,
+        };
+        log::info!("Exchange rate {base}/{quote} as of {as_of}: {rate}");
+        if let Ok(mut rates) = self.rates.lock() {
+            rates.insert(key, rate);
+        }
+        Ok(rate)
+    }
+
+    /// Rate of file of overrides. Rate of the same day is preferred to one without date
+    fn overridden(&self, base: &str, quote: &str, as_of: NaiveDate) -> Option<f64> {
+        let find = |base: &str, quote: &str| {
+            let pair = || {
+                self.overrides
+                    .iter()
+                    .filter(move |o| o.base == base && o.quote == quote)
+            };
+            pair()
+                .find(|o| o.date == Some(as_of))
+                .or_else(|| pair().find(|o| o.date.is_none()))
+                .map(|o| o.rate)
+        };
+        find(base, quote).or_else(|| find(quote, base).map(|rate| 1.0 / rate))
+    }
+
+    /// Rate quoted by provider directly, as inverse pair or crossed via US dollar
+    fn quoted(&self, base: &str, quote: &str, as_of: NaiveDate) -> Result<f64, Error> {
+        let missing = || Error::missing(&format!("exchange rate {base}/{quote} as of {as_of}"));
+        let source = self.source.as_ref().ok_or_else(missing)?;
+        let close = |symbol: String| match source.quote(&symbol, as_of) {
+            Ok(quote) if quote.price > 0.0 => Some(quote.price),
+            Ok(quote) => {
+                log::warn!("Exchange rate {symbol}: invalid close {}", quote.price);
+                None
+            }
+            Err(e) => {
+                log::warn!("Exchange rate {symbol}: {e}");
+                None
+            }
+        };
+        if let Some(rate) = close(format!("{base}{quote}=X")) {
+            return Ok(rate);
+        }
+        if let Some(rate) = close(format!("{quote}{base}=X")) {
+            return Ok(1.0 / rate);
+        }
+        if base != "USD" && quote != "USD" {
+            if let (Ok(base_usd), Ok(usd_quote)) = (
+                self.major_rate(base, "USD", as_of),
+                self.major_rate("USD", quote, as_of),
+            ) {
+                return Ok(base_usd * usd_quote);
+            }
+        }
+        Err(missing().with_provider(source.source()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::LocalProvider;
+    use crate::DataSource;
+
+    #[test]
+    fn test_fx_rates() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("fx-rates-{}", std::process::id()));
+        let write = |path: &str, contents: &str| -> Result<(), String> {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+            std::fs::write(path, contents).map_err(|e| e.to_string())
+        };
+        write(
+            "USDPLN=X/prices.csv",
+            "date,close\n2024-01-12,3.95\n2024-01-15,4.0\n",
+        )?;
+        write("EURUSD=X/prices.csv", "date,close\n2024-01-15,1.1\n")?;
+        write("GBPUSD=X/prices.csv", "date,close\n2024-01-15,1.25\n")?;
+        write(
+            "overrides.csv",
+            "base,quote,rate,date\nCHF,PLN,4.6,\nCHF,PLN,4.7,2024-01-15\n",
+        )?;
+        let provider = LocalProvider::new(&dir).map_err(|e| e.to_string())?;
+        let fx = FxRates::new(Some(Box::new(provider)))
+            .with_overrides(&dir.join("overrides.csv"))
+            .map_err(|e| e.to_string())?;
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let round = |rate: Result<f64, Error>| rate.map(|r| (r * 1e6).round() / 1e6);
+
+        // Quoted, historical, inverse and crossed rates
+        assert_eq!(fx.rate("USD", "PLN", date(2024, 1, 15)), Ok(4.0));
+        assert_eq!(fx.rate("USD", "PLN", date(2024, 1, 14)), Ok(3.95));
+        assert_eq!(round(fx.rate("PLN", "USD", date(2024, 1, 15))), Ok(0.25));
+        assert_eq!(round(fx.rate("EUR", "PLN", date(2024, 1, 15))), Ok(4.4));
+        assert_eq!(round(fx.rate("EUR", "GBP", date(2024, 1, 15))), Ok(0.88));
+        assert_eq!(round(fx.rate("GBp", "USD", date(2024, 1, 15))), Ok(0.0125));
+        assert_eq!(fx.rate("usd", "USD", date(2024, 1, 15)), Ok(1.0));
+
+        // Overrides of given day are preferred to ones without date
+        assert_eq!(fx.rate("CHF", "PLN", date(2024, 1, 15)), Ok(4.7));
+        assert_eq!(fx.rate("CHF", "PLN", date(2024, 1, 16)), Ok(4.6));
+        assert_eq!(
+            round(fx.convert(9.4, "PLN", "CHF", date(2024, 1, 15))),
+            Ok(2.0)
+        );
+
+        // Missing rate is an error
+        assert_eq!(
+            fx.rate("USD", "PLN", date(2023, 1, 15)),
+            Err(Error::MissingData {
+                provider: Some(DataSource::Other("Local")),
+                ticker: None,
+                what: "exchange rate USD/PLN as of 2023-01-15".to_owned(),
+            })
+        );
+
+        let mut profile =
+            DividendProfile::new("ABR", DataSource::Other("Local"), date(2024, 1, 15));
+        profile.share_price = 13.0;
+        profile.recent_div = Some(0.43);
+        profile.div_yield = Some(13.2);
+        profile.currency = Some("USD".to_owned());
+        fx.convert_profile(&mut profile, "PLN")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(profile.share_price, 52.0);
+        assert_eq!(profile.recent_div, Some(1.72));
+        assert_eq!(profile.div_yield, Some(13.2));
+        assert_eq!(profile.currency, Some("PLN".to_owned()));
+
+        // Profile of unknown currency is not converted
+        let mut profile = DividendProfile::new("ABR", DataSource::Polygon, date(2024, 1, 15));
+        profile.share_price = 13.0;
+        assert_eq!(
+            fx.convert_profile(&mut profile, "PLN"),
+            Err(Error::MissingData {
+                provider: Some(DataSource::Polygon),
+                ticker: Some("ABR".to_owned()),
+                what: "currency".to_owned(),
+            })
+        );
+        assert_eq!(profile.share_price, 13.0);
+
+        std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+//! Estimators of dividend growth rate (DGR) from annual dividends.
+//!
+//! Annual dividends are given as sums per year, ordered from the oldest year and with zeros
+//! for years without dividends. Estimators differ in how they treat such gaps and single
+//! outlying years.
+
+use std::collections::BTreeMap;
+
+use crate::dividend::median;
+
+/// Fraction of year-over-year changes dropped from each end by trimmed mean
+const TRIM_FRACTION: f64 = 0.1;
+
+/// Method of computing dividend growth rate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DgrEstimator {
+    /// Arithmetic mean of year-over-year changes. Gap year counts as -100% and recovery
+    /// from it as +100%
+    #[default]
+    Average,
+    /// Compound annual growth rate between the oldest and the most recent year
+    Cagr,
+    /// Median of year-over-year changes. Changes from gap years are skipped
+    Median,
+    /// Mean of year-over-year changes without the highest and the lowest ones. Changes from
+    /// gap years are skipped
+    TrimmedMean,
+    /// Slope of linear regression of logarithm of annual dividend. Gap years are skipped
+    Regression,
+}
+
+impl DgrEstimator {
+    /// Name shown in column headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            DgrEstimator::Average => "avg",
+            DgrEstimator::Cagr => "CAGR",
+            DgrEstimator::Median => "median",
+            DgrEstimator::TrimmedMean => "trimmed mean",
+            DgrEstimator::Regression => "log-linear",
+        }
+    }
+}
+
+/// Dividend growth rate estimated from annual dividends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrowthEstimate {
+    /// Annual growth rate[%]
+    pub rate: f64,
+    /// Coefficient of determination of regression, for regression estimator only
+    pub r_squared: Option<f64>,
+}
+
+impl GrowthEstimate {
+    fn from_rate(rate: f64) -> Self {
+        Self {
+            rate,
+            r_squared: None,
+        }
+    }
+}
+
+/// Growth rate of given annual dividends. `None` if it cannot be estimated with chosen
+/// estimator e.g. CAGR when the oldest year has no dividends
+pub fn estimate(
+    annual_div: &BTreeMap<i32, f64>,
+    estimator: DgrEstimator,
+) -> Option<GrowthEstimate> {
+    if annual_div.is_empty() {
+        return None;
+    }
+    // Single year does not show any growth
+    if annual_div.len() == 1 {
+        return Some(GrowthEstimate::from_rate(0.0));
+    }
+    match estimator {
+        DgrEstimator::Average => Some(GrowthEstimate::from_rate(average(annual_div))),
+        DgrEstimator::Cagr => cagr(annual_div).map(GrowthEstimate::from_rate),
+        DgrEstimator::Median => median(changes(annual_div)).map(GrowthEstimate::from_rate),
+        DgrEstimator::TrimmedMean => {
+            trimmed_mean(changes(annual_div)).map(GrowthEstimate::from_rate)
+        }
+        DgrEstimator::Regression => regression(annual_div),
+    }
+}
+
+fn average(annual_div: &BTreeMap<i32, f64>) -> f64 {
+    let mut average = 0.0;
+    let mut num_averages = 0;
+    let mut from_newer_to_older = annual_div.iter().rev();
+    let mut next_year_div = match from_newer_to_older.next() {
+        Some((_, div)) => div,
+        None => return 0.0,
+    };
+    for (year, sum) in from_newer_to_older {
+        log::info!("DGR: Annual dividend year: {year} annual_div: {sum}");
+        if *sum > 0.0 {
+            average += (next_year_div / sum - 1.0) * 100.0;
+        } else {
+            // If next year dividend is positive and previous one is zero then
+            // increase was by 100%
+            if *next_year_div > 0.0 {
+                average += 100.0;
+            } else {
+                average += 0.0;
+            }
+        }
+        next_year_div = sum;
+        num_averages += 1;
+    }
+
+    if num_averages == 0 {
+        0.0
+    } else {
+        average / num_averages as f64
+    }
+}
+
+/// Year-over-year changes[%], except changes from years without dividends
+fn changes(annual_div: &BTreeMap<i32, f64>) -> Vec<f64> {
+    let values: Vec<f64> = annual_div.values().copied().collect();
+    values
+        .windows(2)
+        .filter(|w| w[0] > 0.0)
+        .map(|w| (w[1] / w[0] - 1.0) * 100.0)
+        .collect()
+}
+
+fn cagr(annual_div: &BTreeMap<i32, f64>) -> Option<f64> {
+    let (first_year, first) = annual_div.iter().next()?;
+    let (last_year, last) = annual_div.iter().next_back()?;
+    if *first <= 0.0 || *last <= 0.0 {
+        return None;
+    }
+    let num_years = (last_year - first_year) as f64;
+    Some(((last / first).powf(1.0 / num_years) - 1.0) * 100.0)
+}
+
+fn trimmed_mean(mut changes: Vec<f64>) -> Option<f64> {
+    if changes.is_empty() {
+        return None;
+    }
+    changes.sort_by(|a, b| a.total_cmp(b));
+    // Nothing is dropped from two changes, as nothing would remain
+    let num_trimmed = if changes.len() > 2 {
+        (changes.len() as f64 * TRIM_FRACTION).ceil() as usize
+    } else {
+        0
+    };
+    let kept = &changes[num_trimmed..changes.len() - num_trimmed];
+    Some(kept.iter().sum::<f64>() / kept.len() as f64)
+}
+
+/// Least squares fit of ln(dividend) = a + b * year
+fn regression(annual_div: &BTreeMap<i32, f64>) -> Option<GrowthEstimate> {
+    let points: Vec<(f64, f64)> = annual_div
+        .iter()
+        .filter(|(_, div)| **div > 0.0)
+        .map(|(year, div)| (*year as f64, div.ln()))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+
+    let ss_tot: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    let ss_res: f64 = points
+        .iter()
+        .map(|(x, y)| (y - (intercept + slope * x)).powi(2))
+        .sum();
+    // Constant dividend is fitted perfectly
+    let r_squared = if ss_tot > 0.0 {
+        1.0 - ss_res / ss_tot
+    } else {
+        1.0
+    };
+
+    Some(GrowthEstimate {
+        rate: (slope.exp() - 1.0) * 100.0,
+        r_squared: Some(r_squared),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round2(val: f64) -> f64 {
+        (val * 100.0).round() / 100.0
+    }
+
+    #[test]
+    fn test_estimators_with_gap_year() -> Result<(), String> {
+        // Dividend growing by 10% a year, but not paid in 2019
+        let annual_div = BTreeMap::from([
+            (2016, 1.0),
+            (2017, 1.1),
+            (2018, 1.21),
+            (2019, 0.0),
+            (2020, 1.4641),
+            (2021, 1.61051),
+        ]);
+        let rate = |estimator| estimate(&annual_div, estimator).map(|g| round2(g.rate));
+
+        // Gap year dominates average
+        assert_eq!(rate(DgrEstimator::Average), Some(6.0));
+        assert_eq!(rate(DgrEstimator::Cagr), Some(10.0));
+        assert_eq!(rate(DgrEstimator::Median), Some(10.0));
+        // -100% change is trimmed
+        assert_eq!(rate(DgrEstimator::TrimmedMean), Some(10.0));
+        assert_eq!(rate(DgrEstimator::Regression), Some(10.0));
+        let r_squared = estimate(&annual_div, DgrEstimator::Regression)
+            .and_then(|g| g.r_squared)
+            .map(round2);
+        assert_eq!(r_squared, Some(1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimators_edge_cases() -> Result<(), String> {
+        assert_eq!(estimate(&BTreeMap::new(), DgrEstimator::Cagr), None);
+        assert_eq!(
+            estimate(&BTreeMap::from([(2020, 1.0)]), DgrEstimator::Regression),
+            Some(GrowthEstimate::from_rate(0.0))
+        );
+        // Dividend started in 2021
+        let annual_div = BTreeMap::from([(2020, 0.0), (2021, 1.0), (2022, 1.0)]);
+        assert_eq!(estimate(&annual_div, DgrEstimator::Cagr), None);
+        let regression = estimate(&annual_div, DgrEstimator::Regression);
+        assert_eq!(regression.map(|g| g.rate), Some(0.0));
+        assert_eq!(regression.and_then(|g| g.r_squared), Some(1.0));
+        Ok(())
+    }
+}
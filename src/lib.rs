@@ -1,155 +1,214 @@
-use calamine::{Reader, Xlsx};
 use polars::prelude::*;
 use std::fmt;
-use yahoo_finance_api as yahoo;
 
 use chrono::prelude::*;
-use time::OffsetDateTime;
 
-use polygon_client::rest::RESTClient;
 use std::collections::BTreeMap;
-use std::collections::HashMap;
 
-pub fn get_yahoo_connector() -> Result<Option<yahoo::YahooConnector>, String> {
-    if std::env::var("POLYGON_AUTH_KEY").is_ok() {
-        Ok(None)
-    } else {
-        Ok(Some(yahoo::YahooConnector::new().map_err(|_| {
-            "Could not create Yahoo provider".to_owned()
-        })?))
+pub mod cache;
+pub mod database;
+pub mod dividend;
+pub mod error;
+pub mod fetch;
+pub mod frame;
+pub mod fx;
+pub mod growth;
+pub mod list;
+pub mod local;
+pub mod provider;
+pub mod recording;
+mod response;
+pub mod retry;
+pub mod streak;
+pub mod transport;
+
+pub use dividend::{
+    Attribution, DividendBasis, DividendEvent, DividendKind, DividendSeries, FrequencyEstimate,
+    PaymentFrequency, SplitEvent, YieldKind,
+};
+pub use error::Error;
+pub use growth::{DgrEstimator, GrowthEstimate};
+pub use list::load_list;
+use provider::{DividendDataProvider, FinancialReport, ReportTimeframe};
+pub use streak::{DividendList, Streaks};
+
+/// Provider that dividend data of a company was fetched from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataSource {
+    Yahoo,
+    Polygon,
+    /// Provider implemented outside of this crate
+    Other(&'static str),
+}
+
+impl fmt::Display for DataSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataSource::Yahoo => write!(f, "Yahoo"),
+            DataSource::Polygon => write!(f, "Polygon"),
+            DataSource::Other(name) => write!(f, "{name}"),
+        }
     }
 }
 
-pub fn load_list<R>(excel: &mut Xlsx<R>, category: &str) -> Result<DataFrame, &'static str>
-where
-    R: std::io::BufRead,
-    R: std::io::Read,
-    R: std::io::Seek,
-{
-    log::info!("Processing category: {}", category);
-    let names = excel.sheet_names();
-    log::info!("Available categories: {:?}", names);
-    let name_sheet = names
-        .iter()
-        .find(|x| *x == category)
-        .ok_or("Error: Category not found")?;
-
-    // Dividend Yield
-    // Dividend
-    // Share price
-    // sector
-    let mut df = DataFrame::default();
-    if let Some(Ok(r)) = excel.worksheet_range(&name_sheet) {
-        let mut rows = r.rows();
-
-        // Rewind to actual categories
-        rows.next();
-        rows.next();
-
-        let categories = rows
-            .next()
-            .expect_and_log("Error: unable to get descriptive row");
-        //       let mut symbol = 0;
-
-        let mut columns: Vec<&str> = Vec::default();
-        let mut sseries: HashMap<usize, Vec<Option<&str>>> = HashMap::new();
-        let mut fseries: HashMap<usize, Vec<Option<f64>>> = HashMap::new();
-        for c in categories {
-            // Find indices of interesting collumns
-            if let Some(v) = c.get_string() {
-                columns.push(v);
-            } else if c.is_empty() {
-                columns.push("Blended"); // Blended info got empty name of column
-            }
+/// Dividend related data of a single company as returned by `get_dividend_profile`. Metrics that could not be obtained from provider are `None`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DividendProfile {
+    /// Ticker symbol e.g. "ABR"
+    pub symbol: String,
+    /// Most recent share price
+    pub share_price: f64,
+    /// Currency of share price and dividends e.g. "USD"
+    pub currency: Option<String>,
+    /// Most recently paid dividend (single payment)
+    pub recent_div: Option<f64>,
+    /// Dividend yield[%] of kind chosen in `AnalysisOptions`
+    pub div_yield: Option<f64>,
+    /// Yield[%] of forward annualized dividend
+    pub forward_yield: Option<f64>,
+    /// Yield[%] of dividends paid within twelve trailing months
+    pub ttm_yield: Option<f64>,
+    /// Yield[%] of dividends of the most recent complete year
+    pub last_fy_yield: Option<f64>,
+    /// Dividend yield[%] as reported by provider. Its definition differs between providers
+    pub reported_yield: Option<f64>,
+    /// Number of dividend payments per year as inferred from spacing of regular payments, or
+    /// number of payments in previous year if they do not follow any schedule
+    pub frequency: Option<i64>,
+    /// Schedule of regular payments with flag telling if it is followed consistently
+    pub payment_frequency: Option<FrequencyEstimate>,
+    /// Forward annualized dividend: latest regular payment times number of payments per year
+    pub forward_div: Option<f64>,
+    /// Average dividend growth rate over last ten years[%]. DGRs are computed with estimator
+    /// chosen in `AnalysisOptions`
+    pub dgr_10y: Option<f64>,
+    /// Goodness of fit of DGR over last ten years, for regression estimator only
+    pub dgr_10y_r_squared: Option<f64>,
+    /// Average dividend growth rate over last five years[%]
+    pub dgr_5y: Option<f64>,
+    /// Average dividend growth rate over last three years[%]
+    pub dgr_3y: Option<f64>,
+    /// Dividend growth rate of previous year[%]
+    pub dgr_1y: Option<f64>,
+    /// Dividend growth rate of twelve trailing months[%]
+    pub dgr_1y_ttm: Option<f64>,
+    /// Consecutive years of dividend growth, not including current year
+    pub years_of_growth: Option<i64>,
+    /// Consecutive years without dividend cut, not including current year
+    pub years_without_cut: Option<i64>,
+    /// Consecutive years with dividend paid, not including current year
+    pub years_of_payment: Option<i64>,
+    /// List of dividend growers company belongs to according to its years of growth
+    pub dividend_list: Option<DividendList>,
+    /// Dividend payout ratio[%] as reported by provider, or of operating cash flow of twelve
+    /// trailing months (the most recent annual report if quarterly ones are not available)
+    pub payout_ratio: Option<f64>,
+    /// Fiscal periods of reports payout ratio was computed from e.g. "2023 Q1". Empty if it was
+    /// reported by provider
+    pub payout_periods: Vec<String>,
+    /// Dividends per share relative to free cash flow per share[%]
+    pub fcf_payout_ratio: Option<f64>,
+    /// Dividends per share relative to diluted earnings per share[%]
+    pub eps_payout_ratio: Option<f64>,
+    /// Free cash flow divided by cash dividends paid. Dividends are covered if above 1
+    pub fcf_coverage: Option<f64>,
+    /// Special, supplemental and irregular dividends paid in previous year (sum per share).
+    /// They are not included in other metrics unless requested
+    pub special_div: Option<f64>,
+    /// Industry description
+    pub sector: Option<String>,
+    /// Date the analysis was made as of. Data published after it is ignored
+    pub as_of: NaiveDate,
+    /// Provider the data was fetched from
+    pub source: DataSource,
+}
+
+impl DividendProfile {
+    /// Profile of given company with no metrics known yet
+    pub fn new(symbol: &str, source: DataSource, as_of: NaiveDate) -> Self {
+        Self {
+            symbol: symbol.to_owned(),
+            share_price: 0.0,
+            currency: None,
+            recent_div: None,
+            div_yield: None,
+            forward_yield: None,
+            ttm_yield: None,
+            last_fy_yield: None,
+            reported_yield: None,
+            frequency: None,
+            payment_frequency: None,
+            forward_div: None,
+            dgr_10y: None,
+            dgr_10y_r_squared: None,
+            dgr_5y: None,
+            dgr_3y: None,
+            dgr_1y: None,
+            dgr_1y_ttm: None,
+            years_of_growth: None,
+            years_without_cut: None,
+            years_of_payment: None,
+            dividend_list: None,
+            payout_ratio: None,
+            payout_periods: vec![],
+            fcf_payout_ratio: None,
+            eps_payout_ratio: None,
+            fcf_coverage: None,
+            special_div: None,
+            sector: None,
+            as_of,
+            source,
         }
-        log::info!("Columns: {:?}", columns);
-
-        // Iterate through rows of actual sold transactions
-        for row in rows {
-            for (i, cell) in row.iter().enumerate() {
-                match cell {
-                    calamine::DataType::Float(f) => {
-                        if fseries.contains_key(&i) {
-                            let vf = fseries
-                                .get_mut(&i)
-                                .ok_or("Error: accessing invalid category")?;
-                            vf.push(Some(*f));
-                        } else {
-                            fseries.insert(i, vec![Some(*f)]);
-                        }
-                    }
-                    calamine::DataType::String(s) => {
-                        if sseries.contains_key(&i) {
-                            let vf = sseries
-                                .get_mut(&i)
-                                .ok_or("Error: accessing invalid category")?;
-                            vf.push(Some(s));
-                        } else {
-                            if s != "" {
-                                sseries.insert(i, vec![Some(s)]);
-                            } else {
-                                // If empty field then it maybe a missing data
-                                log::warn!("Missing data at row: {:?}", row);
-                                if fseries.contains_key(&i) {
-                                    let vf = fseries
-                                        .get_mut(&i)
-                                        .ok_or("Error: accessing invalid category")?;
-                                    vf.push(None);
-                                } else {
-                                    log::error!("Error: incomplete data. Please update manualy");
-                                }
-                            }
-                        }
-                    }
-                    calamine::DataType::DateTime(s) => {
-                        if fseries.contains_key(&i) {
-                            let vf = fseries
-                                .get_mut(&i)
-                                .ok_or("Error: accessing invalid category")?;
-                            vf.push(Some(*s));
-                        } else {
-                            fseries.insert(i, vec![Some(*s)]);
-                        }
-                    }
-                    calamine::DataType::Empty => {
-                        // If empty field then it maybe a missing data
-                        log::warn!("Missing data at row: {:?}", row);
-                        if fseries.contains_key(&i) {
-                            let vf = fseries
-                                .get_mut(&i)
-                                .ok_or("Error: accessing invalid category")?;
-                            vf.push(None);
-                        } else if sseries.contains_key(&i) {
-                            let vf = sseries
-                                .get_mut(&i)
-                                .ok_or("Error: accessing invalid category")?;
-                            vf.push(None);
-                        } else {
-                            sseries.insert(i, vec![None]);
-                        }
-                    }
-                    _ => (),
-                }
-            }
+    }
+
+    /// Dividend yield[%] of given kind
+    pub fn yield_of(&self, kind: YieldKind) -> Option<f64> {
+        match kind {
+            YieldKind::Forward => self.forward_yield,
+            YieldKind::Ttm => self.ttm_yield,
+            YieldKind::LastFy => self.last_fy_yield,
         }
+    }
 
-        // Build DataFrame
-        let mut df_series: Vec<Series> = vec![];
-        fseries.iter().for_each(|(k, v)| {
-            let s = Series::new(columns[*k], v.iter());
-            df_series.push(s);
-        });
-        sseries.iter().for_each(|(k, v)| {
-            let s = Series::new(columns[*k], v);
-            df_series.push(s);
-        });
-        df = DataFrame::new(df_series).map_err(|msg| {
-            log::error!("DF error: {msg}");
-            "Error: Could not create DataFrame"
-        })?;
+    /// Dividend payout ratio[%] of given kind. Dividends paid while free cash flow is not
+    /// positive have no payout ratio, rather than a negative one passing any screen
+    pub fn payout_of(&self, basis: PayoutBasis) -> Option<f64> {
+        match basis {
+            PayoutBasis::OperatingCashFlow => self.payout_ratio,
+            PayoutBasis::FreeCashFlow => self.fcf_payout_ratio,
+            PayoutBasis::Earnings => self.eps_payout_ratio,
+            PayoutBasis::DividendsPaid => self
+                .fcf_coverage
+                .filter(|coverage| *coverage > 0.0)
+                .map(|coverage| 100.0 / coverage),
+        }
     }
+}
 
-    Ok(df)
+/// What dividends are compared with when computing payout ratio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PayoutBasis {
+    /// Operating cash flow (or payout ratio reported by provider)
+    #[default]
+    OperatingCashFlow,
+    /// Free cash flow: operating cash flow less capital expenditure
+    FreeCashFlow,
+    /// Diluted earnings per share
+    Earnings,
+    /// Cash dividends paid relative to free cash flow, i.e. inverse of FCF coverage
+    DividendsPaid,
+}
+
+impl PayoutBasis {
+    /// Name shown in column headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            PayoutBasis::OperatingCashFlow => "OCF",
+            PayoutBasis::FreeCashFlow => "FCF",
+            PayoutBasis::Earnings => "EPS",
+            PayoutBasis::DividendsPaid => "dividends paid/FCF",
+        }
+    }
 }
 
 // Let's extend Result with logging
@@ -185,939 +244,326 @@ pub fn init_logging_infrastructure() {
     }
     simple_logger::SimpleLogger::new().env().init().unwrap();
 }
-fn should_try_again<T>(
-    maybe_resp: Result<T, reqwest::Error>,
-    dummy: T,
-) -> Result<(T, bool), &'static str> {
-    match maybe_resp {
-        Ok(r) => Ok((r, false)),
-        Err(e) => {
-            log::info!("Error: {:?}", e.status());
-            let repeat = if let Some(status) = e.status() {
-                if status == 429 {
-                    println!("Waiting for 30 s and rerunning query");
-                    let thirty_secs = std::time::Duration::new(30, 0);
-                    std::thread::sleep(thirty_secs);
-                    true
-                } else {
-                    log::warn!("POLYGON API: failed to query tickers");
-                    return Err("POLYGON_API: Unhandled error");
-                }
-            } else {
-                log::warn!("POLYGON API: failed to query tickers");
-                return Err("POLYGON_API: Unrecognized error");
-            };
-            Ok((dummy, repeat))
-        }
-    }
-}
-
-pub fn get_polygon_companies_list() -> Result<Vec<(String, Option<String>)>, &'static str> {
-    let mut query_params = HashMap::new();
-    query_params.insert("active", "true");
-    query_params.insert("market", "stocks");
 
-    let client = RESTClient::new(None, None);
-    // Get all dividend data we can have
-    tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap()
-        .block_on(async {
-            let mut run = true;
-            let mut resp = polygon_client::types::ReferenceTickersResponse {
-                next_url: None,
-                results: vec![],
-                status: "OK".to_owned(),
-                count: 0,
-                request_id: "".to_owned(),
-            };
-
-            while run {
-                let maybe_resp = client.reference_tickers(&query_params).await;
-                log::info!("RESPONSE(LIST COMPANIES): {maybe_resp:#?}");
-                (resp, run) = should_try_again(maybe_resp, resp)?;
-            }
-
-            let tickers_results_to_vec =
-                |results: &Vec<polygon_client::types::ReferenceTickersResponseTickerV3>| {
-                    let mut companies: Vec<(String, Option<String>)> = results
-                        .iter()
-                        .map(|x| {
-                            log::info!("{}: name: {:?}", x.ticker, x.name);
-                            (x.ticker.clone(), x.name.clone())
-                        })
-                        .collect();
-                    companies
-                };
-
-            let mut companies: Vec<(String, Option<String>)> =
-                tickers_results_to_vec(&resp.results);
-
-            while resp.next_url.clone().is_some() {
-                if let Some(url) = &resp.next_url.clone() {
-                    run = true;
-                    while run {
-                        let maybe_resp = client.fetch_next_page(url).await;
-                        log::info!("RESPONSE NEXT PAGE (LIST COMPANIES): {maybe_resp:#?}");
-                        (resp, run) = should_try_again(maybe_resp, resp)?;
-                    }
-                    // Here let's attach
-                    companies.append(&mut tickers_results_to_vec(&resp.results));
-                }
-            }
+/// Tickers and names of all active companies of Polygon.io. Requests are sent, recorded or
+/// replayed according to given options, live ones wait for permission of their rate limiter
+/// (if any) and are repeated according to their retry policy
+pub fn get_polygon_companies_list(
+    options: &provider::ProviderOptions,
+) -> Result<Vec<(String, Option<String>)>, Error> {
+    let transport = options.transport(DataSource::Polygon)?;
+    let url = format!(
+        "{}/v3/reference/tickers?active=true&market=stocks",
+        provider::POLYGON_URL
+    );
+    let results = provider::polygon_results(
+        transport.as_ref(),
+        &transport::Request::market("tickers", url),
+    )?;
 
-            return Ok::<Vec<(String, Option<String>)>, &'static str>(companies);
+    let companies = results
+        .iter()
+        .filter_map(|x| {
+            let ticker = x["ticker"].as_str()?;
+            let name = x["name"].as_str().map(|name| name.to_owned());
+            log::info!("{}: name: {:?}", ticker, name);
+            Some((ticker.to_owned(), name))
         })
+        .collect();
+    Ok(companies)
 }
 
-async fn get_company_details(
-    client: &RESTClient,
-    company: &str,
-) -> Result<Option<String>, &'static str> {
-    let mut resp = polygon_client::types::ReferenceTickerDetailsResponse {
-        request_id: "".to_owned(),
-        results: Default::default(),
-        status: "OK".to_owned(),
-    };
-
-    let mut run = true;
-    while run {
-        let maybe_resp = client
-            .reference_ticker_details(company, &HashMap::new())
-            .await;
-        log::info!("RESPONSE(COMPANY DETAILS): {maybe_resp:#?}");
-        (resp, run) = match should_try_again(maybe_resp, resp) {
-            Ok((lresp, lrun)) => (lresp, lrun),
-            Err(_) => return Ok(None),
-        };
-    }
-
-    Ok(resp.results.sic_description)
-}
+/// Fill in dividend related metrics (recent dividend, DGRs, years of growth) of given profile
+/// and return dividend history trimmed to the years used for computing them
+fn calculate_dividend_metrics(
+    profile: &mut DividendProfile,
+    div_history: DividendSeries,
+    estimator: DgrEstimator,
+) -> Result<DividendSeries, Error> {
+    log::info!("Ordered dividends: {div_history:#?}");
 
-fn get_yahoo_dividiend_data(
-    provider: &yahoo::YahooConnector,
-    company: &str,
-    currency: &str,
-) -> Result<
-    (
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<i64>,
-        Vec<(String, f64)>,
-    ),
-    &'static str,
-> {
-    // Get scope of for dividends (previous year)
-    let now = OffsetDateTime::now_utc();
-    let prev_year = now.year() - 1;
-
-    let start = time::Date::from_calendar_date(prev_year - 20, time::Month::January, 1)
-        .map_err(|_| "Cannot set date")?
-        .with_hms(0, 0, 0)
-        .map_err(|_| "Cannot set date")?
-        .assume_utc();
-    let end = time::Date::from_calendar_date(prev_year, time::Month::December, 31)
-        .map_err(|_| "Cannot set date")?
-        .with_hms(23, 59, 59)
-        .map_err(|_| "Cannot set date")?
-        .assume_utc();
-
-    let resp = provider
-        .get_quote_history(company, start, end)
-        .map_err(|_| "Cannot get dividends history")?;
-    let mut div_history: Vec<(String, f64)> = vec![];
-
-    if let Some(result) = resp.chart.result.as_ref() {
-        for r in result {
-            if let Some(events) = &r.events {
-                if let Some(dividends) = &events.dividends {
-                    for (_timestamp_str, div) in dividends {
-                        let dt = OffsetDateTime::from_unix_timestamp(div.date as i64)
-                            .map_err(|_| "Cannot set date")?;
-                        let exdiv_date =
-                            format!("{}-{:02}-{:02}", dt.year(), dt.month() as u8, dt.day());
-                        div_history.push((exdiv_date, div.amount));
-                    }
-                }
-            }
-        }
-    }
+    let current_date = profile.as_of;
+    // Streaks use the whole history, not only the years DGRs are computed from
+    let streaks = calculate_streaks(&div_history, current_date.year().to_string().as_ref())?;
+    profile.years_of_growth = streaks.map(|s| s.growth);
+    profile.years_without_cut = streaks.map(|s| s.no_cut);
+    profile.years_of_payment = streaks.map(|s| s.payment);
+    profile.dividend_list = streaks.and_then(|s| s.list());
+    log::info!(
+        "Consecutive years of dividend growth: {:?}, without cut: {:?}, of payment: {:?}, list: {:?}",
+        profile.years_of_growth,
+        profile.years_without_cut,
+        profile.years_of_payment,
+        profile.dividend_list
+    );
+    profile.dgr_1y_ttm = calculate_dgr_ttm(&div_history, current_date)?;
 
-    div_history.sort_by(|a, b| {
-        let a_date = NaiveDate::parse_from_str(&a.0, "%Y-%m-%d").expect("unable to parse date");
-        let b_date = NaiveDate::parse_from_str(&b.0, "%Y-%m-%d").expect("unable to parse date");
-        a_date.cmp(&b_date)
-    });
-    log::info!("Ordered dividends: {div_history:#?}");
-    let years_of_growth = calculate_consecutive_years_of_growth(
-        &div_history,
-        Utc::now().year().to_string().as_ref(),
-    )?;
-    log::info!("Consecutive years of dividend growth: {years_of_growth:?}");
-    let current_date = Utc::now();
-    let dgr_1y_ttm = calculate_dgr_ttm(&div_history, &current_date.format("%Y-%m-%d").to_string())?;
-
-    let trim_div_history = |div_history: Vec<(String, f64)>,
-                            current_year: i32,
-                            num_years_of_interest: i32|
-     -> Vec<(String, f64)> {
-        div_history
-            .into_iter()
-            .filter(|x| {
-                let x_date_year = NaiveDate::parse_from_str(&x.0, "%Y-%m-%d")
-                    .expect("unable to parse date")
-                    .year();
+    let trim_div_history =
+        |div_history: &DividendSeries, current_year: i32, num_years_of_interest: i32| {
+            div_history.filter(|x| {
+                let x_date_year = div_history.year(x);
                 // Current year data is not used
-                if x_date_year == current_year {
-                    false
-                } else if (current_year - x_date_year) <= num_years_of_interest {
-                    true
-                } else {
-                    false
-                }
+                x_date_year != current_year && (current_year - x_date_year) <= num_years_of_interest
             })
-            .collect::<Vec<_>>()
-    };
+        };
 
     let current_year = current_date.year();
-    let div_history = trim_div_history(div_history, current_year, 11);
+    let div_history = trim_div_history(&div_history, current_year, 11);
 
     // Curr Dividend  and corressponding date
-    let (curr_div, curr_div_date) = match div_history.iter().rev().next() {
-        Some((pay_date, cash_amount)) => (
-            Some(*cash_amount),
-            Some(
-                NaiveDate::parse_from_str(&pay_date, "%Y-%m-%d").expect("Wrong payout date format"),
-            ),
-        ),
+    let curr_div_date = match div_history.last() {
+        Some(event) => {
+            profile.recent_div = Some(event.amount);
+            Some(div_history.date(event))
+        }
         None => {
             log::info!("No dividend Data!");
-            (None, None)
+            None
         }
     };
 
-    let shorter_div_history = trim_div_history(div_history.clone(), current_year, 6);
-    let even_shorter_div_history = trim_div_history(div_history.clone(), current_year, 4);
-    let shortest_div_history = trim_div_history(div_history.clone(), current_year, 2);
+    let shorter_div_history = trim_div_history(&div_history, current_year, 6);
+    let even_shorter_div_history = trim_div_history(&div_history, current_year, 4);
+    let shortest_div_history = trim_div_history(&div_history, current_year, 2);
     log::info!("Shorted dividend history: {even_shorter_div_history:#?}");
     let current_year = current_year.to_string();
-    let dgr = calculate_dgr(&div_history, current_year.as_ref())?;
-    let dgr5y = calculate_dgr(&shorter_div_history, current_year.as_ref())?;
-    let dgr3y = calculate_dgr(&even_shorter_div_history, current_year.as_ref())?;
-    let dgr1y = calculate_dgr(&shortest_div_history, current_year.as_ref())?;
-
-    log::info!("Current Div: {curr_div:?} {currency:?}, Paid date: {curr_div_date:?}, Average DGR(samples: {}): {dgr:?}, DGR 1Y : {dgr1y:?}",
-            div_history.len());
-
-    Ok((
-        curr_div,
-        dgr,
-        dgr5y,
-        dgr3y,
-        dgr1y,
-        dgr_1y_ttm,
-        years_of_growth,
-        div_history,
-    ))
-
-    //        curr_div,
-    //        dgr,
-    //        dgr5y,
-    //        dgr3y,
-    //       dgr1y,
-    //      dgr_1y_ttm,
-    //     years_of_growth,
-    //    div_history,
-}
-
-async fn get_dividiend_data(
-    client: &RESTClient,
-    query_params: &HashMap<&str, &str>,
-) -> Result<
-    (
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<i64>,
-        Vec<(String, f64)>,
-    ),
-    &'static str,
-> {
-    let dividends_results_to_vec =
-        |results: &mut Vec<polygon_client::types::ReferenceStockDividendsResultV3>| {
-            results.iter().for_each(|x| {
-                log::info!(
-                    "{}: ex date: {}, payment date: {:?}, div type: {} amount: {}",
-                    x.ticker,
-                    x.ex_dividend_date,
-                    x.pay_date,
-                    x.dividend_type,
-                    x.cash_amount
-                );
-            });
-            let div_history: Vec<(String, f64)> = results
-                .iter_mut()
-                .filter(|x| {
-                    x.dividend_type == polygon_client::types::DividendType::CD
-                        && x.pay_date.is_some() == true
-                })
-                .map(|x| (x.pay_date.clone().unwrap(), x.cash_amount))
-                .collect();
-            div_history
-        };
-
-    let mut run = true;
-    let mut resp = polygon_client::types::ReferenceStockDividendsResponse {
-        next_url: None,
-        results: vec![],
-        status: "OK".to_owned(),
+    let dgr_10y = estimate_dgr(&div_history, current_year.as_ref(), estimator)?;
+    profile.dgr_10y = dgr_10y.map(|g| g.rate);
+    profile.dgr_10y_r_squared = dgr_10y.and_then(|g| g.r_squared);
+    let rate = |div_history: &DividendSeries| -> Result<Option<f64>, Error> {
+        Ok(estimate_dgr(div_history, current_year.as_ref(), estimator)?.map(|g| g.rate))
     };
+    profile.dgr_5y = rate(&shorter_div_history)?;
+    profile.dgr_3y = rate(&even_shorter_div_history)?;
+    profile.dgr_1y = rate(&shortest_div_history)?;
 
-    while run {
-        let maybe_resp = client.reference_stock_dividends(&query_params).await;
-        log::info!("RESPONSE(DIVIDENDS): {maybe_resp:#?}");
-        (resp, run) = match should_try_again(maybe_resp, resp) {
-            Ok((resp, run)) => (resp, run),
-            Err(_) => return Ok((None, None, None, None, None, None, None, vec![])),
-        };
-    }
+    log::info!("Current Div: {:?} {:?}, Paid date: {curr_div_date:?}, Average DGR(samples: {}): {:?}, DGR 1Y : {:?}",
+            profile.recent_div, profile.currency, div_history.len(), profile.dgr_10y, profile.dgr_1y);
 
-    let mut div_history: Vec<(String, f64)> = dividends_results_to_vec(&mut resp.results);
-    while resp.next_url.clone().is_some() {
-        if let Some(url) = &resp.next_url.clone() {
-            run = true;
-            while run {
-                let maybe_resp: Result<
-                    polygon_client::types::ReferenceStockDividendsResponse,
-                    reqwest::Error,
-                > = client.fetch_next_page(url).await;
-                log::info!("RESPONSE NEXT PAGE (DIVIDENDS): {maybe_resp:#?}");
-                (resp, run) = match should_try_again(maybe_resp, resp) {
-                    Ok((resp, run)) => (resp, run),
-                    Err(_) => return Ok((None, None, None, None, None, None, None, vec![])),
-                };
-            }
-            // Here let's attach
-            div_history.append(&mut dividends_results_to_vec(&mut resp.results));
-        }
-    }
-
-    div_history.sort_by(|a, b| {
-        let a_date = NaiveDate::parse_from_str(&a.0, "%Y-%m-%d").expect("unable to parse date");
-        let b_date = NaiveDate::parse_from_str(&b.0, "%Y-%m-%d").expect("unable to parse date");
-        a_date.cmp(&b_date)
-    });
-
-    log::info!("Ordered dividends: {div_history:#?}");
+    Ok(div_history)
+}
 
-    let years_of_growth = calculate_consecutive_years_of_growth(
-        &div_history,
-        Utc::now().year().to_string().as_ref(),
-    )?;
-    log::info!("Consecutive years of dividend growth: {years_of_growth:?}");
+/// Settings of dividend analysis shared by all analysed companies
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnalysisOptions {
+    /// Date dividends are attributed to when grouping them into years
+    pub basis: DividendBasis,
+    /// Include special, supplemental and irregular dividends in dividend metrics
+    pub include_special: bool,
+    /// Method of computing dividend growth rates
+    pub dgr_estimator: DgrEstimator,
+    /// Kind of dividend yield reported as `DividendProfile::div_yield`
+    pub yield_kind: YieldKind,
+}
 
-    let current_date = Utc::now();
-    let dgr_1y_ttm = calculate_dgr_ttm(&div_history, &current_date.format("%Y-%m-%d").to_string())?;
+/// Last month of fiscal year according to the most recent annual report, December if unknown
+fn fiscal_year_end(reports: &[FinancialReport]) -> u32 {
+    reports
+        .iter()
+        .filter(|r| r.timeframe == ReportTimeframe::Annual)
+        .filter_map(|r| r.end_date)
+        .max()
+        .map_or(12, |end_date| end_date.month())
+}
 
-    let trim_div_history = |div_history: Vec<(String, f64)>,
-                            current_year: i32,
-                            num_years_of_interest: i32|
-     -> Vec<(String, f64)> {
+/// Get dividend related data of given company from chosen provider, as it was known at `as_of`
+/// date. Dividends and financial reports after that date are ignored
+pub fn get_dividend_profile(
+    provider: &dyn DividendDataProvider,
+    symbol: &str,
+    as_of: NaiveDate,
+    options: &AnalysisOptions,
+) -> Result<DividendProfile, Error> {
+    let mut profile = DividendProfile::new(symbol, provider.source(), as_of);
+    let with_context = |e: Error| e.with_ticker(symbol).with_provider(provider.source());
+
+    let quote = provider.quote(symbol, as_of).map_err(with_context)?;
+    profile.share_price = quote.price;
+    profile.currency = quote.currency;
+
+    let details = provider.company_details(symbol).map_err(with_context)?;
+    profile.sector = details.sector;
+
+    // Reports are used for both attributing dividends to fiscal years and payout ratios
+    let reports: Vec<FinancialReport> = provider
+        .financials(symbol)
+        .map_err(with_context)?
+        .into_iter()
+        .filter(|r| r.end_date.map_or(false, |end_date| end_date <= as_of))
+        .collect();
+
+    let mut attribution = Attribution::new(options.basis);
+    if options.basis == DividendBasis::DeclaredFiscalYear {
+        attribution.fiscal_year_end = fiscal_year_end(&reports);
+    }
+    let (div_history, splits) = provider
+        .dividends_and_splits(symbol)
+        .map_err(with_context)?;
+    // Splits made after as-of date were not known yet
+    let splits: Vec<SplitEvent> = splits.into_iter().filter(|s| s.date <= as_of).collect();
+    let div_history = div_history
+        .split_adjusted(&splits)
+        .attributed(attribution)
+        .until(as_of)
+        .classified();
+    if profile.currency.is_none() {
+        profile.currency = div_history.currency().map(|c| c.to_owned());
+    }
+    if !div_history.is_empty() {
+        let previous_year = as_of.year() - 1;
+        profile.special_div = Some(
+            div_history
+                .non_regular()
+                .iter()
+                .filter(|x| div_history.year(x) == previous_year)
+                .map(|x| x.amount)
+                .sum(),
+        );
+    }
+    let div_history = if options.include_special {
         div_history
-            .into_iter()
-            .filter(|x| {
-                let x_date_year = NaiveDate::parse_from_str(&x.0, "%Y-%m-%d")
-                    .expect("unable to parse date")
-                    .year();
-                // Current year data is not used
-                if x_date_year == current_year {
-                    false
-                } else if (current_year - x_date_year) <= num_years_of_interest {
-                    true
-                } else {
-                    false
-                }
-            })
-            .collect::<Vec<_>>()
-    };
-
-    let current_year = current_date.year();
-    let div_history = trim_div_history(div_history, current_year, 11);
-
-    // Curr Dividend  and corressponding date
-    let (curr_div, curr_div_date) = match div_history.iter().rev().next() {
-        Some((pay_date, cash_amount)) => (
-            Some(*cash_amount),
-            Some(
-                NaiveDate::parse_from_str(&pay_date, "%Y-%m-%d").expect("Wrong payout date format"),
-            ),
-        ),
-        None => {
-            log::info!("No dividend Data!");
-            (None, None)
-        }
-    };
-    let currency = if resp.results.len() > 0 {
-        Some(resp.results[0].currency.clone())
     } else {
-        None
-    };
-
-    let shorter_div_history = trim_div_history(div_history.clone(), current_year, 6);
-    let even_shorter_div_history = trim_div_history(div_history.clone(), current_year, 4);
-    let shortest_div_history = trim_div_history(div_history.clone(), current_year, 2);
-    log::info!("Shorted dividend history: {even_shorter_div_history:#?}");
-    let current_year = current_year.to_string();
-    let dgr = calculate_dgr(&div_history, current_year.as_ref())?;
-    let dgr5y = calculate_dgr(&shorter_div_history, current_year.as_ref())?;
-    let dgr3y = calculate_dgr(&even_shorter_div_history, current_year.as_ref())?;
-    let dgr1y = calculate_dgr(&shortest_div_history, current_year.as_ref())?;
-
-    log::info!("Current Div: {curr_div:?} {currency:?}, Paid date: {curr_div_date:?}, Average DGR(samples: {}): {dgr:?}, DGR 1Y : {dgr1y:?}",
-            div_history.len());
-
-    Ok((
-        curr_div,
-        dgr,
-        dgr5y,
-        dgr3y,
-        dgr1y,
-        dgr_1y_ttm,
-        years_of_growth,
-        div_history,
-    ))
-}
-pub fn get_yahoo_data(
-    company: &str,
-    provider: &mut yahoo::YahooConnector,
-) -> Result<
-    (
-        f64,
-        Option<f64>,
-        Option<f64>,
-        Option<i64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<i64>,
-        Option<f64>,
-        Option<String>,
-    ),
-    &'static str,
-> {
-    log::info!("Yahoo: Getting Ticker: {}", company);
-
-    // stock price
-    let response = provider.get_latest_quotes(company, "1d").map_err(|e| {
-        log::error!("{e}");
-        "Could not get Yahoo response"
-    })?;
-    let share_price = match response.last_quote() {
-        Ok(quote) => quote.close,
-        Err(e) => return Err("Error: could not get stock price"),
+        div_history.regular()
     };
+    // Frequency is inferred from the most recent payments, including ones of current year
+    profile.payment_frequency = div_history.frequency();
+    profile.forward_div = div_history.forward_annualized();
+    log::info!(
+        "Payment frequency: {:?}, forward annualized dividend: {:?}",
+        profile.payment_frequency,
+        profile.forward_div
+    );
 
-    // Pobieranie metadanych (zawierają dodatkowe informacje o akcji)
-    let currency = match response.metadata() {
-        Ok(metadata) => {
-            if let Some(currency) = &metadata.currency {
-                log::info!("Currency: {}", currency);
-                currency.clone()
-            } else {
-                log::warn!("⚠️  No currency information available for stock");
-                "Unknown".to_string()
-            }
-        }
-        Err(e) => {
-            return Err("⚠️  Error getting metadata of stock: ");
-        }
-    };
-    // dividend yield
-    let response_info = provider
-        .get_ticker_info(company)
-        .map_err(|e| "Could not get Yahoo ticker info: {e}")?;
-    let mut yield_value = 0.0;
-    let mut payout_ratio = 0.0;
-    match response_info.quote_summary {
-        Some(info) => {
-            if let Some(summary) = info.result {
-                summary.iter().for_each(|item| {
-                    if let Some(dividend_yield) =
-                        item.summary_detail.as_ref().and_then(|d| d.dividend_yield)
-                    {
-                        // println!("Dividend yeild from metadata : {:.2}%", dividend_yield);
-                        yield_value = dividend_yield * 100.0;
-                    }
-                    if let Some(pr) = item.summary_detail.as_ref().and_then(|d| d.payout_ratio) {
-                        // println!("Dividend yeild from metadata : {:.2}%", dividend_yield);
-                        payout_ratio = pr * 100.0;
-                    }
-                });
-            } else {
-                println!("⚠️ No info about stock");
-            }
-        }
-        None => {
-            println!("⚠️ No info about stock");
+    // Yields are computed the same way whatever provider is used. The one reported by
+    // provider is kept for reference only
+    profile.reported_yield = details.div_yield;
+    if profile.share_price > 0.0 {
+        let to_yield = |div: f64| div / profile.share_price * 100.0;
+        profile.forward_yield = profile.forward_div.map(to_yield);
+        // Trailing dividends include ones of current year, which are dropped below
+        if !div_history.is_empty() {
+            profile.ttm_yield = Some(to_yield(div_history.trailing_twelve_months(as_of)));
         }
+        profile.last_fy_yield = calculate_divy(
+            &div_history,
+            profile.share_price,
+            profile.as_of.year().to_string().as_ref(),
+        )
+        .map_err(with_context)?;
     }
+    profile.div_yield = profile.yield_of(options.yield_kind);
+    log::info!(
+        "Stock price: {}, Div Yield[%] forward: {:.2?}, TTM: {:.2?}, last FY: {:.2?}, reported by provider: {:.2?}",
+        profile.share_price,
+        profile.forward_yield,
+        profile.ttm_yield,
+        profile.last_fy_yield,
+        profile.reported_yield
+    );
 
-    // get dividend data
-    let (curr_div, dgr, dgr5y, dgr3y, dgr1y, dgr1y_ttm, years_of_growth, div_history) =
-        get_yahoo_dividiend_data(&provider, &company, currency.as_ref())?;
+    let div_history = calculate_dividend_metrics(&mut profile, div_history, options.dgr_estimator)
+        .map_err(with_context)?;
 
-    let (annuallized_div, frequency) =
-        match calculate_annualized_div(&div_history, (Utc::now().year() - 1).to_string().as_ref())?
-        {
-            Some((annuallized_div, frequency)) => (Some(annuallized_div), Some(frequency)),
-            None => (None, None),
-        };
-    log::info!("Annualized dividend: {annuallized_div:?}, annual frequency: {frequency:?}");
-
-    //sector_desc,
-
-    Ok((
-        share_price,
-        curr_div,
-        Some(yield_value),
-        frequency,
-        dgr,
-        dgr5y,
-        dgr3y,
-        dgr1y,
-        dgr1y_ttm,
-        years_of_growth,
-        Some(payout_ratio),
-        None,
-    ))
-}
-
-pub fn get_polygon_data(
-    company: &str,
-) -> Result<
-    (
-        f64,
-        Option<f64>,
-        Option<f64>,
-        Option<i64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<f64>,
-        Option<i64>,
-        Option<f64>,
-        Option<String>,
-    ),
-    &'static str,
-> {
-    let mut query_params = HashMap::new();
-    query_params.insert("ticker", company);
-
-    let client = RESTClient::new(None, None);
-    // Get all dividend data we can have
-    tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap()
-        .block_on(async {
-            let (curr_div, dgr, dgr5y, dgr3y, dgr1y, dgr1y_ttm, years_of_growth, div_history) =
-                get_dividiend_data(&client, &query_params).await?;
-
-            let sector_desc = get_company_details(&client, company).await?;
-
-            let mut close_query_params = HashMap::new();
-            close_query_params.insert("adjusted", "true");
-
-            let mut run = true;
-            let mut resp = polygon_client::types::StockEquitiesPreviousCloseResponse {
-                ticker: "".to_owned(),
-                results: Some(vec![]),
-                count: Some(0),
-                query_count: Some(0),
-                results_count: Some(0),
-                status: Some("OK".to_owned()),
-                adjusted: Some(false),
-            };
-            while run {
-                let maybe_resp = client
-                    .stock_equities_previous_close(company, &HashMap::new())
-                    .await;
-                log::info!("RESPONSE(STOCK EQUITIES): {maybe_resp:#?}");
-                (resp, run) = match should_try_again(maybe_resp, resp) {
-                    Ok((resp, run)) => (resp, run),
-                    Err(_) => {
-                        return Ok::<
-                            (
-                                f64,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<i64>,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<i64>,
-                                Option<f64>,
-                                Option<String>,
-                            ),
-                            &'static str,
-                        >((
-                            0.0,
-                            curr_div,
-                            None,
-                            None,
-                            dgr,
-                            dgr5y,
-                            dgr3y,
-                            dgr1y,
-                            dgr1y_ttm,
-                            years_of_growth,
-                            None,
-                            sector_desc,
-                        ))
-                    }
-                };
-            }
+    let annuallized_div = match calculate_annualized_div(
+        &div_history,
+        (profile.as_of.year() - 1).to_string().as_ref(),
+    )
+    .map_err(with_context)?
+    {
+        Some((annuallized_div, frequency)) => {
+            profile.frequency = profile
+                .payment_frequency
+                .and_then(|f| f.frequency.payments_per_year())
+                .map(|payments| payments as i64)
+                .or(Some(frequency));
+            Some(annuallized_div)
+        }
+        None => None,
+    };
+    log::info!(
+        "Annualized dividend: {annuallized_div:?}, annual frequency: {:?}",
+        profile.frequency
+    );
 
-            let share_price = match resp.results {
-                Some(ref results) => {
-                    let share_price = results
-                        .iter()
-                        .next()
-                        .ok_or("Error reading previous date share price")?
-                        .c;
-                    log::info!("Stock price: {share_price}");
-                    share_price
-                }
-                None => {
-                    log::info!("No stock price data found");
-                    return Ok::<
-                        (
-                            f64,
-                            Option<f64>,
-                            Option<f64>,
-                            Option<i64>,
-                            Option<f64>,
-                            Option<f64>,
-                            Option<f64>,
-                            Option<f64>,
-                            Option<f64>,
-                            Option<i64>,
-                            Option<f64>,
-                            Option<String>,
-                        ),
-                        &'static str,
-                    >((
-                        0.0,
-                        curr_div,
-                        None,
-                        None,
-                        dgr,
-                        dgr5y,
-                        dgr3y,
-                        dgr1y,
-                        dgr1y_ttm,
-                        years_of_growth,
-                        None,
-                        sector_desc,
-                    ));
-                }
-            };
-
-            let divy = calculate_divy(
-                &div_history,
-                share_price,
-                Utc::now().year().to_string().as_ref(),
-            )?;
-            log::info!("Stock price: {share_price}, Div Yield[%]: {divy:.2?}");
-
-            let (annuallized_div, frequency) = match calculate_annualized_div(
-                &div_history,
-                (Utc::now().year() - 1).to_string().as_ref(),
-            )? {
-                Some((annuallized_div, frequency)) => (Some(annuallized_div), Some(frequency)),
-                None => (None, None),
-            };
-            log::info!("Annualized dividend: {annuallized_div:?}, annual frequency: {frequency:?}");
-
-            run = true;
-            let mut resp = polygon_client::types::ReferenceStockFinancialsVXResponse {
-                next_url: None,
-                results: vec![],
-                status: "OK".to_owned(),
-                request_id: None,
-            };
-            while run {
-                let maybe_resp = client.reference_stock_financials_vx(&query_params).await;
-                log::info!("RESPONSE(STOCK FINANCIALS): {maybe_resp:#?}");
-                (resp, run) = match should_try_again(maybe_resp, resp) {
-                    Ok((resp, run)) => (resp, run),
-                    Err(_) => {
-                        return Ok::<
-                            (
-                                f64,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<i64>,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<f64>,
-                                Option<i64>,
-                                Option<f64>,
-                                Option<String>,
-                            ),
-                            &'static str,
-                        >((
-                            share_price,
-                            curr_div,
-                            divy,
-                            frequency,
-                            dgr,
-                            dgr5y,
-                            dgr3y,
-                            dgr1y,
-                            dgr1y_ttm,
-                            years_of_growth,
-                            None,
-                            sector_desc,
-                        ))
-                    }
-                };
+    // Reports count shares as they were at the time, so dividends are taken as paid
+    profile.payout_ratio = match details.payout_ratio {
+        Some(payout_ratio) => Some(payout_ratio),
+        None => match get_payout_rate(&reports, &div_history.raw()).map_err(with_context)? {
+            Some((payout_ratio, periods)) => {
+                log::info!("Payout ratio[%]: {payout_ratio:.2} of {periods:?}");
+                profile.payout_periods = periods;
+                Some(payout_ratio)
             }
-
-            let payout_rate = get_annual_payout_rate(&resp, &div_history)?;
-
-            return Ok::<
-                (
-                    f64,
-                    Option<f64>,
-                    Option<f64>,
-                    Option<i64>,
-                    Option<f64>,
-                    Option<f64>,
-                    Option<f64>,
-                    Option<f64>,
-                    Option<f64>,
-                    Option<i64>,
-                    Option<f64>,
-                    Option<String>,
-                ),
-                &'static str,
-            >((
-                share_price,
-                curr_div,
-                divy,
-                frequency,
-                dgr,
-                dgr5y,
-                dgr3y,
-                dgr1y,
-                dgr1y_ttm,
-                years_of_growth,
-                payout_rate,
-                sector_desc,
-            ));
-        })
-}
-
-fn get_net_cash_flow(
-    fd: &polygon_client::types::FinancialDimensions,
-    company_name: &str,
-    fiscal_year: &str,
-    fiscal_period: &str,
-) -> Result<Option<f64>, &'static str> {
-    let net_value = if let Some(ismap) = &fd.cash_flow_statement {
-        let net_value = if ismap.contains_key("net_cash_flow_from_operating_activities") {
-            let net_cash_flow = ismap
-                .get("net_cash_flow_from_operating_activities")
-                .expect("Error getting net_cash_flow_from_operating_activities");
-            let net_value = net_cash_flow.value.clone().unwrap();
-            let net_unit = net_cash_flow.unit.clone().unwrap();
-            let net_label = net_cash_flow.label.clone().unwrap();
-            log::info!(
-                "{}: {} {} net cash flow: {} of {}, labeled as {}",
-                company_name,
-                fiscal_year,
-                fiscal_period,
-                net_value,
-                net_unit,
-                net_label
-            );
-
-            // curr_div * num_shares  / net_value
-            Some(net_value)
-        } else {
-            log::info!("Error: Implement missing cash flow operating statement");
-            None
-        };
-        net_value
-    } else {
-        log::info!("Error: Implement missing cash flow statement");
-        None
+            None => None,
+        },
     };
-    Ok(net_value)
-}
+    calculate_annual_payouts(&mut profile, &reports, &div_history.raw()).map_err(with_context)?;
 
-fn get_basic_average_shares(
-    fd: &polygon_client::types::FinancialDimensions,
-    company_name: &str,
-    fiscal_year: &str,
-    fiscal_period: &str,
-) -> Result<Option<f64>, &'static str> {
-    let basic_average_shares = if let Some(ismap) = &fd.income_statement {
-        let basic_average_shares = if ismap.contains_key("basic_average_shares") {
-            let basic_average_shares = ismap
-                .get("basic_average_shares")
-                .expect("Error getting basic_average_shares");
-            let value = basic_average_shares.value.clone().unwrap();
-            let unit = basic_average_shares.unit.clone().unwrap();
-            let label = basic_average_shares.label.clone().unwrap();
-            log::info!(
-                "{}: {} {} basic average shares: {} of {}, labeled as {}",
-                company_name,
-                fiscal_year,
-                fiscal_period,
-                value,
-                unit,
-                label
-            );
-            Some(value)
-        } else {
-            None
-        };
-        basic_average_shares
-    } else {
-        log::warn!("Implement missing net_cash_flow_continuing");
-        None
-    };
-    Ok(basic_average_shares)
+    Ok(profile)
 }
 
 fn calculate_annualized_div(
-    div_history: &Vec<(String, f64)>,
+    div_history: &DividendSeries,
     fiscal_year: &str,
-) -> Result<Option<(f64, i64)>, &'static str> {
+) -> Result<Option<(f64, i64)>, Error> {
     if div_history.len() == 0 {
         return Ok(None);
     }
 
+    let fiscal_year = fiscal_year
+        .parse::<i32>()
+        .map_err(|e| Error::parse("fiscal year", e))?;
     let mut frequency = 0;
-    let annuallized_div = div_history
-        .iter()
-        .filter(|x| {
-            NaiveDate::parse_from_str(&x.0, "%Y-%m-%d")
-                .expect("Dividend date parsing error")
-                .year()
-                == fiscal_year
-                    .parse::<i32>()
-                    .expect("Unable to parse fiscal year")
-        })
-        .fold(0.0, |mut acc, num| {
-            acc += num.1;
+    let mut annuallized_div = 0.0;
+    div_history.iter().try_for_each(|x| {
+        let year = div_history.year(x);
+        if year == fiscal_year {
+            annuallized_div += x.amount;
             frequency += 1;
-            acc
-        });
+        }
+        Ok::<(), Error>(())
+    })?;
     Ok(Some((annuallized_div, frequency)))
 }
 
-/// Calculate consecutive years of growing dividend, not including current year
-fn calculate_consecutive_years_of_growth(
-    div_history: &Vec<(String, f64)>,
+/// Calculate dividend streaks up to the previous year, as current year has no full data yet
+fn calculate_streaks(
+    div_history: &DividendSeries,
     current_year: &str,
-) -> Result<Option<i64>, &'static str> {
+) -> Result<Option<Streaks>, Error> {
     let current_year = current_year
         .parse::<i32>()
-        .expect("Unable to parse currrent year");
-    let mut annual_div: BTreeMap<i32, f64> = BTreeMap::new();
+        .map_err(|e| Error::parse("current year", e))?;
 
     if div_history.len() == 0 {
         return Ok(None);
     }
 
-    div_history.iter().try_for_each(|x| {
-        let year = NaiveDate::parse_from_str(&x.0, "%Y-%m-%d")
-            .map_err(|_| "Error parsing dividend year")?
-            .year();
-        // Skip current year (no full data yet)
-        if year != current_year {
-            let possible_sum = annual_div.get_mut(&year);
-            match possible_sum {
-                Some(s) => *s += x.1,
-                None => {
-                    annual_div.insert(year, x.1);
-                    ()
-                }
-            }
-        }
-        Ok::<(), &str>(())
-    })?;
-
+    let annual_div = div_history.annual(current_year);
     if annual_div.len() == 0 {
         log::info!("No annual dividend data found");
         return Ok(None);
     }
+    annual_div
+        .iter()
+        .for_each(|(year, sum)| log::info!("Annual dividend year: {year} annual_div: {sum}"));
 
-    let mut num_consecutive_years = 0;
-    let mut from_newer_to_older = annual_div.iter().rev();
-    let (next_year, mut next_year_div) = from_newer_to_older
-        .next()
-        .ok_or("Error: unable to get dividend")?;
-    log::info!("Annual dividend year: {next_year} annual_div: {next_year_div}");
-    'petla: for (year, sum) in from_newer_to_older {
-        log::info!("Annual dividend year: {year} annual_div: {sum}");
-        if sum < next_year_div {
-            num_consecutive_years += 1;
-            next_year_div = sum;
-        } else {
-            break 'petla;
-        }
-    }
-
-    Ok(Some(num_consecutive_years))
+    Ok(Some(streak::streaks(&annual_div, current_year - 1)))
 }
 
 fn get_annual_payout_rate(
-    resp: &polygon_client::types::ReferenceStockFinancialsVXResponse,
-    div_history: &Vec<(String, f64)>,
-) -> Result<Option<f64>, &'static str> {
+    reports: &[FinancialReport],
+    div_history: &DividendSeries,
+) -> Result<Option<f64>, Error> {
     // Pick the most recent annual report
-    let res = resp
-        .results
+    let res = reports
         .iter()
-        .filter(|x| x.timeframe == "annual")
-        .max_by(|x, y| {
-            let x_date = NaiveDate::parse_from_str(
-                &x.end_date.clone().expect("Missing end date"),
-                "%Y-%m-%d",
-            )
-            .expect("Wrong end date format");
-            let y_date = NaiveDate::parse_from_str(
-                &y.end_date.clone().expect("Missing end date"),
-                "%Y-%m-%d",
-            )
-            .expect("Wrong end date format");
-            x_date.cmp(&y_date)
-        });
+        .filter(|x| x.timeframe == ReportTimeframe::Annual && x.end_date.is_some())
+        .max_by_key(|x| x.end_date);
 
     if let Some(r) = res {
         log::info!(
-            "{:?}: start date: {:?}, end date: {:?}, fiscal_year: {}, timeframe: {} fiscal_period: {}",
-            r.tickers,
+            "start date: {:?}, end date: {:?}, fiscal_year: {}, timeframe: {:?} fiscal_period: {}",
             r.start_date,
             r.end_date,
             r.fiscal_year,
@@ -1130,18 +576,8 @@ fn get_annual_payout_rate(
             None => None,
         };
 
-        let net_value = get_net_cash_flow(
-            &r.financials,
-            r.company_name.as_ref(),
-            r.fiscal_year.as_ref(),
-            r.fiscal_period.as_ref(),
-        )?;
-        let basic_average_shares = get_basic_average_shares(
-            &r.financials,
-            r.company_name.as_ref(),
-            r.fiscal_year.as_ref(),
-            r.fiscal_period.as_ref(),
-        )?;
+        let net_value = r.net_cash_flow_from_operating_activities;
+        let basic_average_shares = r.basic_average_shares;
         let payout_rate = match (basic_average_shares, annuallized_div, net_value) {
             (Some(num_shares), Some(annuallized_div), Some(net_value)) => Some(
                 calculate_payout_ratio(annuallized_div, num_shares, net_value)?,
@@ -1158,84 +594,151 @@ fn get_annual_payout_rate(
     }
 }
 
-fn get_quaterly_payout_rate(
-    resp: &polygon_client::types::ReferenceStockFinancialsVXResponse,
-    div_history: &Vec<(String, f64)>,
-) -> Result<f64, &'static str> {
-    // Pick the most recent finished period
-    let res = resp
-        .results
+/// Fill in payout ratios of free cash flow and earnings and coverage of dividends by free cash
+/// flow, according to the most recent annual report
+fn calculate_annual_payouts(
+    profile: &mut DividendProfile,
+    reports: &[FinancialReport],
+    div_history: &DividendSeries,
+) -> Result<(), Error> {
+    let report = match reports
         .iter()
-        .filter(|x| x.timeframe == "quarterly")
-        .max_by(|x, y| {
-            let x_date = NaiveDate::parse_from_str(
-                &x.end_date.clone().expect("Missing end date"),
-                "%Y-%m-%d",
-            )
-            .expect("Wrong end date format");
-            let y_date = NaiveDate::parse_from_str(
-                &y.end_date.clone().expect("Missing end date"),
-                "%Y-%m-%d",
-            )
-            .expect("Wrong end date format");
-            x_date.cmp(&y_date)
-        })
-        .ok_or("Unable to get most recent financial period")?;
+        .filter(|x| x.timeframe == ReportTimeframe::Annual && x.end_date.is_some())
+        .max_by_key(|x| x.end_date)
+    {
+        Some(report) => report,
+        None => return Ok(()),
+    };
+    let free_cash_flow = report.free_cash_flow();
+    let annuallized_div = calculate_annualized_div(div_history, &report.fiscal_year)?.map(|x| x.0);
 
+    profile.fcf_payout_ratio = match (annuallized_div, report.basic_average_shares, free_cash_flow)
+    {
+        (Some(div), Some(num_shares), Some(fcf)) if fcf > 0.0 => {
+            Some(calculate_payout_ratio(div, num_shares, fcf)?)
+        }
+        _ => None,
+    };
+    profile.eps_payout_ratio = match (annuallized_div, report.diluted_earnings_per_share) {
+        (Some(div), Some(eps)) if eps > 0.0 => Some(div / eps * 100.0),
+        _ => None,
+    };
+    profile.fcf_coverage = match (free_cash_flow, report.dividends_paid) {
+        (Some(fcf), Some(dividends_paid)) if dividends_paid > 0.0 => Some(fcf / dividends_paid),
+        _ => None,
+    };
     log::info!(
-        "{:?}: start date: {:?}, end date: {:?}, fiscal_year: {}, timeframe: {} fiscal_period: {}",
-        res.tickers,
-        res.start_date,
-        res.end_date,
-        res.fiscal_year,
-        res.timeframe,
-        res.fiscal_period
+        "Fiscal year {}: FCF: {free_cash_flow:?}, net income: {:?}, FCF payout[%]: {:.2?}, EPS payout[%]: {:.2?}, FCF coverage: {:.2?}",
+        report.fiscal_year,
+        report.net_income,
+        profile.fcf_payout_ratio,
+        profile.eps_payout_ratio,
+        profile.fcf_coverage
     );
+    Ok(())
+}
 
-    let start_date = NaiveDate::parse_from_str(
-        &res.start_date.clone().expect("Missing start date"),
-        "%Y-%m-%d",
-    )
-    .expect("Wrong start date format");
-    let end_date =
-        NaiveDate::parse_from_str(&res.end_date.clone().expect("Missing end date"), "%Y-%m-%d")
-            .expect("Wrong end date format");
-
-    // Div payout date must be within start and end of quarter
-    let div = div_history
+/// Maximal number of days between end of one quarterly report and start of the next one for
+/// them to be considered consecutive
+const MAX_QUARTERS_GAP: i64 = 7;
+
+/// Payout ratio of twelve trailing months: dividends paid within the last four consecutive
+/// quarters relative to their summed operating cash flow. Returned together with fiscal periods
+/// used e.g. "2023 Q1"
+fn get_ttm_payout_rate(
+    reports: &[FinancialReport],
+    div_history: &DividendSeries,
+) -> Result<Option<(f64, Vec<String>)>, Error> {
+    let mut quarters: Vec<&FinancialReport> = reports
         .iter()
         .filter(|x| {
-            let x_date =
-                NaiveDate::parse_from_str(&x.0, "%Y-%m-%d").expect("Wrong end date format");
-            (start_date < x_date) && (x_date < end_date)
+            x.timeframe == ReportTimeframe::Quarterly
+                && x.start_date.is_some()
+                && x.end_date.is_some()
         })
-        .next()
-        .ok_or("Unable to get dividend from recent financial period")?;
+        .collect();
+    quarters.sort_by_key(|x| std::cmp::Reverse(x.end_date));
+    quarters.truncate(4);
+    if quarters.len() < 4 {
+        log::info!("Less than four quarterly financial reports found");
+        return Ok(None);
+    }
+    quarters.reverse();
+
+    let mut dividends_paid = 0.0;
+    let mut net_value = 0.0;
+    let mut previous_end: Option<NaiveDate> = None;
+    for quarter in &quarters {
+        let start_date = quarter
+            .start_date
+            .ok_or_else(|| Error::missing("start date of quarterly financial report"))?;
+        let end_date = quarter
+            .end_date
+            .ok_or_else(|| Error::missing("end date of quarterly financial report"))?;
+        if let Some(previous_end) = previous_end {
+            if (start_date - previous_end).num_days() > MAX_QUARTERS_GAP {
+                log::info!(
+                    "Quarterly financial reports are not consecutive: {previous_end} - {start_date}"
+                );
+                return Ok(None);
+            }
+        }
+        previous_end = Some(end_date);
+
+        let (num_shares, cash_flow) = match (
+            quarter.basic_average_shares,
+            quarter.net_cash_flow_from_operating_activities,
+        ) {
+            (Some(num_shares), Some(cash_flow)) => (num_shares, cash_flow),
+            _ => {
+                log::info!(
+                    "Missing shares or cash flow in {} {} financial report",
+                    quarter.fiscal_year,
+                    quarter.fiscal_period
+                );
+                return Ok(None);
+            }
+        };
+        // Div payout dates must be within start and end of quarter
+        let div: f64 = div_history
+            .iter()
+            .filter(|x| start_date <= div_history.date(x) && div_history.date(x) <= end_date)
+            .map(|x| x.amount)
+            .sum();
+        dividends_paid += div * num_shares;
+        net_value += cash_flow;
+    }
 
-    let net_value = get_net_cash_flow(
-        &res.financials,
-        res.company_name.as_ref(),
-        res.fiscal_year.as_ref(),
-        res.fiscal_period.as_ref(),
-    )?;
-    let basic_average_shares = get_basic_average_shares(
-        &res.financials,
-        res.company_name.as_ref(),
-        res.fiscal_year.as_ref(),
-        res.fiscal_period.as_ref(),
-    )?;
+    let periods = quarters
+        .iter()
+        .map(|x| format!("{} {}", x.fiscal_year, x.fiscal_period))
+        .collect();
+    Ok(Some((
+        calculate_payout_ratio(dividends_paid, 1.0, net_value)?,
+        periods,
+    )))
+}
 
-    let payout_rate = match (basic_average_shares, net_value) {
-        (Some(num_shares), Some(net_value)) => {
-            calculate_payout_ratio(div.1, num_shares, net_value)?
-        }
-        _ => return Err("Unable to get basic shares value from quaterly financial report"),
-    };
-    Ok(payout_rate)
+/// Payout ratio of twelve trailing months, or of the most recent annual report if there are
+/// not enough quarterly ones. Returned together with fiscal periods used
+fn get_payout_rate(
+    reports: &[FinancialReport],
+    div_history: &DividendSeries,
+) -> Result<Option<(f64, Vec<String>)>, Error> {
+    if let Some(ttm) = get_ttm_payout_rate(reports, div_history)? {
+        return Ok(Some(ttm));
+    }
+    let annual_period = reports
+        .iter()
+        .filter(|x| x.timeframe == ReportTimeframe::Annual && x.end_date.is_some())
+        .max_by_key(|x| x.end_date)
+        .map(|x| format!("{} {}", x.fiscal_year, x.fiscal_period));
+    Ok(get_annual_payout_rate(reports, div_history)?
+        .map(|payout_rate| (payout_rate, annual_period.into_iter().collect())))
 }
 
-/// DGR On quaterly basis calculate(make UT)
-fn calculate_payout_ratio(div: f64, num_shares: f64, net_value: f64) -> Result<f64, &'static str> {
+/// Payout ratio[%]: dividend per share paid on all shares, as part of net income
+fn calculate_payout_ratio(div: f64, num_shares: f64, net_value: f64) -> Result<f64, Error> {
     let payout_rate = div * num_shares as f64 / net_value * 100.0;
     Ok(payout_rate)
 }
@@ -1243,10 +746,10 @@ fn calculate_payout_ratio(div: f64, num_shares: f64, net_value: f64) -> Result<f
 /// Calculate dividend yield
 /// Formula : get historical data e.g. from
 fn calculate_divy(
-    div_history: &Vec<(String, f64)>,
+    div_history: &DividendSeries,
     share_price: f64,
     current_year: &str,
-) -> Result<Option<f64>, &'static str> {
+) -> Result<Option<f64>, Error> {
     let dhiter = div_history.iter();
 
     let mut average = 0.0;
@@ -1254,7 +757,7 @@ fn calculate_divy(
 
     let current_year = current_year
         .parse::<i32>()
-        .expect("Unable to parse currrent year");
+        .map_err(|e| Error::parse("current year", e))?;
     let mut annual_div: BTreeMap<i32, f64> = BTreeMap::new();
 
     if div_history.len() == 0 {
@@ -1262,21 +765,19 @@ fn calculate_divy(
     }
 
     div_history.iter().try_for_each(|x| {
-        let year = NaiveDate::parse_from_str(&x.0, "%Y-%m-%d")
-            .map_err(|_| "Error parsing dividend year")?
-            .year();
+        let year = div_history.year(x);
         // Skip current year (no full data yet)
         if year != current_year {
             let possible_sum = annual_div.get_mut(&year);
             match possible_sum {
-                Some(s) => *s += x.1,
+                Some(s) => *s += x.amount,
                 None => {
-                    annual_div.insert(year, x.1);
+                    annual_div.insert(year, x.amount);
                     ()
                 }
             }
         }
-        Ok::<(), &str>(())
+        Ok::<(), Error>(())
     })?;
 
     if annual_div.len() == 0 {
@@ -1287,7 +788,7 @@ fn calculate_divy(
     let mut from_newer_to_older = annual_div.iter().rev();
     let annual_div = from_newer_to_older
         .next()
-        .ok_or("Error: unable to get dividend")?
+        .ok_or_else(|| Error::missing("annual dividend"))?
         .1;
 
     Ok(Some(annual_div / share_price * 100.0))
@@ -1296,15 +797,13 @@ fn calculate_divy(
 /// DGR calculated based on dividends paid within
 /// last twenty four trailing months
 fn calculate_dgr_ttm(
-    div_history: &Vec<(String, f64)>,
-    current_date: &str,
-) -> Result<Option<f64>, &'static str> {
+    div_history: &DividendSeries,
+    current_date: NaiveDate,
+) -> Result<Option<f64>, Error> {
     if div_history.is_empty() {
         return Ok(None);
     }
 
-    let current_date = NaiveDate::parse_from_str(&current_date, "%Y-%m-%d")
-        .map_err(|_| "Error parsing current date ")?;
     let current_year = current_date.year();
     let current_month = current_date.month();
 
@@ -1326,27 +825,26 @@ fn calculate_dgr_ttm(
     // so I can calculate sum of dividends from that period.
     // And then I do the same for similar period but year earlier
 
-    let get_annualized_dividend_within_range = |div_history: &Vec<(String, f64)>,
+    let get_annualized_dividend_within_range = |div_history: &DividendSeries,
                                                 start_month: u32,
                                                 end_month: u32,
                                                 start_year: i32,
                                                 end_year: i32|
-     -> Result<(f64, u8), &'static str> {
+     -> Result<(f64, u8), Error> {
         let mut dividend_sum = 0.0;
         let mut frequency: u8 = 0;
         div_history.iter().try_for_each(|x| {
-            let div_date = NaiveDate::parse_from_str(&x.0, "%Y-%m-%d")
-                .map_err(|_| "Error parsing dividend year")?;
+            let div_date = div_history.date(x);
             let div_year = div_date.year();
             let div_month = div_date.month();
             // sum all dividends which dates fit within range
             if (div_year == end_year && div_month <= end_month)
                 || (div_year == start_year && div_month >= start_month)
             {
-                dividend_sum += x.1;
+                dividend_sum += x.amount;
                 frequency += 1;
             }
-            Ok::<(), &str>(())
+            Ok::<(), Error>(())
         })?;
 
         Ok((dividend_sum, frequency))
@@ -1397,177 +895,562 @@ fn calculate_dgr_ttm(
     Ok(Some(dgr_ttm))
 }
 
-/// DGR On quaterly basis calculate
-fn calculate_dgr(
-    div_history: &Vec<(String, f64)>,
+/// DGR computed with chosen estimator from annual dividends, not including current year
+fn estimate_dgr(
+    div_history: &DividendSeries,
     current_year: &str,
-) -> Result<Option<f64>, &'static str> {
-    let dhiter = div_history.iter();
-
-    let mut average = 0.0;
-    let mut annual_div = 0.0;
-
+    estimator: DgrEstimator,
+) -> Result<Option<GrowthEstimate>, Error> {
     if div_history.len() == 0 {
         return Ok(None);
     }
 
     let current_year = current_year
         .parse::<i32>()
-        .expect("Unable to parse currrent year");
-    let mut annual_div: BTreeMap<i32, f64> = BTreeMap::new();
+        .map_err(|e| Error::parse("current year", e))?;
+    // Skip current year (no full data yet)
+    let annual_div = div_history.annual(current_year);
+    if annual_div.len() == 0 {
+        log::info!("No valid annual dividend data found to compute DGR");
+        return Ok(None);
+    }
 
-    div_history.iter().try_for_each(|x| {
-        let year = NaiveDate::parse_from_str(&x.0, "%Y-%m-%d")
-            .map_err(|_| "Error parsing dividend year")?
-            .year();
-        // Skip current year (no full data yet)
-        if year != current_year {
-            let possible_sum = annual_div.get_mut(&year);
-            match possible_sum {
-                Some(s) => *s += x.1,
-                None => {
-                    annual_div.insert(year, x.1);
-                    ()
-                }
+    Ok(growth::estimate(&annual_div, estimator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round2(val: f64) -> f64 {
+        (val * 100.0).round() / 100.0
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn event(ex_date: &str, amount: f64) -> DividendEvent {
+        DividendEvent::new(date(ex_date), amount)
+    }
+
+    #[test]
+    fn test_dividend_profile_from_replay() -> Result<(), String> {
+        let fixtures =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+        // Responses of Yahoo go through its parser as if they were received
+        let provider = provider::ProviderOptions {
+            replay_dir: Some(fixtures),
+            ..provider::ProviderOptions::new(provider::ProviderKind::Yahoo)
+        }
+        .build()
+        .map_err(|e| e.to_string())?;
+        let options = AnalysisOptions::default();
+
+        let as_of = NaiveDate::from_ymd_opt(2023, 6, 30).unwrap();
+        let profile =
+            get_dividend_profile(&provider, "ABR", as_of, &options).map_err(|e| e.to_string())?;
+        assert_eq!(profile.source, DataSource::Yahoo);
+        assert_eq!(profile.share_price, 12.5);
+        assert_eq!(profile.currency, Some("USD".to_owned()));
+        assert_eq!(profile.reported_yield, Some(12.8));
+        assert_eq!(profile.div_yield, profile.forward_yield);
+        assert_eq!(profile.forward_yield.map(round2), Some(12.8));
+        assert_eq!(profile.ttm_yield.map(round2), Some(6.4));
+        assert_eq!(profile.last_fy_yield.map(round2), Some(12.56));
+        assert_eq!(profile.payout_ratio, Some(95.0));
+        assert_eq!(profile.recent_div, Some(0.4));
+        assert_eq!(profile.frequency, Some(4));
+        assert_eq!(
+            profile.payment_frequency,
+            Some(FrequencyEstimate {
+                frequency: PaymentFrequency::Quarterly,
+                confident: true,
+            })
+        );
+        assert_eq!(profile.forward_div, Some(1.6));
+        assert_eq!(profile.years_of_growth, Some(1));
+        assert_eq!(profile.years_without_cut, Some(1));
+        assert_eq!(profile.years_of_payment, Some(2));
+        assert_eq!(profile.dividend_list, None);
+        assert_eq!(profile.special_div, Some(0.0));
+
+        // Dividends paid after as-of date are not known yet
+        let as_of = NaiveDate::from_ymd_opt(2022, 6, 30).unwrap();
+        let profile =
+            get_dividend_profile(&provider, "ABR", as_of, &options).map_err(|e| e.to_string())?;
+        assert_eq!(profile.recent_div, Some(0.38));
+        assert_eq!(profile.years_of_growth, Some(0));
+
+        // TTM yield spans dividends of both years
+        assert_eq!(profile.ttm_yield.map(round2), Some(12.16));
+        let options = AnalysisOptions {
+            yield_kind: YieldKind::LastFy,
+            ..Default::default()
+        };
+        let profile =
+            get_dividend_profile(&provider, "ABR", as_of, &options).map_err(|e| e.to_string())?;
+        assert_eq!(profile.div_yield.map(round2), Some(11.44));
+
+        let err = get_dividend_profile(&provider, "ABM", as_of, &options).unwrap_err();
+        assert_eq!(err.ticker(), Some("ABM"));
+        Ok(())
+    }
+
+    /// Provider serving given dividends, as reported by provider of given kind
+    struct EventsProvider {
+        source: DataSource,
+        events: Vec<DividendEvent>,
+        splits: Vec<SplitEvent>,
+    }
+
+    impl DividendDataProvider for EventsProvider {
+        fn source(&self) -> DataSource {
+            self.source
+        }
+
+        fn quote(&self, _symbol: &str, _as_of: NaiveDate) -> Result<provider::Quote, Error> {
+            Ok(provider::Quote {
+                price: 50.0,
+                currency: Some("USD".to_owned()),
+            })
+        }
+
+        fn dividend_history(&self, _symbol: &str) -> Result<DividendSeries, Error> {
+            Ok(DividendSeries::new(self.events.clone()))
+        }
+
+        fn splits(&self, _symbol: &str) -> Result<Vec<SplitEvent>, Error> {
+            Ok(self.splits.clone())
+        }
+
+        fn company_details(&self, _symbol: &str) -> Result<provider::CompanyDetails, Error> {
+            Ok(provider::CompanyDetails::default())
+        }
+
+        fn financials(&self, _symbol: &str) -> Result<Vec<FinancialReport>, Error> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn test_dividend_basis_uniform_across_providers() -> Result<(), String> {
+        // Quarterly dividends raised every year, December ones paid in January
+        let mut yahoo_events = vec![];
+        let mut polygon_events = vec![];
+        for (year, amount) in (2018..2024).zip([0.2, 0.22, 0.24, 0.26, 0.28, 0.3]) {
+            for (month, day) in [(3, 10), (6, 10), (9, 10), (12, 20)] {
+                let ex_date = NaiveDate::from_ymd_opt(year, month, day).unwrap();
+                // Yahoo reports ex-dividend date only
+                yahoo_events.push(DividendEvent::new(ex_date, amount));
+                // Polygon reports also other dates, which are ignored with ex-date basis
+                let mut event = DividendEvent::new(ex_date, amount);
+                event.pay_date = Some(ex_date + chrono::Duration::days(20));
+                event.declaration_date = Some(ex_date - chrono::Duration::days(30));
+                event.currency = Some("USD".to_owned());
+                event.declared_frequency = Some(4);
+                polygon_events.push(event);
             }
         }
-        Ok::<(), &str>(())
-    })?;
+        let yahoo = EventsProvider {
+            source: DataSource::Yahoo,
+            events: yahoo_events,
+            splits: vec![],
+        };
+        let polygon = EventsProvider {
+            source: DataSource::Polygon,
+            events: polygon_events,
+            splits: vec![],
+        };
 
-    if annual_div.len() == 0 {
-        log::info!("No valid annual dividend data found to compute DGR");
-        return Ok(None);
+        let as_of = date("2024-03-15");
+        let options = AnalysisOptions::default();
+        let from_yahoo =
+            get_dividend_profile(&yahoo, "ABM", as_of, &options).map_err(|e| e.to_string())?;
+        let from_polygon =
+            get_dividend_profile(&polygon, "ABM", as_of, &options).map_err(|e| e.to_string())?;
+        assert_eq!(
+            from_polygon,
+            DividendProfile {
+                source: DataSource::Yahoo,
+                ..from_yahoo.clone()
+            }
+        );
+        assert_eq!(from_yahoo.frequency, Some(4));
+        assert_eq!(from_yahoo.years_of_growth, Some(5));
+
+        // With pay-date basis December dividend counts towards the next year
+        let options = AnalysisOptions {
+            basis: DividendBasis::PayDate,
+            ..Default::default()
+        };
+        let by_pay_date =
+            get_dividend_profile(&polygon, "ABM", as_of, &options).map_err(|e| e.to_string())?;
+        assert_eq!(by_pay_date.frequency, Some(4));
+        assert_eq!(by_pay_date.dgr_1y.map(round2), Some(7.27));
+        assert_eq!(from_polygon.dgr_1y.map(round2), Some(7.14));
+        Ok(())
     }
-    // Update data with zeros when there was no dividends in a given historical period
 
-    let oldest_year = annual_div
-        .iter()
-        .next()
-        .ok_or("Error: unable to get dividend")?
-        .0;
-    for y in *oldest_year..current_year {
-        if annual_div.contains_key(&y) == false {
-            annual_div.insert(y, 0.0);
-            log::info!("Company was having a gap in paying divdends at {y}");
+    /// Provider counting requests of financial reports
+    struct CountingProvider {
+        inner: EventsProvider,
+        financials: std::sync::atomic::AtomicUsize,
+    }
+
+    impl DividendDataProvider for CountingProvider {
+        fn source(&self) -> DataSource {
+            self.inner.source()
+        }
+
+        fn quote(&self, symbol: &str, as_of: NaiveDate) -> Result<provider::Quote, Error> {
+            self.inner.quote(symbol, as_of)
+        }
+
+        fn dividend_history(&self, symbol: &str) -> Result<DividendSeries, Error> {
+            self.inner.dividend_history(symbol)
+        }
+
+        fn splits(&self, symbol: &str) -> Result<Vec<SplitEvent>, Error> {
+            self.inner.splits(symbol)
+        }
+
+        fn dividends_and_splits(
+            &self,
+            symbol: &str,
+        ) -> Result<(DividendSeries, Vec<SplitEvent>), Error> {
+            self.inner.dividends_and_splits(symbol)
+        }
+
+        fn company_details(&self, symbol: &str) -> Result<provider::CompanyDetails, Error> {
+            self.inner.company_details(symbol)
+        }
+
+        fn financials(&self, symbol: &str) -> Result<Vec<FinancialReport>, Error> {
+            self.financials
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.inner.financials(symbol)
         }
     }
 
-    let mut from_newer_to_older = annual_div.iter().rev();
-    let (next_year, mut next_year_div) = from_newer_to_older
-        .next()
-        .ok_or("Error: unable to get devidend")?;
-
-    log::info!("DGR: Annual dividend year: {next_year} annual_div: {next_year_div}");
-    let mut num_averages = 0;
-    for (year, sum) in from_newer_to_older {
-        log::info!("DGR: Annual dividend year: {year} annual_div: {sum}");
-        if *sum > 0.0 {
-            average += (next_year_div / sum - 1.0) * 100.0;
-        } else {
-            // If next year dividend is positive and previous one is zero then
-            // increase was by 100%
-            if *next_year_div > 0.0 {
-                average += 100.0;
-            } else {
-                average += 0.0;
+    #[test]
+    fn test_financials_fetched_once() -> Result<(), String> {
+        let provider = CountingProvider {
+            inner: EventsProvider {
+                source: DataSource::Polygon,
+                events: vec![event("2023-03-10", 0.3), event("2023-06-10", 0.3)],
+                splits: vec![],
+            },
+            financials: std::sync::atomic::AtomicUsize::new(0),
+        };
+        let options = AnalysisOptions {
+            basis: DividendBasis::DeclaredFiscalYear,
+            ..Default::default()
+        };
+        get_dividend_profile(&provider, "ABM", date("2024-03-15"), &options)
+            .map_err(|e| e.to_string())?;
+        assert_eq!(
+            provider
+                .financials
+                .load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_special_dividends_excluded() -> Result<(), String> {
+        let mut events = vec![];
+        for year in 2020..2024 {
+            for month in [3, 6, 9, 12] {
+                let ex_date = NaiveDate::from_ymd_opt(year, month, 10).unwrap();
+                events.push(DividendEvent::new(ex_date, 0.25));
             }
         }
-        next_year_div = sum;
-        num_averages += 1;
+        // One-off payment reported as regular one, e.g. by Yahoo
+        events.push(event("2023-07-20", 1.5));
+        let provider = EventsProvider {
+            source: DataSource::Yahoo,
+            events,
+            splits: vec![],
+        };
+
+        let as_of = date("2024-03-15");
+        let options = AnalysisOptions::default();
+        let profile =
+            get_dividend_profile(&provider, "ABM", as_of, &options).map_err(|e| e.to_string())?;
+        assert_eq!(profile.special_div, Some(1.5));
+        assert_eq!(profile.frequency, Some(4));
+        assert_eq!(profile.forward_div, Some(1.0));
+        assert_eq!(profile.div_yield.map(round2), Some(2.0));
+        assert_eq!(profile.dgr_1y, Some(0.0));
+
+        let options = AnalysisOptions {
+            include_special: true,
+            ..Default::default()
+        };
+        let profile =
+            get_dividend_profile(&provider, "ABM", as_of, &options).map_err(|e| e.to_string())?;
+        assert_eq!(profile.special_div, Some(1.5));
+        // Special payment is not a part of payment schedule
+        assert_eq!(profile.frequency, Some(4));
+        assert_eq!(profile.dgr_1y, Some(150.0));
+        Ok(())
     }
 
-    if num_averages == 0 {
-        Ok(Some(0.0))
-    } else {
-        Ok(Some(average / num_averages as f64))
+    #[test]
+    fn test_split_adjusted_dividend_history() -> Result<(), String> {
+        // Dividend raised by 10% every year, shares split 2-for-1 in the middle of 2021
+        let mut events = vec![];
+        for (year, amount) in (2018..2024).zip([1.0, 1.1, 1.21, 0.6655, 0.73205, 0.805255]) {
+            for month in [3, 6, 9, 12] {
+                let ex_date = NaiveDate::from_ymd_opt(year, month, 10).unwrap();
+                let amount = if year == 2021 && month <= 6 {
+                    amount * 2.0
+                } else {
+                    amount
+                };
+                events.push(DividendEvent::new(ex_date, amount));
+            }
+        }
+        let split = SplitEvent {
+            date: date("2021-07-01"),
+            from: 1.0,
+            to: 2.0,
+        };
+        let provider = EventsProvider {
+            source: DataSource::Polygon,
+            events,
+            splits: vec![split],
+        };
+
+        let options = AnalysisOptions::default();
+        let profile = get_dividend_profile(&provider, "ABM", date("2024-01-15"), &options)
+            .map_err(|e| e.to_string())?;
+        assert_eq!(profile.years_of_growth, Some(5));
+        assert_eq!(profile.dgr_5y.map(round2), Some(10.0));
+        assert_eq!(profile.dgr_1y.map(round2), Some(10.0));
+        assert_eq!(profile.special_div, Some(0.0));
+
+        // Split not known yet as of given date is not applied
+        let profile = get_dividend_profile(&provider, "ABM", date("2021-06-30"), &options)
+            .map_err(|e| e.to_string())?;
+        assert_eq!(profile.recent_div.map(round2), Some(1.21));
+
+        // Raw amounts show a cut in the year of split
+        let raw = provider
+            .dividend_history("ABM")
+            .map_err(|e| e.to_string())?
+            .split_adjusted(&[split])
+            .raw();
+        assert_eq!(streak::streaks(&raw.annual(2024), 2023).growth, 1);
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_dgr_estimator_selection() -> Result<(), String> {
+        // Dividend growing by 10% a year, with no dividend in 2021
+        let mut events = vec![];
+        for (year, amount) in [(2019, 1.0), (2020, 1.1), (2022, 1.331), (2023, 1.4641)] {
+            let ex_date = NaiveDate::from_ymd_opt(year, 6, 10).unwrap();
+            events.push(DividendEvent::new(ex_date, amount));
+        }
+        let provider = EventsProvider {
+            source: DataSource::Yahoo,
+            events,
+            splits: vec![],
+        };
 
-    fn round2(val: f64) -> f64 {
-        (val * 100.0).round() / 100.0
+        let as_of = date("2024-03-15");
+        let profile = get_dividend_profile(&provider, "ABM", as_of, &AnalysisOptions::default())
+            .map_err(|e| e.to_string())?;
+        assert_eq!(profile.dgr_5y.map(round2), Some(5.0));
+        assert_eq!(profile.dgr_10y_r_squared, None);
+
+        let options = AnalysisOptions {
+            dgr_estimator: DgrEstimator::Regression,
+            ..Default::default()
+        };
+        let profile =
+            get_dividend_profile(&provider, "ABM", as_of, &options).map_err(|e| e.to_string())?;
+        assert_eq!(profile.dgr_5y.map(round2), Some(10.0));
+        assert_eq!(profile.dgr_10y_r_squared.map(round2), Some(1.0));
+        Ok(())
     }
 
     #[test]
-    fn test_calulate_divy() -> Result<(), String> {
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 0.5),
-            ("2023-07-01".to_owned(), 0.5),
-            ("2023-11-01".to_owned(), 0.5),
+    fn test_calculate_annual_payouts() -> Result<(), String> {
+        let div_history = DividendSeries::new(
+            ["2022-03-10", "2022-06-10", "2022-09-10", "2022-12-10"]
+                .iter()
+                .map(|d| event(d, 0.5))
+                .collect(),
+        );
+        let report = FinancialReport {
+            fiscal_year: "2022".to_owned(),
+            fiscal_period: "FY".to_owned(),
+            end_date: Some(date("2022-12-31")),
+            net_cash_flow_from_operating_activities: Some(1000.0),
+            basic_average_shares: Some(100.0),
+            capital_expenditure: Some(400.0),
+            diluted_earnings_per_share: Some(5.0),
+            dividends_paid: Some(200.0),
+            ..Default::default()
+        };
+        let mut profile = DividendProfile::new("ABM", DataSource::Polygon, date("2023-03-01"));
+        calculate_annual_payouts(&mut profile, &[report.clone()], &div_history)
+            .map_err(|e| e.to_string())?;
+        assert_eq!(profile.fcf_payout_ratio.map(round2), Some(33.33));
+        assert_eq!(profile.eps_payout_ratio, Some(40.0));
+        assert_eq!(profile.fcf_coverage, Some(3.0));
+        assert_eq!(
+            profile.payout_of(PayoutBasis::DividendsPaid).map(round2),
+            Some(33.33)
+        );
+
+        // Capital expenditure exceeding operating cash flow leaves dividends uncovered
+        let report = FinancialReport {
+            capital_expenditure: Some(1200.0),
+            ..report
+        };
+        calculate_annual_payouts(&mut profile, &[report], &div_history)
+            .map_err(|e| e.to_string())?;
+        assert_eq!(profile.fcf_payout_ratio, None);
+        assert_eq!(profile.fcf_coverage, Some(-1.0));
+        assert_eq!(profile.payout_of(PayoutBasis::DividendsPaid), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_payout_rate_ttm_and_annual_fallback() -> Result<(), String> {
+        let div_history = DividendSeries::new(
+            ["2022-02-10", "2022-05-10", "2022-08-10", "2022-11-10"]
+                .iter()
+                .map(|d| event(d, 0.5))
+                .collect(),
+        );
+        let quarter = |year: &str, period: &str, start: &str, end: &str| FinancialReport {
+            fiscal_year: year.to_owned(),
+            fiscal_period: period.to_owned(),
+            timeframe: ReportTimeframe::Quarterly,
+            start_date: Some(date(start)),
+            end_date: Some(date(end)),
+            net_cash_flow_from_operating_activities: Some(100.0),
+            basic_average_shares: Some(10.0),
+            ..Default::default()
+        };
+        let annual = FinancialReport {
+            fiscal_year: "2022".to_owned(),
+            fiscal_period: "FY".to_owned(),
+            start_date: Some(date("2022-01-01")),
+            end_date: Some(date("2022-12-31")),
+            net_cash_flow_from_operating_activities: Some(500.0),
+            basic_average_shares: Some(10.0),
+            ..Default::default()
+        };
+        let mut reports = vec![
+            annual,
+            quarter("2021", "Q4", "2021-10-01", "2021-12-31"),
+            quarter("2022", "Q1", "2022-01-01", "2022-03-31"),
+            quarter("2022", "Q2", "2022-04-01", "2022-06-30"),
+            quarter("2022", "Q3", "2022-07-01", "2022-09-30"),
+            quarter("2022", "Q4", "2022-10-01", "2022-12-31"),
         ];
+
+        let (payout_rate, periods) = get_payout_rate(&reports, &div_history)
+            .map_err(|e| e.to_string())?
+            .ok_or("No payout rate")?;
+        assert_eq!(round2(payout_rate), 5.0);
+        assert_eq!(periods, vec!["2022 Q1", "2022 Q2", "2022 Q3", "2022 Q4"]);
+
+        // Without third quarter the last four are not consecutive
+        reports.remove(4);
+        let (payout_rate, periods) = get_payout_rate(&reports, &div_history)
+            .map_err(|e| e.to_string())?
+            .ok_or("No payout rate")?;
+        assert_eq!(round2(payout_rate), 4.0);
+        assert_eq!(periods, vec!["2022 FY"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_calulate_divy() -> Result<(), String> {
+        let div_hists = DividendSeries::new(vec![
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 0.5),
+            event("2023-07-01", 0.5),
+            event("2023-11-01", 0.5),
+        ]);
         assert_eq!(calculate_divy(&div_hists, 100.0, "2024"), Ok(Some(2.0)));
 
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2023-01-01".to_owned(), 1.0),
-            ("2023-04-01".to_owned(), 1.0),
-            ("2023-07-01".to_owned(), 2.0),
-            ("2023-11-01".to_owned(), 4.0),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("2023-01-01", 1.0),
+            event("2023-04-01", 1.0),
+            event("2023-07-01", 2.0),
+            event("2023-11-01", 4.0),
+        ]);
         assert_eq!(calculate_divy(&div_hists, 100.0, "2024"), Ok(Some(8.0)));
         Ok(())
     }
 
     #[test]
     fn test_calculate_dgr_ttm() -> Result<(), String> {
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 0.5),
-            ("2023-07-01".to_owned(), 0.5),
-            ("2023-11-01".to_owned(), 0.5),
-        ];
-        assert_eq!(calculate_dgr_ttm(&div_hists, "2023-12-01"), Ok(None));
-
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 0.5),
-            ("2023-07-01".to_owned(), 0.5),
-            ("2023-11-01".to_owned(), 0.5),
-            ("2022-01-01".to_owned(), 0.5),
-            ("2022-04-01".to_owned(), 0.5),
-            ("2022-07-01".to_owned(), 0.5),
-            ("2022-11-01".to_owned(), 0.5),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 0.5),
+            event("2023-07-01", 0.5),
+            event("2023-11-01", 0.5),
+        ]);
+        assert_eq!(calculate_dgr_ttm(&div_hists, date("2023-12-01")), Ok(None));
+
+        let div_hists = DividendSeries::new(vec![
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 0.5),
+            event("2023-07-01", 0.5),
+            event("2023-11-01", 0.5),
+            event("2022-01-01", 0.5),
+            event("2022-04-01", 0.5),
+            event("2022-07-01", 0.5),
+            event("2022-11-01", 0.5),
+        ]);
         assert_eq!(
-            calculate_dgr_ttm(&div_hists, "2024-12-01"),
+            calculate_dgr_ttm(&div_hists, date("2024-12-01")),
             Ok(Some(-100.0))
         );
 
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2022-01-01".to_owned(), 0.1),
-            ("2022-04-01".to_owned(), 0.9),
-            ("2022-07-01".to_owned(), 1.0),
-            ("2022-11-01".to_owned(), 1.0),
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 0.5),
-            ("2023-07-01".to_owned(), 2.0),
-            ("2023-11-01".to_owned(), 3.0),
-        ];
-        assert_eq!(calculate_dgr_ttm(&div_hists, "2023-12-01"), Ok(Some(100.0)));
-
-        assert_eq!(calculate_dgr(&vec![], "2024-01-01"), Ok(None));
-
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2022-03-01".to_owned(), 0.365),
-            ("2022-06-01".to_owned(), 0.365),
-            ("2022-09-01".to_owned(), 0.365),
-            ("2022-12-01".to_owned(), 0.365),
-            ("2023-03-01".to_owned(), 0.365),
-            ("2023-06-01".to_owned(), 0.125),
-            ("2023-09-01".to_owned(), 0.125),
-            ("2023-12-01".to_owned(), 0.125),
-            ("2024-03-01".to_owned(), 0.125),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("2022-01-01", 0.1),
+            event("2022-04-01", 0.9),
+            event("2022-07-01", 1.0),
+            event("2022-11-01", 1.0),
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 0.5),
+            event("2023-07-01", 2.0),
+            event("2023-11-01", 3.0),
+        ]);
+        assert_eq!(
+            calculate_dgr_ttm(&div_hists, date("2023-12-01")),
+            Ok(Some(100.0))
+        );
+
+        let div_hists = DividendSeries::new(vec![
+            event("2022-03-01", 0.365),
+            event("2022-06-01", 0.365),
+            event("2022-09-01", 0.365),
+            event("2022-12-01", 0.365),
+            event("2023-03-01", 0.365),
+            event("2023-06-01", 0.125),
+            event("2023-09-01", 0.125),
+            event("2023-12-01", 0.125),
+            event("2024-03-01", 0.125),
+        ]);
 
         //0.125*4.0 = 0.5
         //0.365*4.0 = 1.46
         // DGR: (0.5/1.46 - 1.0)*100.0 = -65.753425
         assert_eq!(
             Ok::<f64, &str>(round2(
-                calculate_dgr_ttm(&div_hists, "2024-04-01")
+                calculate_dgr_ttm(&div_hists, date("2024-04-01"))
                     .unwrap()
                     .unwrap()
             )),
@@ -1576,24 +1459,24 @@ mod tests {
 
         // CAG as of 29th of July 2025
 
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2025-05-29".to_string(), 0.35),
-            ("2025-02-27".to_string(), 0.35),
-            ("2024-11-27".to_string(), 0.35),
-            ("2024-08-29".to_string(), 0.35),
-            ("2024-05-30".to_string(), 0.35),
-            ("2024-02-29".to_string(), 0.35),
-            ("2023-11-30".to_string(), 0.35),
-            ("2023-08-31".to_string(), 0.35),
-            ("2023-06-01".to_string(), 0.33),
-            ("2023-03-02".to_string(), 0.33),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("2025-05-29", 0.35),
+            event("2025-02-27", 0.35),
+            event("2024-11-27", 0.35),
+            event("2024-08-29", 0.35),
+            event("2024-05-30", 0.35),
+            event("2024-02-29", 0.35),
+            event("2023-11-30", 0.35),
+            event("2023-08-31", 0.35),
+            event("2023-06-01", 0.33),
+            event("2023-03-02", 0.33),
+        ]);
         //0.35*4.0 = 1.4
         //0.35*4.0 + 0.33 = 1.73
-        // DGR: (1.4/1.73 - 1.0)*100.0 =0.0
+        // DGR: (1.4/1.73 - 1.0)*100.0 = -19.075145
         assert_eq!(
             Ok::<f64, &str>(round2(
-                calculate_dgr_ttm(&div_hists, "2025-06-29")
+                calculate_dgr_ttm(&div_hists, date("2025-06-29"))
                     .unwrap()
                     .unwrap()
             )),
@@ -1602,24 +1485,24 @@ mod tests {
 
         // UPS as of 29th of July 2025
 
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2025-06-05".to_owned(), 1.64),
-            ("2025-03-06".to_owned(), 1.64),
-            ("2024-12-05".to_owned(), 1.63),
-            ("2024-09-05".to_owned(), 1.63),
-            ("2024-05-30".to_owned(), 1.63),
-            ("2024-03-08".to_owned(), 1.63),
-            ("2023-11-30".to_owned(), 1.62),
-            ("2023-08-31".to_owned(), 1.62),
-            ("2023-06-01".to_owned(), 1.62),
-            ("2023-03-10".to_owned(), 1.62),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("2025-06-05", 1.64),
+            event("2025-03-06", 1.64),
+            event("2024-12-05", 1.63),
+            event("2024-09-05", 1.63),
+            event("2024-05-30", 1.63),
+            event("2024-03-08", 1.63),
+            event("2023-11-30", 1.62),
+            event("2023-08-31", 1.62),
+            event("2023-06-01", 1.62),
+            event("2023-03-10", 1.62),
+        ]);
         //1.64*2.0  + 1.63*2.0  = 6.54
         //1.63*2.0  + 1.62*2.0  = 6.5
         // DGR: (6.54/6.5 - 1.0)*100.0 = 0.615385
         assert_eq!(
             Ok::<f64, &str>(round2(
-                calculate_dgr_ttm(&div_hists, "2025-07-29")
+                calculate_dgr_ttm(&div_hists, date("2025-07-29"))
                     .unwrap()
                     .unwrap()
             )),
@@ -1630,7 +1513,7 @@ mod tests {
         // DGR: (4.9/8.12 - 1.0)*100.0 = -39.655172
         assert_eq!(
             Ok::<f64, &str>(round2(
-                calculate_dgr_ttm(&div_hists, "2025-06-29")
+                calculate_dgr_ttm(&div_hists, date("2025-06-29"))
                     .unwrap()
                     .unwrap()
             )),
@@ -1638,78 +1521,78 @@ mod tests {
         );
 
         // ABEV as of 29th of June 2025
-        let div_hists: Vec<(String, f64)> = vec![
-            ("1970-01-01".to_owned(), 0.0550986),
-            ("2005-10-14".to_owned(), 0.005492),
-            ("2006-01-05".to_owned(), 0.001936),
-            ("2006-04-10".to_owned(), 0.01076),
-            ("2006-07-10".to_owned(), 0.011932),
-            ("2006-11-09".to_owned(), 0.0121),
-            ("2007-01-08".to_owned(), 0.014732),
-            ("2007-04-12".to_owned(), 0.014256),
-            ("2007-06-18".to_owned(), 0.001926),
-            ("2007-07-09".to_owned(), 0.0068092),
-            ("2007-10-26".to_owned(), 0.0325832),
-            ("2007-12-28".to_owned(), 0.010568),
-            ("2008-05-08".to_owned(), 0.031102),
-            ("2008-06-16".to_owned(), 0.0002936),
-            ("2008-08-13".to_owned(), 0.0296124),
-            ("2008-10-24".to_owned(), 0.0173388),
-            ("2009-02-09".to_owned(), 0.0056056),
-            ("2009-06-08".to_owned(), 0.0069884),
-            ("2009-06-22".to_owned(), 0.0010552),
-            ("2009-08-11".to_owned(), 0.0068696),
-            ("2009-10-14".to_owned(), 0.0046288),
-            ("2009-12-29".to_owned(), 0.0102084),
-            ("2010-04-08".to_owned(), 0.0085252),
-            ("2010-10-25".to_owned(), 0.0191172),
-            ("2010-12-22".to_owned(), 0.0299664),
-            ("2011-03-29".to_owned(), 0.0670016),
-            ("2011-08-12".to_owned(), 0.0314662),
-            ("2011-11-28".to_owned(), 0.0112554),
-            ("2012-04-17".to_owned(), 0.0655808),
-            ("2012-08-03".to_owned(), 0.0117658),
-            ("2012-10-22".to_owned(), 0.0108748),
-            ("2013-01-29".to_owned(), 0.0793902),
-            ("2013-04-05".to_owned(), 0.0079676),
-            ("2014-01-30".to_owned(), 0.065248),
-            ("2014-05-02".to_owned(), 0.057983),
-            ("2014-09-05".to_owned(), 0.026945),
-            ("2014-11-24".to_owned(), 0.092538),
-            ("2015-01-21".to_owned(), 0.048092),
-            ("2015-02-06".to_owned(), 0.037219),
-            ("2015-04-07".to_owned(), 0.027902),
-            ("2015-07-09".to_owned(), 0.032246),
-            ("2015-10-08".to_owned(), 0.035761),
-            ("2016-01-07".to_owned(), 0.038278),
-            ("2016-03-07".to_owned(), 0.032999),
-            ("2016-08-05".to_owned(), 0.039616),
-            ("2016-12-05".to_owned(), 0.047804),
-            ("2017-01-05".to_owned(), 0.067134),
-            ("2017-03-02".to_owned(), 0.022606),
-            ("2017-07-24".to_owned(), 0.049309),
-            ("2018-01-08".to_owned(), 0.093373),
-            ("2018-03-05".to_owned(), 0.021539),
-            ("2018-08-06".to_owned(), 0.042972),
-            ("2019-01-07".to_owned(), 0.081518),
-            ("2020-01-07".to_owned(), 0.120835),
-            ("2021-01-11".to_owned(), 0.078966),
-            ("2021-02-04".to_owned(), 0.01424),
-            ("2022-01-06".to_owned(), 0.09),
-            ("2023-01-05".to_owned(), 0.12),
-            ("2024-01-08".to_owned(), 0.13),
-            ("2025-01-09".to_owned(), 0.04),
-            ("2025-01-17".to_owned(), 0.07),
-            ("2025-04-14".to_owned(), 0.02),
-            ("2025-07-17".to_owned(), 0.02),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("1970-01-01", 0.0550986),
+            event("2005-10-14", 0.005492),
+            event("2006-01-05", 0.001936),
+            event("2006-04-10", 0.01076),
+            event("2006-07-10", 0.011932),
+            event("2006-11-09", 0.0121),
+            event("2007-01-08", 0.014732),
+            event("2007-04-12", 0.014256),
+            event("2007-06-18", 0.001926),
+            event("2007-07-09", 0.0068092),
+            event("2007-10-26", 0.0325832),
+            event("2007-12-28", 0.010568),
+            event("2008-05-08", 0.031102),
+            event("2008-06-16", 0.0002936),
+            event("2008-08-13", 0.0296124),
+            event("2008-10-24", 0.0173388),
+            event("2009-02-09", 0.0056056),
+            event("2009-06-08", 0.0069884),
+            event("2009-06-22", 0.0010552),
+            event("2009-08-11", 0.0068696),
+            event("2009-10-14", 0.0046288),
+            event("2009-12-29", 0.0102084),
+            event("2010-04-08", 0.0085252),
+            event("2010-10-25", 0.0191172),
+            event("2010-12-22", 0.0299664),
+            event("2011-03-29", 0.0670016),
+            event("2011-08-12", 0.0314662),
+            event("2011-11-28", 0.0112554),
+            event("2012-04-17", 0.0655808),
+            event("2012-08-03", 0.0117658),
+            event("2012-10-22", 0.0108748),
+            event("2013-01-29", 0.0793902),
+            event("2013-04-05", 0.0079676),
+            event("2014-01-30", 0.065248),
+            event("2014-05-02", 0.057983),
+            event("2014-09-05", 0.026945),
+            event("2014-11-24", 0.092538),
+            event("2015-01-21", 0.048092),
+            event("2015-02-06", 0.037219),
+            event("2015-04-07", 0.027902),
+            event("2015-07-09", 0.032246),
+            event("2015-10-08", 0.035761),
+            event("2016-01-07", 0.038278),
+            event("2016-03-07", 0.032999),
+            event("2016-08-05", 0.039616),
+            event("2016-12-05", 0.047804),
+            event("2017-01-05", 0.067134),
+            event("2017-03-02", 0.022606),
+            event("2017-07-24", 0.049309),
+            event("2018-01-08", 0.093373),
+            event("2018-03-05", 0.021539),
+            event("2018-08-06", 0.042972),
+            event("2019-01-07", 0.081518),
+            event("2020-01-07", 0.120835),
+            event("2021-01-11", 0.078966),
+            event("2021-02-04", 0.01424),
+            event("2022-01-06", 0.09),
+            event("2023-01-05", 0.12),
+            event("2024-01-08", 0.13),
+            event("2025-01-09", 0.04),
+            event("2025-01-17", 0.07),
+            event("2025-04-14", 0.02),
+            event("2025-07-17", 0.02),
+        ]);
 
         //0.02*2.0 + 0.07 + 0.4= 0.15
         // 0.13
         // DGR: (0.15/0.13 - 1.0)*100.0 = 15.384615
         assert_eq!(
             Ok::<f64, &str>(round2(
-                calculate_dgr_ttm(&div_hists, "2025-08-01")
+                calculate_dgr_ttm(&div_hists, date("2025-08-01"))
                     .unwrap()
                     .unwrap()
             )),
@@ -1721,141 +1604,159 @@ mod tests {
 
     #[test]
     fn test_calculate_dgr() -> Result<(), String> {
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 0.5),
-            ("2023-07-01".to_owned(), 0.5),
-            ("2023-11-01".to_owned(), 0.5),
-        ];
-        assert_eq!(calculate_dgr(&div_hists, "2024"), Ok(Some(0.0)));
-
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 0.5),
-            ("2023-07-01".to_owned(), 0.5),
-            ("2023-11-01".to_owned(), 0.5),
-            ("2022-01-01".to_owned(), 0.5),
-            ("2022-04-01".to_owned(), 0.5),
-            ("2022-07-01".to_owned(), 0.5),
-            ("2022-11-01".to_owned(), 0.5),
-        ];
-        assert_eq!(calculate_dgr(&div_hists, "2024"), Ok(Some(0.0)));
-
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2022-01-01".to_owned(), 0.1),
-            ("2022-04-01".to_owned(), 0.9),
-            ("2022-07-01".to_owned(), 1.0),
-            ("2022-11-01".to_owned(), 1.0),
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 0.5),
-            ("2023-07-01".to_owned(), 2.0),
-            ("2023-11-01".to_owned(), 3.0),
-        ];
-        assert_eq!(calculate_dgr(&div_hists, "2024"), Ok(Some(100.0)));
-
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2022-03-01".to_owned(), 0.365),
-            ("2022-06-01".to_owned(), 0.365),
-            ("2022-09-01".to_owned(), 0.365),
-            ("2022-12-01".to_owned(), 0.365),
-            ("2023-03-01".to_owned(), 0.365),
-            ("2023-06-01".to_owned(), 0.125),
-            ("2023-09-01".to_owned(), 0.125),
-            ("2023-12-01".to_owned(), 0.125),
-            ("2024-03-01".to_owned(), 0.125),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 0.5),
+            event("2023-07-01", 0.5),
+            event("2023-11-01", 0.5),
+        ]);
+        assert_eq!(
+            growth::estimate(&div_hists.annual(2024), DgrEstimator::Average).map(|g| g.rate),
+            Some(0.0)
+        );
 
-        assert_eq!(calculate_dgr(&vec![], "2024"), Ok(None));
+        let div_hists = DividendSeries::new(vec![
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 0.5),
+            event("2023-07-01", 0.5),
+            event("2023-11-01", 0.5),
+            event("2022-01-01", 0.5),
+            event("2022-04-01", 0.5),
+            event("2022-07-01", 0.5),
+            event("2022-11-01", 0.5),
+        ]);
+        assert_eq!(
+            growth::estimate(&div_hists.annual(2024), DgrEstimator::Average).map(|g| g.rate),
+            Some(0.0)
+        );
+
+        let div_hists = DividendSeries::new(vec![
+            event("2022-01-01", 0.1),
+            event("2022-04-01", 0.9),
+            event("2022-07-01", 1.0),
+            event("2022-11-01", 1.0),
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 0.5),
+            event("2023-07-01", 2.0),
+            event("2023-11-01", 3.0),
+        ]);
+        assert_eq!(
+            growth::estimate(&div_hists.annual(2024), DgrEstimator::Average).map(|g| g.rate),
+            Some(100.0)
+        );
+
+        let div_hists = DividendSeries::new(vec![
+            event("2022-03-01", 0.365),
+            event("2022-06-01", 0.365),
+            event("2022-09-01", 0.365),
+            event("2022-12-01", 0.365),
+            event("2023-03-01", 0.365),
+            event("2023-06-01", 0.125),
+            event("2023-09-01", 0.125),
+            event("2023-12-01", 0.125),
+            event("2024-03-01", 0.125),
+        ]);
+
+        assert_eq!(
+            growth::estimate(
+                &DividendSeries::default().annual(2024),
+                DgrEstimator::Average
+            ),
+            None
+        );
 
         //0.125*3.0+0.365 = 0.74
         //0.365*4.0 = 1.46
         // DGR: (0.74/1.46 - 1.0)*100.0 = -49.315068
         assert_eq!(
-            Ok::<f64, &str>(round2(calculate_dgr(&div_hists, "2024").unwrap().unwrap())),
-            Ok(-49.32)
+            growth::estimate(&div_hists.annual(2024), DgrEstimator::Average)
+                .map(|g| round2(g.rate)),
+            Some(-49.32)
         );
 
         // 0.3475
         // 2.0*0.365 = 0.73
         // 0.0
         // DGR = (0.0 -1.0)*100.0 + (0.73/0.3475 - 1.0)*100.0 = 10.071942 / 2.0 = 5.04
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2021-12-01".to_owned(), 0.3475),
-            ("2022-03-01".to_owned(), 0.365),
-            ("2022-06-01".to_owned(), 0.365),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("2021-12-01", 0.3475),
+            event("2022-03-01", 0.365),
+            event("2022-06-01", 0.365),
+        ]);
 
         assert_eq!(
-            Ok::<f64, &str>(round2(calculate_dgr(&div_hists, "2024").unwrap().unwrap())),
-            Ok(5.04)
+            growth::estimate(&div_hists.annual(2024), DgrEstimator::Average)
+                .map(|g| round2(g.rate)),
+            Some(5.04)
         );
 
         // ABEV as of 28th of March 2024
-        let div_hists: Vec<(String, f64)> = vec![
-            ("1970-01-01".to_owned(), 0.0550986),
-            ("2005-10-14".to_owned(), 0.005492),
-            ("2006-01-05".to_owned(), 0.001936),
-            ("2006-04-10".to_owned(), 0.01076),
-            ("2006-07-10".to_owned(), 0.011932),
-            ("2006-11-09".to_owned(), 0.0121),
-            ("2007-01-08".to_owned(), 0.014732),
-            ("2007-04-12".to_owned(), 0.014256),
-            ("2007-06-18".to_owned(), 0.001926),
-            ("2007-07-09".to_owned(), 0.0068092),
-            ("2007-10-26".to_owned(), 0.0325832),
-            ("2007-12-28".to_owned(), 0.010568),
-            ("2008-05-08".to_owned(), 0.031102),
-            ("2008-06-16".to_owned(), 0.0002936),
-            ("2008-08-13".to_owned(), 0.0296124),
-            ("2008-10-24".to_owned(), 0.0173388),
-            ("2009-02-09".to_owned(), 0.0056056),
-            ("2009-06-08".to_owned(), 0.0069884),
-            ("2009-06-22".to_owned(), 0.0010552),
-            ("2009-08-11".to_owned(), 0.0068696),
-            ("2009-10-14".to_owned(), 0.0046288),
-            ("2009-12-29".to_owned(), 0.0102084),
-            ("2010-04-08".to_owned(), 0.0085252),
-            ("2010-10-25".to_owned(), 0.0191172),
-            ("2010-12-22".to_owned(), 0.0299664),
-            ("2011-03-29".to_owned(), 0.0670016),
-            ("2011-08-12".to_owned(), 0.0314662),
-            ("2011-11-28".to_owned(), 0.0112554),
-            ("2012-04-17".to_owned(), 0.0655808),
-            ("2012-08-03".to_owned(), 0.0117658),
-            ("2012-10-22".to_owned(), 0.0108748),
-            ("2013-01-29".to_owned(), 0.0793902),
-            ("2013-04-05".to_owned(), 0.0079676),
-            ("2014-01-30".to_owned(), 0.065248),
-            ("2014-05-02".to_owned(), 0.057983),
-            ("2014-09-05".to_owned(), 0.026945),
-            ("2014-11-24".to_owned(), 0.092538),
-            ("2015-01-21".to_owned(), 0.048092),
-            ("2015-02-06".to_owned(), 0.037219),
-            ("2015-04-07".to_owned(), 0.027902),
-            ("2015-07-09".to_owned(), 0.032246),
-            ("2015-10-08".to_owned(), 0.035761),
-            ("2016-01-07".to_owned(), 0.038278),
-            ("2016-03-07".to_owned(), 0.032999),
-            ("2016-08-05".to_owned(), 0.039616),
-            ("2016-12-05".to_owned(), 0.047804),
-            ("2017-01-05".to_owned(), 0.067134),
-            ("2017-03-02".to_owned(), 0.022606),
-            ("2017-07-24".to_owned(), 0.049309),
-            ("2018-01-08".to_owned(), 0.093373),
-            ("2018-03-05".to_owned(), 0.021539),
-            ("2018-08-06".to_owned(), 0.042972),
-            ("2019-01-07".to_owned(), 0.081518),
-            ("2020-01-07".to_owned(), 0.120835),
-            ("2021-01-11".to_owned(), 0.078966),
-            ("2021-02-04".to_owned(), 0.01424),
-            ("2022-01-06".to_owned(), 0.107707),
-            ("2023-01-05".to_owned(), 0.144287),
-            ("2024-01-08".to_owned(), 0.150969),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("1970-01-01", 0.0550986),
+            event("2005-10-14", 0.005492),
+            event("2006-01-05", 0.001936),
+            event("2006-04-10", 0.01076),
+            event("2006-07-10", 0.011932),
+            event("2006-11-09", 0.0121),
+            event("2007-01-08", 0.014732),
+            event("2007-04-12", 0.014256),
+            event("2007-06-18", 0.001926),
+            event("2007-07-09", 0.0068092),
+            event("2007-10-26", 0.0325832),
+            event("2007-12-28", 0.010568),
+            event("2008-05-08", 0.031102),
+            event("2008-06-16", 0.0002936),
+            event("2008-08-13", 0.0296124),
+            event("2008-10-24", 0.0173388),
+            event("2009-02-09", 0.0056056),
+            event("2009-06-08", 0.0069884),
+            event("2009-06-22", 0.0010552),
+            event("2009-08-11", 0.0068696),
+            event("2009-10-14", 0.0046288),
+            event("2009-12-29", 0.0102084),
+            event("2010-04-08", 0.0085252),
+            event("2010-10-25", 0.0191172),
+            event("2010-12-22", 0.0299664),
+            event("2011-03-29", 0.0670016),
+            event("2011-08-12", 0.0314662),
+            event("2011-11-28", 0.0112554),
+            event("2012-04-17", 0.0655808),
+            event("2012-08-03", 0.0117658),
+            event("2012-10-22", 0.0108748),
+            event("2013-01-29", 0.0793902),
+            event("2013-04-05", 0.0079676),
+            event("2014-01-30", 0.065248),
+            event("2014-05-02", 0.057983),
+            event("2014-09-05", 0.026945),
+            event("2014-11-24", 0.092538),
+            event("2015-01-21", 0.048092),
+            event("2015-02-06", 0.037219),
+            event("2015-04-07", 0.027902),
+            event("2015-07-09", 0.032246),
+            event("2015-10-08", 0.035761),
+            event("2016-01-07", 0.038278),
+            event("2016-03-07", 0.032999),
+            event("2016-08-05", 0.039616),
+            event("2016-12-05", 0.047804),
+            event("2017-01-05", 0.067134),
+            event("2017-03-02", 0.022606),
+            event("2017-07-24", 0.049309),
+            event("2018-01-08", 0.093373),
+            event("2018-03-05", 0.021539),
+            event("2018-08-06", 0.042972),
+            event("2019-01-07", 0.081518),
+            event("2020-01-07", 0.120835),
+            event("2021-01-11", 0.078966),
+            event("2021-02-04", 0.01424),
+            event("2022-01-06", 0.107707),
+            event("2023-01-05", 0.144287),
+            event("2024-01-08", 0.150969),
+        ]);
 
         assert_eq!(
-            Ok::<f64, &str>(round2(calculate_dgr(&div_hists, "2024").unwrap().unwrap())),
-            Ok(17.58)
+            growth::estimate(&div_hists.annual(2024), DgrEstimator::Average)
+                .map(|g| round2(g.rate)),
+            Some(17.58)
         );
 
         Ok(())
@@ -1869,16 +1770,16 @@ mod tests {
 
     #[test]
     fn test_calculate_annualized_div() -> Result<(), String> {
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 1.0),
-            ("2023-07-01".to_owned(), 2.0),
-            ("2023-11-01".to_owned(), 4.0),
-            ("2022-04-01".to_owned(), 0.3),
-            ("2022-07-01".to_owned(), 0.3),
-            ("2022-11-01".to_owned(), 0.2),
-            ("2022-01-01".to_owned(), 0.1),
-        ];
+        let div_hists = DividendSeries::new(vec![
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 1.0),
+            event("2023-07-01", 2.0),
+            event("2023-11-01", 4.0),
+            event("2022-04-01", 0.3),
+            event("2022-07-01", 0.3),
+            event("2022-11-01", 0.2),
+            event("2022-01-01", 0.1),
+        ]);
 
         assert_eq!(
             calculate_annualized_div(&div_hists, "2023"),
@@ -1888,83 +1789,86 @@ mod tests {
             calculate_annualized_div(&div_hists, "2022"),
             Ok(Some((0.9, 4)))
         );
-        assert_eq!(calculate_annualized_div(&vec![], "2022"), Ok(None));
-        Ok(())
-    }
-
-    #[test]
-    fn test_calculate_consecutive_years_of_growth() -> Result<(), String> {
-        let div_hists: Vec<(String, f64)> = vec![];
         assert_eq!(
-            calculate_consecutive_years_of_growth(&div_hists, "2024"),
+            calculate_annualized_div(&DividendSeries::default(), "2022"),
             Ok(None)
         );
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 1.0),
-            ("2023-07-01".to_owned(), 2.0),
-            ("2023-11-01".to_owned(), 4.0),
-            ("2022-04-01".to_owned(), 0.3),
-            ("2022-07-01".to_owned(), 0.3),
-            ("2022-11-01".to_owned(), 0.2),
-            ("2022-01-01".to_owned(), 0.1),
-        ];
-        assert_eq!(
-            calculate_consecutive_years_of_growth(&div_hists, "2024"),
-            Ok(Some(1))
-        );
+        Ok(())
+    }
 
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2024-01-01".to_owned(), 0.5),
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 1.0),
-            ("2023-07-01".to_owned(), 2.0),
-            ("2023-11-01".to_owned(), 4.0),
-            ("2022-04-01".to_owned(), 0.3),
-            ("2022-07-01".to_owned(), 0.3),
-            ("2022-11-01".to_owned(), 0.2),
-            ("2022-01-01".to_owned(), 0.1),
-        ];
-        assert_eq!(
-            calculate_consecutive_years_of_growth(&div_hists, "2024"),
-            Ok(Some(1))
-        );
+    #[test]
+    fn test_malformed_fiscal_year() -> Result<(), String> {
+        let div_hists =
+            DividendSeries::new(vec![event("2023-01-01", 0.5), event("2023-04-01", 1.0)]);
 
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2024-01-01".to_owned(), 0.5),
-            ("2023-01-01".to_owned(), 0.5),
-            ("2023-04-01".to_owned(), 1.0),
-            ("2023-07-01".to_owned(), 2.0),
-            ("2023-11-01".to_owned(), 3.0),
-            ("2022-04-01".to_owned(), 3.3),
-            ("2022-07-01".to_owned(), 3.3),
-            ("2022-11-01".to_owned(), 0.2),
-            ("2022-01-01".to_owned(), 0.1),
-        ];
-        assert_eq!(
-            calculate_consecutive_years_of_growth(&div_hists, "2024"),
-            Ok(Some(0))
-        );
+        match calculate_annualized_div(&div_hists, "last year") {
+            Err(Error::Parse { what, .. }) => assert_eq!(what, "fiscal year"),
+            other => panic!("Expected parse error, got: {other:?}"),
+        }
+        Ok(())
+    }
 
-        let div_hists: Vec<(String, f64)> = vec![
-            ("2024-01-01".to_owned(), 0.5),
-            ("2023-01-01".to_owned(), 2.5),
-            ("2023-04-01".to_owned(), 1.0),
-            ("2023-07-01".to_owned(), 2.0),
-            ("2023-11-01".to_owned(), 3.0),
-            ("2022-04-01".to_owned(), 2.3),
-            ("2022-07-01".to_owned(), 3.3),
-            ("2022-11-01".to_owned(), 0.2),
-            ("2022-01-01".to_owned(), 0.1),
-            ("2021-04-01".to_owned(), 3.3),
-            ("2021-07-01".to_owned(), 3.3),
-            ("2021-11-01".to_owned(), 0.2),
-            ("2021-01-01".to_owned(), 0.1),
-        ];
+    #[test]
+    fn test_calculate_consecutive_years_of_growth() -> Result<(), String> {
+        let div_hists = DividendSeries::new(vec![]);
         assert_eq!(
-            calculate_consecutive_years_of_growth(&div_hists, "2024"),
-            Ok(Some(1))
+            streak::streaks(&div_hists.annual(2024), 2023),
+            Streaks::default()
         );
+        let div_hists = DividendSeries::new(vec![
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 1.0),
+            event("2023-07-01", 2.0),
+            event("2023-11-01", 4.0),
+            event("2022-04-01", 0.3),
+            event("2022-07-01", 0.3),
+            event("2022-11-01", 0.2),
+            event("2022-01-01", 0.1),
+        ]);
+        assert_eq!(streak::streaks(&div_hists.annual(2024), 2023).growth, 1);
+
+        let div_hists = DividendSeries::new(vec![
+            event("2024-01-01", 0.5),
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 1.0),
+            event("2023-07-01", 2.0),
+            event("2023-11-01", 4.0),
+            event("2022-04-01", 0.3),
+            event("2022-07-01", 0.3),
+            event("2022-11-01", 0.2),
+            event("2022-01-01", 0.1),
+        ]);
+        assert_eq!(streak::streaks(&div_hists.annual(2024), 2023).growth, 1);
+
+        let div_hists = DividendSeries::new(vec![
+            event("2024-01-01", 0.5),
+            event("2023-01-01", 0.5),
+            event("2023-04-01", 1.0),
+            event("2023-07-01", 2.0),
+            event("2023-11-01", 3.0),
+            event("2022-04-01", 3.3),
+            event("2022-07-01", 3.3),
+            event("2022-11-01", 0.2),
+            event("2022-01-01", 0.1),
+        ]);
+        assert_eq!(streak::streaks(&div_hists.annual(2024), 2023).growth, 0);
+
+        let div_hists = DividendSeries::new(vec![
+            event("2024-01-01", 0.5),
+            event("2023-01-01", 2.5),
+            event("2023-04-01", 1.0),
+            event("2023-07-01", 2.0),
+            event("2023-11-01", 3.0),
+            event("2022-04-01", 2.3),
+            event("2022-07-01", 3.3),
+            event("2022-11-01", 0.2),
+            event("2022-01-01", 0.1),
+            event("2021-04-01", 3.3),
+            event("2021-07-01", 3.3),
+            event("2021-11-01", 0.2),
+            event("2021-01-01", 0.1),
+        ]);
+        assert_eq!(streak::streaks(&div_hists.annual(2024), 2023).growth, 1);
 
         Ok(())
     }
@@ -0,0 +1,723 @@
+//! Loading of dividend lists (Dividend Champions spreadsheet of moneyzine/DripInvesting).
+//!
+//! Header row is found by its content rather than by position, and known columns are
+//! recognized by any of their aliases and stored under canonical names with fixed types
+//! (see `SCHEMA`), so the analysis does not depend on layout details of a particular edition
+//! of the spreadsheet. Every column has a value (possibly null) for every company, and layout
+//! which lacks required columns or has text in numeric ones is reported as error instead of
+//! being loaded.
+
+use calamine::{open_workbook_auto, DataType, Reader};
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::path::Path;
+
+use crate::Error;
+
+/// Type of values of list column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Number,
+    Date,
+}
+
+/// Column of dividend list known to the analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnSpec {
+    /// Name of column in loaded data frame
+    pub name: &'static str,
+    /// Headers the column may have in spreadsheet, compared ignoring case and whitespace
+    pub aliases: &'static [&'static str],
+    pub kind: ColumnType,
+    /// List without this column is rejected
+    pub required: bool,
+}
+
+const fn column(
+    name: &'static str,
+    aliases: &'static [&'static str],
+    kind: ColumnType,
+    required: bool,
+) -> ColumnSpec {
+    ColumnSpec {
+        name,
+        aliases,
+        kind,
+        required,
+    }
+}
+
+/// Columns of dividend list used by the tools. Remaining columns of spreadsheet are loaded
+/// under their own headers, as numbers if all their values are numeric
+pub const SCHEMA: &[ColumnSpec] = &[
+    column("Symbol", &["Symbol", "Ticker"], ColumnType::Text, true),
+    column(
+        "Company",
+        &["Company", "Name", "Company Name"],
+        ColumnType::Text,
+        true,
+    ),
+    column("Sector", &["Sector"], ColumnType::Text, false),
+    column("Industry", &["Industry"], ColumnType::Text, false),
+    column(
+        "No Years",
+        &["No Years", "Years", "Streak"],
+        ColumnType::Number,
+        false,
+    ),
+    column("Price", &["Price", "Share Price"], ColumnType::Number, true),
+    column(
+        "Div Yield",
+        &["Div Yield", "Dividend Yield", "Yield"],
+        ColumnType::Number,
+        true,
+    ),
+    column(
+        "Current Div",
+        &["Current Div", "Current Dividend"],
+        ColumnType::Number,
+        true,
+    ),
+    column(
+        "Payouts/Year",
+        &["Payouts/Year", "Payouts Per Year"],
+        ColumnType::Number,
+        false,
+    ),
+    column(
+        "Annualized",
+        &["Annualized", "Annualized Div", "Annual Div"],
+        ColumnType::Number,
+        true,
+    ),
+    column("DGR 1Y", &["DGR 1Y", "1Y DGR"], ColumnType::Number, true),
+    column("DGR 3Y", &["DGR 3Y", "3Y DGR"], ColumnType::Number, true),
+    column("DGR 5Y", &["DGR 5Y", "5Y DGR"], ColumnType::Number, true),
+    column("DGR 10Y", &["DGR 10Y", "10Y DGR"], ColumnType::Number, true),
+    column(
+        "CF/Share",
+        &["CF/Share", "Cash Flow/Share", "CFPS"],
+        ColumnType::Number,
+        true,
+    ),
+    column("P/E", &["P/E", "PE"], ColumnType::Number, false),
+    column(
+        "Ex-Date",
+        &["Ex-Date", "Ex-Div Date", "Ex-Dividend Date"],
+        ColumnType::Date,
+        false,
+    ),
+    column(
+        "Pay-Date",
+        &["Pay-Date", "Pay Date", "Payment Date"],
+        ColumnType::Date,
+        false,
+    ),
+];
+
+/// Header row is looked for among that many first rows
+const MAX_HEADER_ROW: usize = 10;
+
+/// Formats of dates written as text
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%m/%d/%Y", "%m/%d/%y"];
+
+/// Texts meaning that value is not available
+const NOT_AVAILABLE: &[&str] = &["-", "n/a", "na", "#n/a"];
+
+fn normalize(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Known column given header refers to
+fn spec_of(header: &str) -> Option<&'static ColumnSpec> {
+    let header = normalize(header);
+    SCHEMA
+        .iter()
+        .find(|spec| spec.aliases.iter().any(|alias| normalize(alias) == header))
+}
+
+fn cell_text(cell: &DataType) -> Option<String> {
+    match cell {
+        DataType::String(s) => {
+            let s = s.trim();
+            (!s.is_empty()).then(|| s.to_owned())
+        }
+        DataType::Empty | DataType::Error(_) => None,
+        cell => Some(cell.to_string()),
+    }
+}
+
+/// Numeric value of cell. Err with text of cell that is not a number
+fn cell_number(cell: &DataType) -> Result<Option<f64>, String> {
+    match cell {
+        DataType::Float(f) | DataType::DateTime(f) => Ok(Some(*f)),
+        DataType::Int(i) => Ok(Some(*i as f64)),
+        DataType::Empty | DataType::Error(_) => Ok(None),
+        DataType::String(s) => {
+            let s = s.trim();
+            if s.is_empty() || NOT_AVAILABLE.contains(&s.to_lowercase().as_str()) {
+                Ok(None)
+            } else {
+                s.trim_end_matches('%')
+                    .replace(',', "")
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| s.to_owned())
+            }
+        }
+        cell => Err(cell.to_string()),
+    }
+}
+
+/// Date of cell holding Excel date (number of days since 1899-12-30) or date written as text
+fn cell_date(cell: &DataType) -> Result<Option<NaiveDate>, String> {
+    let from_serial = |days: f64| {
+        NaiveDate::from_ymd_opt(1899, 12, 30)
+            .and_then(|epoch| epoch.checked_add_signed(chrono::Duration::days(days.floor() as i64)))
+            .ok_or_else(|| days.to_string())
+    };
+    match cell {
+        DataType::DateTime(f) | DataType::Float(f) => from_serial(*f).map(Some),
+        DataType::Int(i) => from_serial(*i as f64).map(Some),
+        DataType::Empty | DataType::Error(_) => Ok(None),
+        DataType::String(s) => {
+            let s = s.trim();
+            if s.is_empty() || NOT_AVAILABLE.contains(&s.to_lowercase().as_str()) {
+                return Ok(None);
+            }
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(s, format).ok())
+                .map(Some)
+                .ok_or_else(|| s.to_owned())
+        }
+        cell => Err(cell.to_string()),
+    }
+}
+
+/// Index of header row: the one with "Symbol" column and the most of known columns
+fn find_header(rows: &[&[DataType]]) -> Result<usize, Error> {
+    rows.iter()
+        .take(MAX_HEADER_ROW)
+        .enumerate()
+        .filter_map(|(index, row)| {
+            let specs: Vec<&str> = row
+                .iter()
+                .filter_map(|cell| cell_text(cell).and_then(|h| spec_of(&h)))
+                .map(|spec| spec.name)
+                .collect();
+            specs
+                .contains(&"Symbol")
+                .then_some((specs.len(), std::cmp::Reverse(index)))
+        })
+        .max()
+        .map(|(_, std::cmp::Reverse(index))| index)
+        .ok_or_else(|| {
+            Error::missing(&format!(
+                "header row of dividend list (no \"Symbol\" column in the first {MAX_HEADER_ROW} rows)"
+            ))
+        })
+}
+
+/// Column of spreadsheet being loaded
+struct SheetColumn<'a> {
+    index: usize,
+    name: &'a str,
+    symbol_index: usize,
+}
+
+impl SheetColumn<'_> {
+    /// Values of column in given rows (with their indices). Cell that could not be parsed is
+    /// reported together with company it belongs to
+    fn parse<T>(
+        &self,
+        rows: &[(usize, &[DataType])],
+        expected: &str,
+        parse: impl Fn(&DataType) -> Result<Option<T>, String>,
+    ) -> Result<Vec<Option<T>>, Error> {
+        rows.iter()
+            .map(|(index, row)| {
+                parse(row.get(self.index).unwrap_or(&DataType::Empty)).map_err(|text| {
+                    Error::Parse {
+                        ticker: row.get(self.symbol_index).and_then(cell_text),
+                        what: format!("column \"{}\" of dividend list", self.name),
+                        cause: format!(
+                            "expected {expected} at row {}, found \"{text}\"",
+                            index + 1
+                        ),
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// Data frame of dividend list given as spreadsheet rows
+pub fn list_from_rows(rows: &[&[DataType]]) -> Result<DataFrame, Error> {
+    let header_index = find_header(rows)?;
+    let header = rows[header_index];
+
+    // Spreadsheet columns loaded, with their names and types (None for inferred ones)
+    let mut columns: Vec<(usize, String, Option<ColumnType>)> = vec![];
+    for (i, cell) in header.iter().enumerate() {
+        let text = match cell_text(cell) {
+            Some(text) => text,
+            None => {
+                log::info!("Skipping column {i} of dividend list without header");
+                continue;
+            }
+        };
+        let (name, kind) = match spec_of(&text) {
+            Some(spec) => (spec.name.to_owned(), Some(spec.kind)),
+            None => (text, None),
+        };
+        if columns.iter().any(|(_, n, _)| *n == name) {
+            log::warn!("Skipping repeated column \"{name}\" of dividend list");
+            continue;
+        }
+        columns.push((i, name, kind));
+    }
+    log::info!(
+        "Columns: {:?}",
+        columns.iter().map(|(_, n, _)| n).collect::<Vec<_>>()
+    );
+
+    let missing: Vec<&str> = SCHEMA
+        .iter()
+        .filter(|spec| spec.required && !columns.iter().any(|(_, n, _)| n == spec.name))
+        .map(|spec| spec.name)
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::MissingData {
+            provider: None,
+            ticker: None,
+            what: format!(
+                "columns {missing:?} of dividend list (header at row {}: {:?})",
+                header_index + 1,
+                columns.iter().map(|(_, n, _)| n).collect::<Vec<_>>()
+            ),
+        });
+    }
+
+    // Companies are the rows with symbol
+    let symbol_index = columns
+        .iter()
+        .find(|(_, n, _)| n == "Symbol")
+        .map(|(i, _, _)| *i)
+        .ok_or_else(|| Error::missing("\"Symbol\" column of dividend list"))?;
+    let empty = DataType::Empty;
+    let data: Vec<(usize, &[DataType])> = rows
+        .iter()
+        .enumerate()
+        .skip(header_index + 1)
+        .filter(|(_, row)| row.get(symbol_index).and_then(cell_text).is_some())
+        .map(|(index, row)| (index, *row))
+        .collect();
+
+    let mut series = vec![];
+    for (i, name, kind) in columns {
+        let cells = data.iter().map(|(_, row)| row.get(i).unwrap_or(&empty));
+        let kind = kind.unwrap_or_else(|| {
+            let filled: Vec<&DataType> = cells.clone().filter(|c| !c.is_empty()).collect();
+            if !filled.is_empty() && filled.iter().all(|c| matches!(c, DataType::DateTime(_))) {
+                ColumnType::Date
+            } else if cells.clone().all(|c| cell_number(c).is_ok()) {
+                ColumnType::Number
+            } else {
+                ColumnType::Text
+            }
+        });
+        let sheet_column = SheetColumn {
+            index: i,
+            name: &name,
+            symbol_index,
+        };
+        series.push(match kind {
+            ColumnType::Text => {
+                let values: Vec<Option<String>> = data
+                    .iter()
+                    .map(|(_, row)| row.get(i).and_then(cell_text))
+                    .collect();
+                Series::new(&name, values)
+            }
+            ColumnType::Number => {
+                Series::new(&name, sheet_column.parse(&data, "number", cell_number)?)
+            }
+            ColumnType::Date => Series::new(&name, sheet_column.parse(&data, "date", cell_date)?),
+        });
+    }
+    DataFrame::new(series).map_err(|msg| {
+        log::error!("DF error: {msg}");
+        Error::parse("dividend list", msg)
+    })
+}
+
+/// Columns that companies need values of to be screened
+pub fn required_columns() -> Vec<&'static str> {
+    SCHEMA
+        .iter()
+        .filter(|spec| spec.required)
+        .map(|spec| spec.name)
+        .collect()
+}
+
+fn list_column<'a>(df: &'a DataFrame, name: &str) -> Result<&'a Series, Error> {
+    df.column(name)
+        .map_err(|_| Error::missing(&format!("column \"{name}\" of dividend list")))
+}
+
+/// Symbols of companies lacking values of any of given columns, with names of those columns
+pub fn incomplete_rows(
+    df: &DataFrame,
+    columns: &[&str],
+) -> Result<Vec<(String, Vec<String>)>, Error> {
+    let symbols = list_column(df, "Symbol")?
+        .utf8()
+        .map_err(|e| Error::parse("\"Symbol\" column of dividend list", e))?;
+    let nulls = columns
+        .iter()
+        .map(|name| Ok((*name, list_column(df, name)?.is_null())))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok((0..df.height())
+        .filter_map(|i| {
+            let missing: Vec<String> = nulls
+                .iter()
+                .filter(|(_, null)| null.get(i) == Some(true))
+                .map(|(name, _)| name.to_string())
+                .collect();
+            (!missing.is_empty()).then(|| (symbols.get(i).unwrap_or_default().to_owned(), missing))
+        })
+        .collect())
+}
+
+/// Companies having values of all given columns
+pub fn drop_incomplete(df: &DataFrame, columns: &[&str]) -> Result<DataFrame, Error> {
+    let mut mask = BooleanChunked::full("", true, df.height());
+    for name in columns {
+        mask = mask & list_column(df, name)?.is_not_null();
+    }
+    df.filter(&mask)
+        .map_err(|e| Error::parse("dividend list", e))
+}
+
+/// Load companies of given category (sheet of spreadsheet e.g. "Champions" or "All")
+pub fn load_list<RS, W>(workbook: &mut W, category: &str) -> Result<DataFrame, Error>
+where
+    RS: std::io::Read + std::io::Seek,
+    W: Reader<RS>,
+{
+    log::info!("Processing category: {}", category);
+    let names = workbook.sheet_names();
+    log::info!("Available categories: {:?}", names);
+    let name_sheet = names
+        .iter()
+        .find(|x| *x == category)
+        .ok_or_else(|| Error::missing(&format!("category \"{category}\" in dividend list")))?;
+
+    let range = workbook
+        .worksheet_range(name_sheet)
+        .ok_or_else(|| Error::missing(&format!("category \"{category}\" in dividend list")))?
+        .map_err(|e| {
+            Error::parse(
+                &format!("category \"{category}\" of dividend list"),
+                format!("{e:?}"),
+            )
+        })?;
+    let rows: Vec<&[DataType]> = range.rows().collect();
+    list_from_rows(&rows)
+}
+
+/// Cells of CSV text. Fields may be quoted, with quotes inside written twice
+fn csv_rows(text: &str) -> Vec<Vec<DataType>> {
+    let mut rows = vec![];
+    let (mut row, mut field) = (vec![], String::new());
+    let (mut quoted, mut chars) = (false, text.chars().peekable());
+    let cell = |field: &mut String| match field.is_empty() {
+        true => DataType::Empty,
+        false => DataType::String(std::mem::take(field)),
+    };
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => row.push(cell(&mut field)),
+            ('\r', false) => (),
+            ('\n', false) => {
+                row.push(cell(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(cell(&mut field));
+        rows.push(row);
+    }
+    rows
+}
+
+/// Load dividend list from file of format given by its extension: spreadsheet (.xlsx or .ods)
+/// with sheet of given category, or CSV export of a single sheet
+pub fn load_list_file(path: &Path, category: &str) -> Result<DataFrame, Error> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    let what = format!("dividend list {}", path.display());
+    match extension.as_deref() {
+        Some("csv") => {
+            log::info!("Loading {what}, single category assumed instead of \"{category}\"");
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| Error::Config(format!("Unable to read {what}: {e}")))?;
+            let rows = csv_rows(&text);
+            let rows: Vec<&[DataType]> = rows.iter().map(|r| r.as_slice()).collect();
+            list_from_rows(&rows)
+        }
+        Some("xlsx") | Some("xlsm") | Some("ods") => {
+            let mut workbook = open_workbook_auto(path)
+                .map_err(|e| Error::Config(format!("Unable to open {what}: {e}")))?;
+            load_list(&mut workbook, category)
+        }
+        _ => Err(Error::Config(format!(
+            "Unsupported format of {what}, expected .xlsx, .ods or .csv file"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> DataType {
+        DataType::String(s.to_owned())
+    }
+
+    fn header(names: &[&str]) -> Vec<DataType> {
+        names.iter().map(|name| text(name)).collect()
+    }
+
+    #[test]
+    fn test_list_from_rows() -> Result<(), String> {
+        let rows = vec![
+            vec![text("Dividend Champions"), DataType::Empty],
+            vec![],
+            header(&[
+                "Ticker",
+                "Company",
+                "",
+                "Price",
+                "Dividend Yield",
+                "Current Div",
+                "Annualized",
+                "DGR 1Y",
+                "DGR 3Y",
+                "DGR 5Y",
+                "DGR 10Y",
+                "CF/Share",
+                "Chowder Number",
+                "Payouts/ Year",
+                "Ex-Date",
+                "Pay Date",
+                "Last Increased",
+            ]),
+            vec![
+                text("ABM"),
+                text("ABM Industries"),
+                text("x"),
+                DataType::Float(44.5),
+                DataType::Float(2.0),
+                DataType::Float(0.22),
+                DataType::Float(0.88),
+                DataType::Float(12.8),
+                DataType::Float(7.0),
+                text("n/a"),
+                DataType::Float(5.1),
+                DataType::Float(4.2),
+                DataType::Int(9),
+                DataType::Int(4),
+                DataType::DateTime(45000.0),
+                text("2023-04-01"),
+                DataType::DateTime(44900.5),
+            ],
+            // Missing values are nulls, also at the end of row
+            vec![
+                text("CTBI"),
+                DataType::Empty,
+                DataType::Empty,
+                DataType::Float(35.6),
+                text("5.17%"),
+                DataType::Float(0.46),
+                DataType::Float(1.84),
+            ],
+            // Notes below the list
+            vec![DataType::Empty, text("Data as of 2023-12-31")],
+        ];
+        let rows: Vec<&[DataType]> = rows.iter().map(|r| r.as_slice()).collect();
+        let df = list_from_rows(&rows).map_err(|e| e.to_string())?;
+
+        assert_eq!(df.height(), 2);
+        assert_eq!(
+            df.get_column_names(),
+            [
+                "Symbol",
+                "Company",
+                "Price",
+                "Div Yield",
+                "Current Div",
+                "Annualized",
+                "DGR 1Y",
+                "DGR 3Y",
+                "DGR 5Y",
+                "DGR 10Y",
+                "CF/Share",
+                "Chowder Number",
+                "Payouts/Year",
+                "Ex-Date",
+                "Pay-Date",
+                "Last Increased",
+            ]
+        );
+        let values = |name: &str| -> Result<Vec<Option<f64>>, String> {
+            df.column(name)
+                .and_then(|c| c.f64().map(|c| c.into_iter().collect()))
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(values("Div Yield")?, vec![Some(2.0), Some(5.17)]);
+        assert_eq!(values("DGR 5Y")?, vec![None, None]);
+        assert_eq!(values("Chowder Number")?, vec![Some(9.0), None]);
+        let companies: Vec<Option<&str>> = df
+            .column("Company")
+            .and_then(|c| c.utf8().map(|c| c.into_iter().collect()))
+            .map_err(|e| e.to_string())?;
+        assert_eq!(companies, vec![Some("ABM Industries"), None]);
+
+        // Dates are given as Excel serial numbers or text
+        let dates = |name: &str| -> Result<Vec<Option<NaiveDate>>, String> {
+            df.column(name)
+                .and_then(|c| c.date().map(|c| c.as_date_iter().collect()))
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(
+            dates("Ex-Date")?,
+            vec![NaiveDate::from_ymd_opt(2023, 3, 15), None]
+        );
+        assert_eq!(
+            dates("Pay-Date")?,
+            vec![NaiveDate::from_ymd_opt(2023, 4, 1), None]
+        );
+        assert_eq!(
+            dates("Last Increased")?,
+            vec![NaiveDate::from_ymd_opt(2022, 12, 5), None]
+        );
+
+        // Completeness of rows
+        assert_eq!(
+            incomplete_rows(&df, &["Company", "Price", "DGR 1Y", "Ex-Date"]),
+            Ok(vec![(
+                "CTBI".to_owned(),
+                vec![
+                    "Company".to_owned(),
+                    "DGR 1Y".to_owned(),
+                    "Ex-Date".to_owned()
+                ]
+            )])
+        );
+        assert_eq!(incomplete_rows(&df, &["Price"]), Ok(vec![]));
+        assert!(incomplete_rows(&df, &["Dividend"]).is_err());
+        let complete = drop_incomplete(&df, &["DGR 1Y"]).map_err(|e| e.to_string())?;
+        assert_eq!(complete.height(), 1);
+        let complete = drop_incomplete(&df, &["Price"]).map_err(|e| e.to_string())?;
+        assert_eq!(complete.height(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_schema_mismatch() -> Result<(), String> {
+        // Changed layout without required columns
+        let rows = vec![
+            header(&["Symbol", "Company", "Price", "Div Yield"]),
+            vec![text("ABM"), text("ABM Industries"), DataType::Float(44.5)],
+        ];
+        let rows: Vec<&[DataType]> = rows.iter().map(|r| r.as_slice()).collect();
+        let err = list_from_rows(&rows).map_err(|e| e.to_string());
+        assert!(
+            matches!(&err, Err(e) if e.contains("\"Current Div\"")),
+            "{err:?}"
+        );
+
+        // Text in numeric column
+        let mut names = vec!["Symbol", "Company"];
+        names.extend(
+            SCHEMA
+                .iter()
+                .filter(|s| s.required && s.kind == ColumnType::Number)
+                .map(|s| s.name),
+        );
+        let mut row = vec![text("ABM"), text("ABM Industries"), text("soon")];
+        row.resize(names.len(), DataType::Float(1.0));
+        let rows = vec![header(&names), row];
+        let rows: Vec<&[DataType]> = rows.iter().map(|r| r.as_slice()).collect();
+        assert_eq!(
+            list_from_rows(&rows).err(),
+            Some(Error::Parse {
+                ticker: Some("ABM".to_owned()),
+                what: "column \"Price\" of dividend list".to_owned(),
+                cause: "expected number at row 2, found \"soon\"".to_owned(),
+            })
+        );
+
+        // No header at all
+        let rows = vec![vec![text("Dividend Champions")]];
+        let rows: Vec<&[DataType]> = rows.iter().map(|r| r.as_slice()).collect();
+        assert!(list_from_rows(&rows).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_list_file() -> Result<(), String> {
+        let mut names = vec!["Symbol", "Company"];
+        names.extend(
+            SCHEMA
+                .iter()
+                .filter(|s| s.required && s.kind == ColumnType::Number)
+                .map(|s| s.name),
+        );
+        let values = vec!["2.5"; names.len() - 2].join(",");
+        let csv = format!(
+            "U.S. Dividend Champions,,\r\n{}\r\nJNJ,\"Johnson & Johnson, \"\"JNJ\"\"\",{values}\r\nABM,,{values}",
+            names.join(",")
+        );
+        let path = std::env::temp_dir().join(format!("champions-{}.csv", std::process::id()));
+        std::fs::write(&path, csv).map_err(|e| e.to_string())?;
+        let df = load_list_file(&path, "Champions").map_err(|e| e.to_string());
+        std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+        let df = df?;
+
+        assert_eq!(df.height(), 2);
+        let companies: Vec<Option<&str>> = df
+            .column("Company")
+            .and_then(|c| c.utf8().map(|c| c.into_iter().collect()))
+            .map_err(|e| e.to_string())?;
+        assert_eq!(companies, vec![Some("Johnson & Johnson, \"JNJ\""), None]);
+        let prices: Vec<Option<f64>> = df
+            .column("Price")
+            .and_then(|c| c.f64().map(|c| c.into_iter().collect()))
+            .map_err(|e| e.to_string())?;
+        assert_eq!(prices, vec![Some(2.5), Some(2.5)]);
+
+        assert!(matches!(
+            load_list_file(Path::new("champions.pdf"), "All"),
+            Err(Error::Config(_))
+        ));
+        Ok(())
+    }
+}
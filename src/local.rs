@@ -0,0 +1,295 @@
+//! Provider reading data of companies from local CSV or JSON files.
+//!
+//! Meant for instruments not served well by online providers (e.g. stocks listed in Warsaw,
+//! funds) and for working without network. Every ticker has its own directory:
+//!
+//! ```text
+//! <dir>/<TICKER>/prices.csv       date,close[,currency]           share price history
+//! <dir>/<TICKER>/dividends.csv    ex_date,amount[,pay_date,record_date,declaration_date,
+//!                                 currency,kind,declared_frequency]
+//! <dir>/<TICKER>/splits.csv       date,from,to                    (optional)
+//! <dir>/<TICKER>/details.csv      name,sector,div_yield,payout_ratio (optional)
+//! <dir>/<TICKER>/financials.csv   the same columns as cached financials (optional)
+//! ```
+//!
+//! Dates are written as YYYY-MM-DD and amounts in currency of share price. Instead of CSV file
+//! the same data may be given as `<name>.json` holding array of objects with the same fields
+//! e.g. `[{"ex_date": "2023-05-15", "amount": 0.43}]`. Files of cache directory (see
+//! `crate::cache`) can be used after renaming `quote.csv` to `prices.csv` and adding "date"
+//! column.
+
+use chrono::prelude::*;
+use polars::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::frame::{
+    details_from_df, dividends_from_df, f64_column, financials_from_df, read_df, splits_from_df,
+    str_column,
+};
+use crate::provider::{CompanyDetails, DividendDataProvider, FinancialReport, Quote};
+use crate::{DataSource, DividendSeries, Error, SplitEvent};
+
+/// Provider serving data from files laid out as described in module documentation
+pub struct LocalProvider {
+    dir: PathBuf,
+}
+
+impl LocalProvider {
+    pub fn new(dir: &Path) -> Result<Self, Error> {
+        if !dir.is_dir() {
+            return Err(Error::Config(format!(
+                "Directory of local data: {} does not exist",
+                dir.display()
+            )));
+        }
+        Ok(Self {
+            dir: dir.to_owned(),
+        })
+    }
+
+    /// Contents of CSV or JSON file of given name, None if there is neither of them
+    fn read(&self, symbol: &str, name: &str) -> Result<Option<(PathBuf, DataFrame)>, Error> {
+        let dir = self.dir.join(symbol);
+        let csv = dir.join(format!("{name}.csv"));
+        if let Some(df) = read_df(&csv)? {
+            return Ok(Some((csv, df)));
+        }
+        let json = dir.join(format!("{name}.json"));
+        if !json.exists() {
+            return Ok(None);
+        }
+        let what = format!("local data file {}", json.display());
+        let text = std::fs::read_to_string(&json)
+            .map_err(|e| Error::Config(format!("Unable to read {what}: {e}")))?;
+        let value: serde_json::Value =
+            serde_json::from_str(&text).map_err(|e| Error::parse(&what, e))?;
+        let df = json_to_df(&value).map_err(|e| Error::parse(&what, e))?;
+        Ok(Some((json, df)))
+    }
+
+    /// Convert data of required file
+    fn load<T>(
+        &self,
+        symbol: &str,
+        name: &str,
+        convert: impl Fn(&DataFrame) -> PolarsResult<T>,
+    ) -> Result<T, Error> {
+        self.load_optional(symbol, name, convert)?.ok_or_else(|| {
+            Error::missing(&format!("local {name} file"))
+                .with_ticker(symbol)
+                .with_provider(self.source())
+        })
+    }
+
+    /// Convert data of file that may be missing
+    fn load_optional<T>(
+        &self,
+        symbol: &str,
+        name: &str,
+        convert: impl Fn(&DataFrame) -> PolarsResult<T>,
+    ) -> Result<Option<T>, Error> {
+        match self.read(symbol, name)? {
+            Some((path, df)) => convert(&df).map(Some).map_err(|e| {
+                Error::parse(&format!("local data file {}", path.display()), e).with_ticker(symbol)
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Array of JSON objects as data frame. Fields holding only numbers become float columns,
+/// remaining ones string columns
+fn json_to_df(value: &serde_json::Value) -> PolarsResult<DataFrame> {
+    let rows = value
+        .as_array()
+        .ok_or_else(|| PolarsError::ComputeError("expected array of objects".into()))?;
+    let mut names: Vec<&str> = vec![];
+    for row in rows {
+        let row = row
+            .as_object()
+            .ok_or_else(|| PolarsError::ComputeError("expected array of objects".into()))?;
+        for name in row.keys() {
+            if !names.contains(&name.as_str()) {
+                names.push(name.as_str());
+            }
+        }
+    }
+    let columns = names
+        .into_iter()
+        .map(|name| {
+            let values: Vec<Option<&serde_json::Value>> = rows
+                .iter()
+                .map(|row| row.get(name).filter(|v| !v.is_null()))
+                .collect();
+            if values.iter().flatten().all(|v| v.is_number()) {
+                let numbers: Vec<Option<f64>> =
+                    values.iter().map(|v| v.and_then(|v| v.as_f64())).collect();
+                Series::new(name, numbers)
+            } else {
+                let strings: Vec<Option<String>> = values
+                    .iter()
+                    .map(|v| {
+                        v.map(|v| match v.as_str() {
+                            Some(s) => s.to_owned(),
+                            None => v.to_string(),
+                        })
+                    })
+                    .collect();
+                Series::new(name, strings)
+            }
+        })
+        .collect();
+    DataFrame::new(columns)
+}
+
+/// Close price of as-of date or of the last day before it
+fn quote_from_prices(df: &DataFrame, as_of: NaiveDate) -> PolarsResult<Option<Quote>> {
+    let dates = str_column(df, "date")?;
+    let closes = f64_column(df, "close")?;
+    let currencies = if df.get_column_names().contains(&"currency") {
+        str_column(df, "currency")?
+    } else {
+        vec![None; df.height()]
+    };
+    Ok(dates
+        .into_iter()
+        .zip(closes)
+        .zip(currencies)
+        .filter_map(|((date, close), currency)| {
+            let date = NaiveDate::parse_from_str(&date?, "%Y-%m-%d").ok()?;
+            Some((date, close?, currency))
+        })
+        .filter(|(date, _, _)| *date <= as_of)
+        .max_by_key(|(date, _, _)| *date)
+        .map(|(_, price, currency)| Quote { price, currency }))
+}
+
+impl DividendDataProvider for LocalProvider {
+    fn source(&self) -> DataSource {
+        DataSource::Other("Local")
+    }
+
+    fn quote(&self, symbol: &str, as_of: NaiveDate) -> Result<Quote, Error> {
+        self.load(symbol, "prices", |df| quote_from_prices(df, as_of))?
+            .ok_or_else(|| {
+                Error::missing(&format!("share price as of {as_of}"))
+                    .with_ticker(symbol)
+                    .with_provider(self.source())
+            })
+    }
+
+    fn dividend_history(&self, symbol: &str) -> Result<DividendSeries, Error> {
+        self.load(symbol, "dividends", dividends_from_df)
+    }
+
+    fn splits(&self, symbol: &str) -> Result<Vec<SplitEvent>, Error> {
+        Ok(self
+            .load_optional(symbol, "splits", splits_from_df)?
+            .unwrap_or_default())
+    }
+
+    fn company_details(&self, symbol: &str) -> Result<CompanyDetails, Error> {
+        Ok(self
+            .load_optional(symbol, "details", |df| {
+                // Details file may have only some of the columns
+                let column = |name: &str| df.get_column_names().contains(&name);
+                let mut df = df.clone();
+                for name in ["name", "sector", "div_yield", "payout_ratio"] {
+                    if !column(name) {
+                        df.with_column(Series::full_null(name, df.height(), &DataType::Utf8))?;
+                    }
+                }
+                details_from_df(&df)
+            })?
+            .unwrap_or_default())
+    }
+
+    fn financials(&self, symbol: &str) -> Result<Vec<FinancialReport>, Error> {
+        Ok(self
+            .load_optional(symbol, "financials", financials_from_df)?
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DividendEvent;
+
+    #[test]
+    fn test_local_provider() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("local-provider-{}", std::process::id()));
+        let write = |path: &str, contents: &str| -> Result<(), String> {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+            std::fs::write(path, contents).map_err(|e| e.to_string())
+        };
+        write(
+            "PKN.WA/prices.csv",
+            "date,close,currency\n2023-06-30,62.1,PLN\n2023-07-03,63.5,PLN\n",
+        )?;
+        write(
+            "PKN.WA/dividends.csv",
+            "ex_date,amount\n2022-08-03,3.5\n2023-08-10,5.5\n",
+        )?;
+        write("PKN.WA/details.csv", "name\nORLEN\n")?;
+        write(
+            "FUND/prices.json",
+            r#"[{"date": "2023-07-03", "close": 101}]"#,
+        )?;
+        write(
+            "FUND/dividends.json",
+            r#"[{"ex_date": "2023-03-15", "amount": 0.8, "kind": "special"},
+                {"ex_date": "2023-06-15", "amount": 0.85, "pay_date": null}]"#,
+        )?;
+        let provider = LocalProvider::new(&dir).map_err(|e| e.to_string())?;
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        // Weekend falls back to the last price before it
+        assert_eq!(
+            provider.quote("PKN.WA", date(2023, 7, 2)),
+            Ok(Quote {
+                price: 62.1,
+                currency: Some("PLN".to_owned()),
+            })
+        );
+        assert!(provider.quote("PKN.WA", date(2023, 6, 1)).is_err());
+        assert_eq!(
+            provider
+                .dividend_history("PKN.WA")
+                .map(|d| d.events().len()),
+            Ok(2)
+        );
+        assert_eq!(
+            provider.company_details("PKN.WA").map(|d| d.name),
+            Ok(Some("ORLEN".to_owned()))
+        );
+        assert_eq!(provider.splits("PKN.WA"), Ok(vec![]));
+        assert_eq!(provider.financials("PKN.WA"), Ok(vec![]));
+
+        assert_eq!(
+            provider.quote("FUND", date(2023, 7, 3)).map(|q| q.price),
+            Ok(101.0)
+        );
+        let mut special = DividendEvent::new(date(2023, 3, 15), 0.8);
+        special.kind = crate::DividendKind::from_name("special");
+        assert_eq!(
+            provider.dividend_history("FUND"),
+            Ok(DividendSeries::new(vec![
+                special,
+                DividendEvent::new(date(2023, 6, 15), 0.85),
+            ]))
+        );
+
+        // Ticker without files
+        assert_eq!(
+            provider
+                .dividend_history("ABR")
+                .map_err(|e| e.ticker().map(|t| t.to_owned())),
+            Err(Some("ABR".to_owned()))
+        );
+
+        std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
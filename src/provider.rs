@@ -0,0 +1,899 @@
+//! Sources of share prices, dividends and financial reports.
+//!
+//! Every source of data implements `DividendDataProvider`, so dividend metrics
+//! (`crate::get_dividend_profile`) are computed the same way regardless of where the data
+//! came from. In-house providers only need to implement this trait.
+
+use chrono::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::cache::{Cache, CachingProvider};
+use crate::fetch::RateLimiter;
+use crate::local::LocalProvider;
+use crate::recording::{RecordingTransport, ReplayTransport};
+use crate::response::{PolygonDividend, PolygonFinancialValue, PolygonFinancials, YahooChart};
+use crate::retry::RetryPolicy;
+use crate::transport::{HttpTransport, Request, Transport};
+use crate::{DataSource, DividendEvent, DividendKind, DividendSeries, Error, SplitEvent};
+
+/// Data providers selectable from command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProviderKind {
+    /// Yahoo finance
+    Yahoo,
+    /// Polygon.io API (requires POLYGON_AUTH_KEY)
+    Polygon,
+    /// Dividend list spreadsheet given with "data" argument
+    File,
+    /// CSV/JSON files in directory given with "local-dir" argument (see `crate::local`)
+    Local,
+}
+
+impl ProviderKind {
+    /// Provider to be used when none was explicitly selected
+    pub fn from_env() -> Self {
+        if std::env::var("POLYGON_AUTH_KEY").is_ok() {
+            ProviderKind::Polygon
+        } else {
+            ProviderKind::Yahoo
+        }
+    }
+}
+
+/// Most recent share price of company
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub price: f64,
+    pub currency: Option<String>,
+}
+
+/// Descriptive data of company. Dividend yield and payout ratio are the ones
+/// reported by provider itself (if any)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompanyDetails {
+    pub name: Option<String>,
+    pub sector: Option<String>,
+    /// Dividend yield[%]
+    pub div_yield: Option<f64>,
+    /// Dividend payout ratio[%]
+    pub payout_ratio: Option<f64>,
+}
+
+/// Period covered by financial report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportTimeframe {
+    #[default]
+    Annual,
+    Quarterly,
+}
+
+/// Values of financial report needed for dividend analysis
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FinancialReport {
+    pub fiscal_year: String,
+    pub fiscal_period: String,
+    pub timeframe: ReportTimeframe,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub net_cash_flow_from_operating_activities: Option<f64>,
+    pub basic_average_shares: Option<f64>,
+    /// Purchases of property, plant and equipment (positive amount)
+    pub capital_expenditure: Option<f64>,
+    pub net_income: Option<f64>,
+    pub diluted_earnings_per_share: Option<f64>,
+    /// Cash dividends paid to shareholders (positive amount)
+    pub dividends_paid: Option<f64>,
+}
+
+impl FinancialReport {
+    /// Operating cash flow less capital expenditure
+    pub fn free_cash_flow(&self) -> Option<f64> {
+        Some(self.net_cash_flow_from_operating_activities? - self.capital_expenditure?)
+    }
+}
+
+/// Source of data for dividend analysis. Providers are shared by threads fetching data of
+/// different companies
+pub trait DividendDataProvider: Send + Sync {
+    /// Which provider data comes from
+    fn source(&self) -> DataSource;
+
+    /// Share price at the close of given day (or the last trading day before it)
+    fn quote(&self, symbol: &str, as_of: NaiveDate) -> Result<Quote, Error>;
+
+    /// All dividends paid by company. Dividends after the date of analysis are filtered out
+    /// by caller
+    fn dividend_history(&self, symbol: &str) -> Result<DividendSeries, Error>;
+
+    /// All stock splits of company. Dividend history is adjusted for them by caller
+    fn splits(&self, symbol: &str) -> Result<Vec<SplitEvent>, Error>;
+
+    /// Both dividend history and splits of company. Providers serving them with one response
+    /// override it, so that the response is downloaded once
+    fn dividends_and_splits(
+        &self,
+        symbol: &str,
+    ) -> Result<(DividendSeries, Vec<SplitEvent>), Error> {
+        Ok((self.dividend_history(symbol)?, self.splits(symbol)?))
+    }
+
+    fn company_details(&self, symbol: &str) -> Result<CompanyDetails, Error>;
+
+    /// Financial reports of company. Empty if provider does not serve them
+    fn financials(&self, symbol: &str) -> Result<Vec<FinancialReport>, Error>;
+}
+
+/// Create provider of given kind
+pub fn create_provider(kind: ProviderKind) -> Result<Box<dyn DividendDataProvider>, Error> {
+    match kind {
+        ProviderKind::Yahoo => Ok(Box::new(YahooProvider::new()?)),
+        ProviderKind::Polygon => Ok(Box::new(PolygonProvider::new()?)),
+        ProviderKind::File => Err(Error::Config(
+            "\"file\" provider serves data only from list given with \"data\" argument".to_owned(),
+        )),
+        ProviderKind::Local => Err(Error::Config(
+            "\"local\" provider requires \"local-dir\" argument".to_owned(),
+        )),
+    }
+}
+
+/// Where provider responses come from and whether they should be stored
+#[derive(Debug, Clone)]
+pub struct ProviderOptions {
+    pub kind: ProviderKind,
+    /// Store every response under this directory (see `crate::recording`)
+    pub record_dir: Option<PathBuf>,
+    /// Serve responses stored earlier under this directory instead of asking provider
+    pub replay_dir: Option<PathBuf>,
+    /// Serve fresh enough responses from on-disk cache (not used when recording or replaying)
+    pub cache: Option<Cache>,
+    /// Limit of requests sent to Polygon.io, shared by all its providers
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// How failed requests to provider are repeated
+    pub retry: RetryPolicy,
+    /// Directory of files served by "local" provider
+    pub local_dir: Option<PathBuf>,
+}
+
+impl ProviderOptions {
+    pub fn new(kind: ProviderKind) -> Self {
+        Self {
+            kind,
+            record_dir: None,
+            replay_dir: None,
+            cache: None,
+            rate_limiter: None,
+            retry: RetryPolicy::default(),
+            local_dir: None,
+        }
+    }
+
+    /// Transport of requests to given provider: live one, recording responses or replaying
+    /// recorded ones
+    pub(crate) fn transport(&self, source: DataSource) -> Result<Box<dyn Transport>, Error> {
+        match (&self.replay_dir, &self.record_dir) {
+            (Some(_), Some(_)) => Err(Error::Config(
+                "Responses cannot be recorded and replayed at the same time".to_owned(),
+            )),
+            (Some(dir), None) => Ok(Box::new(ReplayTransport::new(dir, source)?)),
+            (None, record_dir) => {
+                let mut transport = match source {
+                    DataSource::Polygon => HttpTransport::polygon()?,
+                    source => HttpTransport::new(source)?,
+                }
+                .with_retry(self.retry.clone());
+                // Only Polygon.io limits rate of requests
+                if let (DataSource::Polygon, Some(limiter)) = (source, &self.rate_limiter) {
+                    transport = transport.rate_limited(limiter.clone());
+                }
+                let mut transport: Box<dyn Transport> = Box::new(transport);
+                if let Some(dir) = record_dir {
+                    transport = Box::new(RecordingTransport::new(transport, dir)?);
+                }
+                Ok(transport)
+            }
+        }
+    }
+
+    /// Create provider according to options
+    pub fn build(&self) -> Result<Box<dyn DividendDataProvider>, Error> {
+        let replaying = self.replay_dir.is_some();
+        let recording = self.record_dir.is_some();
+        let mut provider: Box<dyn DividendDataProvider> = match self.kind {
+            ProviderKind::Yahoo => Box::new(YahooProvider::with_transport(
+                self.transport(DataSource::Yahoo)?,
+            )),
+            ProviderKind::Polygon => Box::new(PolygonProvider::with_transport(
+                self.transport(DataSource::Polygon)?,
+            )),
+            ProviderKind::Local if replaying || recording => {
+                return Err(Error::Config(
+                    "Responses of \"local\" provider are neither recorded nor replayed".to_owned(),
+                ))
+            }
+            ProviderKind::Local => match &self.local_dir {
+                Some(dir) => Box::new(LocalProvider::new(dir)?),
+                None => create_provider(ProviderKind::Local)?,
+            },
+            kind => create_provider(kind)?,
+        };
+        // Local files are read quickly enough without caching. Replayed responses are on disk
+        // already, while recorded ones have to be actually received
+        let cache = self
+            .cache
+            .as_ref()
+            .filter(|_| self.kind != ProviderKind::Local && !replaying && !recording);
+        if let Some(cache) = cache {
+            provider = Box::new(CachingProvider::new(provider, cache.clone()));
+        }
+        Ok(provider)
+    }
+}
+
+const YAHOO_CHART_URL: &str = "https://query1.finance.yahoo.com/v8/finance/chart";
+const YAHOO_SUMMARY_URL: &str = "https://query2.finance.yahoo.com/v10/finance/quoteSummary";
+pub(crate) const POLYGON_URL: &str = "https://api.polygon.io";
+
+pub struct YahooProvider {
+    transport: Box<dyn Transport>,
+}
+
+impl YahooProvider {
+    /// Provider asking Yahoo over HTTP
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self::with_transport(Box::new(HttpTransport::new(
+            DataSource::Yahoo,
+        )?)))
+    }
+
+    /// Provider parsing responses delivered by given transport e.g. replayed ones
+    pub fn with_transport(transport: Box<dyn Transport>) -> Self {
+        Self { transport }
+    }
+
+    /// Daily quotes of given range of days, together with dividend and split events if asked
+    /// for
+    fn chart(
+        &self,
+        symbol: &str,
+        name: &str,
+        (start, end): (NaiveDate, NaiveDate),
+        events: bool,
+    ) -> Result<YahooChart, Error> {
+        let mut url = format!(
+            "{YAHOO_CHART_URL}/{symbol}?period1={}&period2={}&interval=1d",
+            timestamp(start, 0, 0, 0)?,
+            timestamp(end, 23, 59, 59)?
+        );
+        if events {
+            url.push_str("&events=div%7Csplit");
+        }
+        let body = self.transport.get(&Request::ticker(symbol, name, url))?;
+        serde_json::from_str(&body)
+            .map_err(|e| Error::parse(&format!("{name} response"), e).with_ticker(symbol))
+    }
+
+    /// Thirty years of quotes together with dividend and split events, so that analysis can be
+    /// made also as of past dates
+    fn events_history(&self, symbol: &str) -> Result<YahooChart, Error> {
+        let end = Utc::now().date_naive();
+        let start = NaiveDate::from_ymd_opt(end.year() - 30, 1, 1)
+            .ok_or_else(|| Error::parse("dividend history range", end))?;
+        self.chart(symbol, "chart", (start, end), true)
+    }
+
+    /// Dividends and splits of company, both parsed from single download of its history
+    fn events(&self, symbol: &str) -> Result<(DividendSeries, Vec<SplitEvent>), Error> {
+        let resp = self.events_history(symbol)?;
+        // Yahoo reports only ex-dividend date and amount, currency comes from quotes metadata
+        let currency = resp.currency().map(|c| c.to_owned());
+        let mut div_history: Vec<DividendEvent> = vec![];
+        let mut splits: Vec<SplitEvent> = vec![];
+
+        for r in resp.results() {
+            if let Some(events) = &r.events {
+                if let Some(dividends) = &events.dividends {
+                    for (_timestamp_str, div) in dividends {
+                        let ex_date = DateTime::from_timestamp(div.date, 0)
+                            .ok_or_else(|| Error::parse("dividend date", div.date))?
+                            .date_naive();
+                        let mut event = DividendEvent::new(ex_date, div.amount);
+                        event.currency = currency.clone();
+                        div_history.push(event);
+                    }
+                }
+                if let Some(yahoo_splits) = &events.splits {
+                    for (_timestamp_str, split) in yahoo_splits {
+                        let date = DateTime::from_timestamp(split.date, 0)
+                            .ok_or_else(|| Error::parse("split date", split.date))?
+                            .date_naive();
+                        splits.push(SplitEvent {
+                            date,
+                            from: split.denominator,
+                            to: split.numerator,
+                        });
+                    }
+                }
+            }
+        }
+        splits.sort_by_key(|s| s.date);
+
+        Ok((DividendSeries::new(div_history), splits))
+    }
+}
+
+impl DividendDataProvider for YahooProvider {
+    fn source(&self) -> DataSource {
+        DataSource::Yahoo
+    }
+
+    fn quote(&self, symbol: &str, as_of: NaiveDate) -> Result<Quote, Error> {
+        log::info!("Yahoo: Getting Ticker: {} as of {}", symbol, as_of);
+        // Week long range so that as-of date falling on weekend or holiday has a quote
+        let range = (as_of - chrono::Duration::days(7), as_of);
+        let response = self.chart(symbol, "quote", range, false)?;
+        // Replayed response may cover days after as-of date
+        let end_of_day = timestamp(as_of, 23, 59, 59)?;
+        let price = response
+            .closes()
+            .into_iter()
+            .rfind(|(timestamp, _)| *timestamp <= end_of_day)
+            .map(|(_, close)| close)
+            .ok_or_else(|| Error::missing(&format!("share price as of {as_of}")))?;
+
+        // Pobieranie metadanych (zawierają dodatkowe informacje o akcji)
+        let currency = match response.currency() {
+            Some(currency) => {
+                log::info!("Currency: {}", currency);
+                Some(currency.to_owned())
+            }
+            None => {
+                log::warn!("⚠️  No currency information available for stock");
+                None
+            }
+        };
+        Ok(Quote { price, currency })
+    }
+
+    fn dividend_history(&self, symbol: &str) -> Result<DividendSeries, Error> {
+        self.events(symbol).map(|(div_history, _)| div_history)
+    }
+
+    fn splits(&self, symbol: &str) -> Result<Vec<SplitEvent>, Error> {
+        self.events(symbol).map(|(_, splits)| splits)
+    }
+
+    fn dividends_and_splits(
+        &self,
+        symbol: &str,
+    ) -> Result<(DividendSeries, Vec<SplitEvent>), Error> {
+        self.events(symbol)
+    }
+
+    fn company_details(&self, symbol: &str) -> Result<CompanyDetails, Error> {
+        let url = format!("{YAHOO_SUMMARY_URL}/{symbol}?modules=summaryDetail&formatted=false");
+        let body = self
+            .transport
+            .get(&Request::ticker(symbol, "summary", url).in_session())?;
+        let response_info =
+            parse_json(&body, "summary response").map_err(|e| e.with_ticker(symbol))?;
+        let mut details = CompanyDetails::default();
+        match response_info["quoteSummary"]["result"].as_array() {
+            Some(summary) => {
+                summary.iter().for_each(|item| {
+                    let summary_detail = &item["summaryDetail"];
+                    if let Some(dividend_yield) = summary_detail["dividendYield"].as_f64() {
+                        details.div_yield = Some(dividend_yield * 100.0);
+                    }
+                    if let Some(pr) = summary_detail["payoutRatio"].as_f64() {
+                        details.payout_ratio = Some(pr * 100.0);
+                    }
+                });
+            }
+            None => {
+                log::warn!("{symbol}: no summary of stock");
+            }
+        }
+        Ok(details)
+    }
+
+    fn financials(&self, _symbol: &str) -> Result<Vec<FinancialReport>, Error> {
+        Ok(vec![])
+    }
+}
+
+pub struct PolygonProvider {
+    transport: Box<dyn Transport>,
+}
+
+impl PolygonProvider {
+    /// Provider asking Polygon.io over HTTP, authorized with POLYGON_AUTH_KEY
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self::with_transport(Box::new(HttpTransport::polygon()?)))
+    }
+
+    /// Provider parsing responses delivered by given transport e.g. replayed ones
+    pub fn with_transport(transport: Box<dyn Transport>) -> Self {
+        Self { transport }
+    }
+}
+
+/// Results of all pages of Polygon.io response. Every page but the last one links the next one
+pub(crate) fn polygon_results(
+    transport: &dyn Transport,
+    request: &Request,
+) -> Result<Vec<serde_json::Value>, Error> {
+    let with_ticker = |e: Error| match &request.symbol {
+        Some(symbol) => e.with_ticker(symbol),
+        None => e,
+    };
+    let mut results = vec![];
+    let mut page = Some(request.clone());
+    let mut number = 1;
+    while let Some(current) = page.take() {
+        let body = transport.get(&current)?;
+        let mut resp =
+            parse_json(&body, &format!("{} response", current.name)).map_err(with_ticker)?;
+        log::info!("RESPONSE({}): {resp:#}", current.name.to_uppercase());
+        if let Some(page_results) = resp["results"].as_array_mut() {
+            results.append(page_results);
+        }
+        if let Some(url) = resp["next_url"].as_str() {
+            number += 1;
+            page = Some(request.page(number, url.to_owned()));
+        }
+    }
+    Ok(results)
+}
+
+impl DividendDataProvider for PolygonProvider {
+    fn source(&self) -> DataSource {
+        DataSource::Polygon
+    }
+
+    fn quote(&self, symbol: &str, as_of: NaiveDate) -> Result<Quote, Error> {
+        // Week long range so that as-of date falling on weekend or holiday has a quote
+        let url = format!(
+            "{POLYGON_URL}/v2/aggs/ticker/{symbol}/range/1/day/{}/{as_of}",
+            as_of - chrono::Duration::days(7)
+        );
+        let body = self.transport.get(&Request::ticker(symbol, "quote", url))?;
+        let resp = parse_json(&body, "quote response").map_err(|e| e.with_ticker(symbol))?;
+        log::info!("RESPONSE(AGGREGATES): {resp:#}");
+
+        // Daily bars start at midnight of exchange, stamped in milliseconds. Replayed response
+        // may cover days after as-of date
+        let end_of_day = timestamp(as_of, 23, 59, 59)? * 1000;
+        let share_price = resp["results"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .rev()
+            .filter(|bar| bar["t"].as_i64().map_or(false, |t| t <= end_of_day))
+            .find_map(|bar| bar["c"].as_f64())
+            .ok_or_else(|| Error::missing(&format!("share price as of {as_of}")))?;
+        log::info!("Stock price at {as_of}: {share_price}");
+        Ok(Quote {
+            price: share_price,
+            currency: None,
+        })
+    }
+
+    fn dividend_history(&self, symbol: &str) -> Result<DividendSeries, Error> {
+        let url = format!("{POLYGON_URL}/v3/reference/dividends?ticker={symbol}");
+        let results = polygon_results(
+            self.transport.as_ref(),
+            &Request::ticker(symbol, "dividends", url),
+        )?;
+        let results: Vec<PolygonDividend> =
+            serde_json::from_value(serde_json::Value::Array(results))
+                .map_err(|e| Error::parse("dividends response", e).with_ticker(symbol))?;
+        results.iter().for_each(|x| {
+            log::info!(
+                "{}: ex date: {}, payment date: {:?}, div type: {} amount: {}",
+                x.ticker,
+                x.ex_dividend_date,
+                x.pay_date,
+                x.dividend_type,
+                x.cash_amount
+            );
+        });
+        // All types of payments are kept, they are told apart by analysis
+        let div_history = results
+            .iter()
+            .map(to_dividend_event)
+            .collect::<Result<Vec<_>, Error>>()
+            .map_err(|e| e.with_ticker(symbol))?;
+
+        Ok(DividendSeries::new(div_history))
+    }
+
+    fn splits(&self, symbol: &str) -> Result<Vec<SplitEvent>, Error> {
+        let url = format!("{POLYGON_URL}/v3/reference/splits?ticker={symbol}");
+        let results = polygon_results(
+            self.transport.as_ref(),
+            &Request::ticker(symbol, "splits", url),
+        )?;
+
+        let mut splits = results
+            .iter()
+            .map(|x| {
+                let date = x["execution_date"]
+                    .as_str()
+                    .ok_or_else(|| Error::missing("split date"))?;
+                Ok(SplitEvent {
+                    date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .map_err(|e| Error::parse("split date", e))?,
+                    from: x["split_from"]
+                        .as_f64()
+                        .ok_or_else(|| Error::missing("split ratio"))?,
+                    to: x["split_to"]
+                        .as_f64()
+                        .ok_or_else(|| Error::missing("split ratio"))?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map_err(|e| e.with_ticker(symbol))?;
+        splits.sort_by_key(|s| s.date);
+
+        Ok(splits)
+    }
+
+    fn company_details(&self, symbol: &str) -> Result<CompanyDetails, Error> {
+        let url = format!("{POLYGON_URL}/v3/reference/tickers/{symbol}");
+        let body = self
+            .transport
+            .get(&Request::ticker(symbol, "details", url))?;
+        let resp = parse_json(&body, "details response").map_err(|e| e.with_ticker(symbol))?;
+        log::info!("RESPONSE(COMPANY DETAILS): {resp:#}");
+
+        Ok(CompanyDetails {
+            sector: resp["results"]["sic_description"]
+                .as_str()
+                .map(|s| s.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    fn financials(&self, symbol: &str) -> Result<Vec<FinancialReport>, Error> {
+        // Annual, quarterly and TTM filings come mixed, so all pages are needed to have both
+        // the latest annual report and four consecutive quarters
+        let url = format!("{POLYGON_URL}/vX/reference/financials?ticker={symbol}&limit=100");
+        let results = polygon_results(
+            self.transport.as_ref(),
+            &Request::ticker(symbol, "financials", url),
+        )?;
+
+        results
+            .iter()
+            .filter_map(|r| {
+                let timeframe = match r["timeframe"].as_str() {
+                    Some("annual") => ReportTimeframe::Annual,
+                    Some("quarterly") => ReportTimeframe::Quarterly,
+                    _ => return None,
+                };
+                let company_name = r["company_name"].as_str().unwrap_or_default();
+                let fiscal_year = r["fiscal_year"].as_str().unwrap_or_default();
+                let fiscal_period = r["fiscal_period"].as_str().unwrap_or_default();
+                log::info!(
+                    "{}: start date: {}, end date: {}, fiscal_year: {}, timeframe: {} fiscal_period: {}",
+                    r["tickers"],
+                    r["start_date"],
+                    r["end_date"],
+                    fiscal_year,
+                    r["timeframe"],
+                    fiscal_period
+                );
+                let financials: PolygonFinancials =
+                    match serde_json::from_value(r["financials"].clone()) {
+                        Ok(financials) => financials,
+                        Err(e) => {
+                            let what = format!("{fiscal_year} {fiscal_period} financials");
+                            return Some(Err(Error::parse(&what, e).with_ticker(symbol)));
+                        }
+                    };
+                let parse_date = |date: &serde_json::Value| {
+                    date.as_str()
+                        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                };
+                Some(Ok(FinancialReport {
+                    fiscal_year: fiscal_year.to_owned(),
+                    fiscal_period: fiscal_period.to_owned(),
+                    timeframe,
+                    start_date: parse_date(&r["start_date"]),
+                    end_date: parse_date(&r["end_date"]),
+                    net_cash_flow_from_operating_activities: get_net_cash_flow(
+                        &financials,
+                        company_name,
+                        fiscal_year,
+                        fiscal_period,
+                    ),
+                    basic_average_shares: get_basic_average_shares(
+                        &financials,
+                        company_name,
+                        fiscal_year,
+                        fiscal_period,
+                    ),
+                    capital_expenditure: financials
+                        .cash_flow_statement
+                        .as_ref()
+                        .and_then(|statement| {
+                            get_statement_value(statement, CAPITAL_EXPENDITURE_KEYS)
+                        })
+                        .map(f64::abs),
+                    net_income: financials
+                        .income_statement
+                        .as_ref()
+                        .and_then(|statement| get_statement_value(statement, NET_INCOME_KEYS)),
+                    diluted_earnings_per_share: financials
+                        .income_statement
+                        .as_ref()
+                        .and_then(|statement| get_statement_value(statement, DILUTED_EPS_KEYS)),
+                    dividends_paid: financials
+                        .cash_flow_statement
+                        .as_ref()
+                        .and_then(|statement| {
+                            get_statement_value(statement, DIVIDENDS_PAID_KEYS)
+                        })
+                        .map(f64::abs),
+                }))
+            })
+            .collect()
+    }
+}
+
+fn to_dividend_event(x: &PolygonDividend) -> Result<DividendEvent, Error> {
+    let parse_date = |date: &Option<String>| {
+        date.as_ref()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+    };
+    Ok(DividendEvent {
+        ex_date: NaiveDate::parse_from_str(&x.ex_dividend_date, "%Y-%m-%d")
+            .map_err(|e| Error::parse("ex-dividend date", e))?,
+        pay_date: parse_date(&x.pay_date),
+        record_date: parse_date(&x.record_date),
+        declaration_date: parse_date(&x.declaration_date),
+        amount: x.cash_amount,
+        currency: x.currency.clone(),
+        kind: Some(match x.dividend_type.as_str() {
+            "CD" => DividendKind::Regular,
+            "SC" => DividendKind::Special,
+            // Long and short term capital gains distributions
+            _ => DividendKind::Irregular,
+        }),
+        // Zero stands for one-time payment
+        declared_frequency: Some(x.frequency).filter(|f| *f > 0),
+        split_factor: 1.0,
+    })
+}
+
+/// Seconds since epoch of given time (UTC) of given day
+fn timestamp(date: NaiveDate, h: u32, m: u32, s: u32) -> Result<i64, Error> {
+    date.and_hms_opt(h, m, s)
+        .map(|time| time.and_utc().timestamp())
+        .ok_or_else(|| Error::parse("date of quote", date))
+}
+
+/// Body of response parsed as JSON
+fn parse_json(body: &str, what: &str) -> Result<serde_json::Value, Error> {
+    serde_json::from_str(body).map_err(|e| Error::parse(what, e))
+}
+
+fn get_net_cash_flow(
+    fd: &PolygonFinancials,
+    company_name: &str,
+    fiscal_year: &str,
+    fiscal_period: &str,
+) -> Option<f64> {
+    if let Some(ismap) = &fd.cash_flow_statement {
+        if let Some(net_cash_flow) = ismap.get("net_cash_flow_from_operating_activities") {
+            log::info!(
+                "{}: {} {} net cash flow: {:?} of {:?}, labeled as {:?}",
+                company_name,
+                fiscal_year,
+                fiscal_period,
+                net_cash_flow.value,
+                net_cash_flow.unit,
+                net_cash_flow.label
+            );
+
+            // Entry without value is as good as missing one
+            net_cash_flow.value
+        } else {
+            log::info!("Error: Implement missing cash flow operating statement");
+            None
+        }
+    } else {
+        log::info!("Error: Implement missing cash flow statement");
+        None
+    }
+}
+
+// Names Polygon reports values of financial statements under. Companies report capital
+// expenditure and dividends paid under different names, so the first one present is used
+const CAPITAL_EXPENDITURE_KEYS: &[&str] = &[
+    "payments_to_acquire_property_plant_and_equipment",
+    "capital_expenditure",
+];
+const NET_INCOME_KEYS: &[&str] = &["net_income_loss", "net_income_loss_attributable_to_parent"];
+const DILUTED_EPS_KEYS: &[&str] = &["diluted_earnings_per_share"];
+const DIVIDENDS_PAID_KEYS: &[&str] = &["payments_of_dividends", "dividends_paid"];
+
+/// Value of the first of given entries present in financial statement
+fn get_statement_value(
+    statement: &HashMap<String, PolygonFinancialValue>,
+    keys: &[&str],
+) -> Option<f64> {
+    keys.iter().find_map(|key| {
+        let entry = statement.get(*key)?;
+        log::info!(
+            "{key}: {:?} of {:?}, labeled as {:?}",
+            entry.value,
+            entry.unit,
+            entry.label
+        );
+        entry.value
+    })
+}
+
+fn get_basic_average_shares(
+    fd: &PolygonFinancials,
+    company_name: &str,
+    fiscal_year: &str,
+    fiscal_period: &str,
+) -> Option<f64> {
+    if let Some(ismap) = &fd.income_statement {
+        if let Some(basic_average_shares) = ismap.get("basic_average_shares") {
+            log::info!(
+                "{}: {} {} basic average shares: {:?} of {:?}, labeled as {:?}",
+                company_name,
+                fiscal_year,
+                fiscal_period,
+                basic_average_shares.value,
+                basic_average_shares.unit,
+                basic_average_shares.label
+            );
+            basic_average_shares.value
+        } else {
+            None
+        }
+    } else {
+        log::warn!("Implement missing net_cash_flow_continuing");
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    /// Provider of given kind parsing responses recorded under fixtures directory
+    fn replay(kind: ProviderKind) -> Result<Box<dyn DividendDataProvider>, String> {
+        let fixtures =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+        ProviderOptions {
+            replay_dir: Some(fixtures),
+            ..ProviderOptions::new(kind)
+        }
+        .build()
+        .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_yahoo_from_replay() -> Result<(), String> {
+        let provider = replay(ProviderKind::Yahoo)?;
+        assert_eq!(provider.source(), DataSource::Yahoo);
+
+        // Close of as-of date or of the last trading day before it, never of later days
+        assert_eq!(
+            provider.quote("ABR", date("2022-06-30")),
+            Ok(Quote {
+                price: 12.5,
+                currency: Some("USD".to_owned()),
+            })
+        );
+        assert_eq!(
+            provider.quote("ABR", date("2022-07-03")).map(|q| q.price),
+            Ok(12.7)
+        );
+        assert_eq!(
+            provider.quote("ABR", date("2022-06-26")),
+            Err(Error::missing("share price as of 2022-06-26"))
+        );
+
+        let div_history = provider
+            .dividend_history("ABR")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(div_history.len(), 8);
+        let first = &div_history.events()[0];
+        assert_eq!(first.ex_date, date("2021-02-12"));
+        assert_eq!(first.amount, 0.33);
+        assert_eq!(first.currency, Some("USD".to_owned()));
+        assert_eq!(provider.splits("ABR"), Ok(vec![]));
+        assert_eq!(
+            provider.dividends_and_splits("ABR"),
+            Ok((div_history, vec![]))
+        );
+
+        let details = provider.company_details("ABR").map_err(|e| e.to_string())?;
+        assert_eq!(details.div_yield, Some(12.8));
+        assert_eq!(details.payout_ratio, Some(95.0));
+        assert_eq!(provider.financials("ABR"), Ok(vec![]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_polygon_from_replay() -> Result<(), String> {
+        let provider = replay(ProviderKind::Polygon)?;
+        assert_eq!(provider.source(), DataSource::Polygon);
+
+        assert_eq!(
+            provider.quote("ABR", date("2023-07-02")),
+            Ok(Quote {
+                price: 12.5,
+                currency: None,
+            })
+        );
+        assert_eq!(
+            provider.quote("ABR", date("2023-06-27")).map(|q| q.price),
+            Ok(12.35)
+        );
+
+        // Dividends of both pages of response
+        let div_history = provider
+            .dividend_history("ABR")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(div_history.len(), 9);
+        let special = div_history
+            .iter()
+            .find(|e| e.ex_date == date("2022-06-15"))
+            .ok_or("missing special dividend")?;
+        assert_eq!(special.kind, Some(DividendKind::Special));
+        assert_eq!(special.declared_frequency, None);
+        assert_eq!(special.pay_date, Some(date("2022-06-30")));
+        assert_eq!(special.currency, Some("USD".to_owned()));
+        let last = div_history.events().last().ok_or("no dividends")?;
+        assert_eq!(last.kind, Some(DividendKind::Regular));
+        assert_eq!(last.declared_frequency, Some(4));
+        assert_eq!(provider.splits("ABR"), Ok(vec![]));
+
+        let details = provider.company_details("ABR").map_err(|e| e.to_string())?;
+        assert_eq!(
+            details.sector,
+            Some("REAL ESTATE INVESTMENT TRUSTS".to_owned())
+        );
+
+        // Reports of all pages, trailing twelve months one is skipped
+        let reports = provider.financials("ABR").map_err(|e| e.to_string())?;
+        assert_eq!(reports.len(), 2);
+        assert_eq!(
+            reports[0],
+            FinancialReport {
+                fiscal_year: "2022".to_owned(),
+                fiscal_period: "FY".to_owned(),
+                timeframe: ReportTimeframe::Annual,
+                start_date: Some(date("2022-01-01")),
+                end_date: Some(date("2022-12-31")),
+                net_cash_flow_from_operating_activities: Some(320000000.0),
+                basic_average_shares: Some(165000000.0),
+                capital_expenditure: Some(2500000.0),
+                net_income: Some(330000000.0),
+                diluted_earnings_per_share: Some(1.72),
+                dividends_paid: Some(260000000.0),
+            }
+        );
+        assert_eq!(reports[1].fiscal_year, "2021");
+        assert_eq!(reports[1].end_date, Some(date("2021-12-31")));
+
+        // Ticker that was never recorded
+        let err = provider.dividend_history("ABM").unwrap_err();
+        assert_eq!(err.ticker(), Some("ABM"));
+        Ok(())
+    }
+}
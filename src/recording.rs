@@ -0,0 +1,178 @@
+//! Recording and replaying of provider responses.
+//!
+//! `RecordingTransport` forwards requests to a live transport and stores body of every
+//! response, exactly as received from provider:
+//!
+//! ```text
+//! <dir>/<provider>/<TICKER>/<request>.json    e.g. Yahoo/ABR/chart.json
+//! <dir>/<provider>/<request>.json             requests for whole market e.g. Polygon/tickers.json
+//! ```
+//!
+//! Following pages of paginated responses are stored as `<request>-2.json` and so on.
+//! `ReplayTransport` serves those bodies back to provider, which parses them the same way as
+//! live ones, so analysis can be run (and tested) against frozen data without network.
+//!
+//! Replayed response is picked by name of request, not by its URL. Hence quote recorded once
+//! is served for any as-of date: provider takes the last close not after that date, and reports
+//! missing share price for dates before the recorded ones.
+
+use std::path::{Path, PathBuf};
+
+use crate::transport::{Request, Transport};
+use crate::{DataSource, Error};
+
+/// Location of recorded body of response to given request
+fn response_path(dir: &Path, source: DataSource, request: &Request) -> PathBuf {
+    let dir = dir.join(source.to_string());
+    let dir = match &request.symbol {
+        Some(symbol) => dir.join(symbol),
+        None => dir,
+    };
+    dir.join(format!("{}.json", request.name))
+}
+
+/// Transport storing body of every response of wrapped transport under given directory
+pub struct RecordingTransport {
+    inner: Box<dyn Transport>,
+    dir: PathBuf,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Box<dyn Transport>, dir: &Path) -> Result<Self, Error> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            Error::Config(format!("Unable to create directory {}: {e}", dir.display()))
+        })?;
+        Ok(Self {
+            inner,
+            dir: dir.to_owned(),
+        })
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn source(&self) -> DataSource {
+        self.inner.source()
+    }
+
+    fn get(&self, request: &Request) -> Result<String, Error> {
+        let body = self.inner.get(request)?;
+        let path = response_path(&self.dir, self.source(), request);
+        let what = format!("response file {}", path.display());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| Error::Config(format!("Unable to create {what}: {e}")))?;
+        }
+        std::fs::write(&path, &body)
+            .map_err(|e| Error::Config(format!("Unable to write {what}: {e}")))?;
+        Ok(body)
+    }
+}
+
+/// Transport serving responses of given provider stored by `RecordingTransport`
+pub struct ReplayTransport {
+    dir: PathBuf,
+    source: DataSource,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: &Path, source: DataSource) -> Result<Self, Error> {
+        if !dir.is_dir() {
+            return Err(Error::Config(format!(
+                "Directory of recorded responses: {} does not exist",
+                dir.display()
+            )));
+        }
+        Ok(Self {
+            dir: dir.to_owned(),
+            source,
+        })
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn source(&self) -> DataSource {
+        self.source
+    }
+
+    fn get(&self, request: &Request) -> Result<String, Error> {
+        let path = response_path(&self.dir, self.source, request);
+        match std::fs::read_to_string(&path) {
+            Ok(body) => Ok(body),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let err = Error::missing(&format!("recorded {} response", request.name))
+                    .with_provider(self.source);
+                Err(match &request.symbol {
+                    Some(symbol) => err.with_ticker(symbol),
+                    None => err,
+                })
+            }
+            Err(e) => Err(Error::Config(format!(
+                "Unable to read response file {}: {e}",
+                path.display()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Transport answering every request with its name and URL
+    struct EchoTransport;
+
+    impl Transport for EchoTransport {
+        fn source(&self) -> DataSource {
+            DataSource::Polygon
+        }
+
+        fn get(&self, request: &Request) -> Result<String, Error> {
+            Ok(format!("{{\"{}\": \"{}\"}}", request.name, request.url))
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() -> Result<(), String> {
+        let dir = std::env::temp_dir().join(format!("record-replay-{}", std::process::id()));
+        let recorder =
+            RecordingTransport::new(Box::new(EchoTransport), &dir).map_err(|e| e.to_string())?;
+        let dividends = Request::ticker("ABR", "dividends", "https://dividends?page=1".to_owned());
+        let next_page = dividends.page(2, "https://dividends?page=2".to_owned());
+        let tickers = Request::market("tickers", "https://tickers".to_owned());
+
+        let mut recorded = vec![];
+        for request in [&dividends, &next_page, &tickers] {
+            recorded.push(recorder.get(request).map_err(|e| e.to_string())?);
+        }
+        assert!(dir.join("Polygon/ABR/dividends.json").is_file());
+        assert!(dir.join("Polygon/ABR/dividends-2.json").is_file());
+        assert!(dir.join("Polygon/tickers.json").is_file());
+
+        // Bodies are served whatever the URL of request is
+        let replay = ReplayTransport::new(&dir, DataSource::Polygon).map_err(|e| e.to_string())?;
+        let moved = Request {
+            url: "https://elsewhere".to_owned(),
+            ..dividends.clone()
+        };
+        for (request, body) in [&moved, &next_page, &tickers].into_iter().zip(&recorded) {
+            assert_eq!(replay.get(request).as_ref(), Ok(body));
+        }
+
+        // Ticker that was never recorded
+        let err = replay
+            .get(&Request::ticker("ABM", "dividends", String::new()))
+            .unwrap_err();
+        assert_eq!(err.ticker(), Some("ABM"));
+        assert_eq!(
+            err.to_string(),
+            "Polygon: missing recorded dividends response for ABM"
+        );
+
+        // Responses of other provider
+        let replay = ReplayTransport::new(&dir, DataSource::Yahoo).map_err(|e| e.to_string())?;
+        assert!(replay.get(&dividends).is_err());
+
+        std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
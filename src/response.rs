@@ -0,0 +1,149 @@
+//! Bodies of provider responses, limited to the fields analysis needs. Fields missing from
+//! response (or null) are parsed as `None`, unknown ones are ignored.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Response of Yahoo chart API: daily quotes of requested range, together with dividend and
+/// split events if they were asked for
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooChart {
+    pub chart: YahooChartBody,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooChartBody {
+    pub result: Option<Vec<YahooChartResult>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooChartResult {
+    pub meta: YahooMeta,
+    #[serde(default)]
+    pub timestamp: Vec<i64>,
+    pub indicators: YahooIndicators,
+    pub events: Option<YahooEvents>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooMeta {
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooIndicators {
+    #[serde(default)]
+    pub quote: Vec<YahooBars>,
+}
+
+/// Values of daily bars, in order of timestamps. Days without trading have null values
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooBars {
+    #[serde(default)]
+    pub close: Vec<Option<f64>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooEvents {
+    /// Dividends by timestamp of ex-dividend date
+    pub dividends: Option<HashMap<String, YahooDividend>>,
+    /// Splits by timestamp of their date
+    pub splits: Option<HashMap<String, YahooSplit>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooDividend {
+    pub amount: f64,
+    pub date: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct YahooSplit {
+    pub date: i64,
+    pub numerator: f64,
+    pub denominator: f64,
+}
+
+impl YahooChart {
+    /// Results of all instruments in response (Yahoo serves the requested one only)
+    pub fn results(&self) -> &[YahooChartResult] {
+        self.chart.result.as_deref().unwrap_or_default()
+    }
+
+    /// Timestamps and closes of days with trading, the oldest first
+    pub fn closes(&self) -> Vec<(i64, f64)> {
+        self.results()
+            .iter()
+            .flat_map(|r| {
+                let closes = r.indicators.quote.first().map(|bars| bars.close.as_slice());
+                r.timestamp
+                    .iter()
+                    .zip(closes.unwrap_or_default())
+                    .filter_map(|(timestamp, close)| Some((*timestamp, (*close)?)))
+            })
+            .collect()
+    }
+
+    /// Currency of quotes, as reported in metadata of instrument
+    pub fn currency(&self) -> Option<&str> {
+        self.results().first()?.meta.currency.as_deref()
+    }
+}
+
+/// Dividend of Polygon.io `/v3/reference/dividends` response
+#[derive(Debug, Deserialize)]
+pub(crate) struct PolygonDividend {
+    pub ticker: String,
+    pub ex_dividend_date: String,
+    pub pay_date: Option<String>,
+    pub record_date: Option<String>,
+    pub declaration_date: Option<String>,
+    pub cash_amount: f64,
+    pub currency: Option<String>,
+    /// "CD" (regular), "SC" (special), "LT" and "ST" (capital gains distributions)
+    pub dividend_type: String,
+    /// Payments per year, zero for one-time payment
+    #[serde(default)]
+    pub frequency: u32,
+}
+
+/// Statements of Polygon.io `/vX/reference/financials` report, values by their names
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct PolygonFinancials {
+    pub cash_flow_statement: Option<HashMap<String, PolygonFinancialValue>>,
+    pub income_statement: Option<HashMap<String, PolygonFinancialValue>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PolygonFinancialValue {
+    pub value: Option<f64>,
+    pub unit: Option<String>,
+    pub label: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yahoo_chart() -> Result<(), String> {
+        // Day without trading has null close, response without events has none of them
+        let body = r#"{"chart":{"result":[{"meta":{"currency":"USD","symbol":"ABR"},
+            "timestamp":[1687786200,1687872600,1687959000],
+            "indicators":{"quote":[{"close":[12.21,null,12.44]}]}}],"error":null}}"#;
+        let chart: YahooChart = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        assert_eq!(
+            chart.closes(),
+            vec![(1687786200, 12.21), (1687959000, 12.44)]
+        );
+        assert_eq!(chart.currency(), Some("USD"));
+        assert!(chart.results()[0].events.is_none());
+
+        // Unknown symbol has no results
+        let body = r#"{"chart":{"result":null,"error":{"code":"Not Found"}}}"#;
+        let chart: YahooChart = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        assert_eq!(chart.closes(), vec![]);
+        assert_eq!(chart.currency(), None);
+        Ok(())
+    }
+}
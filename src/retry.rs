@@ -0,0 +1,330 @@
+//! Retrying of failed requests to providers.
+//!
+//! Requests failing for transient reasons (connection problems, timeouts, HTTP 429 and 5xx)
+//! are repeated after exponentially growing delays with random jitter, unless provider told
+//! how long to wait with `Retry-After`. Both are bounded by maximal delay of policy. Once
+//! attempts are exhausted the last failure is returned as `Error`, so callers never get empty
+//! data instead.
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::{DataSource, Error};
+
+/// How failed requests are repeated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of attempts including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry. Every next one is twice as long
+    pub base_delay: Duration,
+    /// Upper bound of delay, both computed by backoff and requested by provider
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Description of failed request needed to decide whether to repeat it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// HTTP status, `None` if no response was received
+    pub status: Option<u16>,
+    /// Whether repeating request may succeed
+    pub transient: bool,
+    /// Delay requested by provider with `Retry-After` header
+    pub retry_after: Option<Duration>,
+    pub cause: String,
+}
+
+impl Failure {
+    /// Failure with given HTTP status. Rate limiting, server errors and timeouts are transient
+    pub fn from_status(status: u16, cause: String) -> Self {
+        Self {
+            status: Some(status),
+            transient: status == 408 || status == 429 || (500..600).contains(&status),
+            retry_after: None,
+            cause,
+        }
+    }
+
+    /// Failure of request made with reqwest. Requests which got no response (connection reset,
+    /// timeout) are transient, while responses that could not be decoded are not
+    pub fn from_reqwest(e: &reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => Self::from_status(status.as_u16(), e.to_string()),
+            None => Self {
+                status: None,
+                transient: !e.is_decode() && !e.is_builder(),
+                retry_after: None,
+                cause: e.to_string(),
+            },
+        }
+    }
+
+    /// Failure of response with error HTTP status, None for any other response. Delay
+    /// requested by provider with `Retry-After` header is kept
+    pub fn from_response(response: &reqwest::Response) -> Option<Self> {
+        let status = response.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return None;
+        }
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        Some(Self {
+            retry_after,
+            ..Self::from_status(status.as_u16(), status.to_string())
+        })
+    }
+
+    fn into_error(self, provider: DataSource, ticker: Option<&str>) -> Error {
+        let ticker = ticker.map(|t| t.to_owned());
+        match self.status {
+            Some(429) => Error::RateLimited { provider, ticker },
+            status => Error::Network {
+                provider,
+                ticker,
+                status,
+                cause: self.cause,
+            },
+        }
+    }
+}
+
+/// Delay given as value of `Retry-After` header: number of seconds or HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or_default())
+}
+
+impl RetryPolicy {
+    /// Policy trying every request only once
+    pub fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before given retry (1 for the first one): random part of exponentially growing
+    /// backoff ("full jitter"), or delay requested by provider. Neither exceeds `max_delay`,
+    /// so a single response cannot stall fetching for hours
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let random = RandomState::new().build_hasher().finish();
+        backoff.mul_f64((random % 1000) as f64 / 1000.0)
+    }
+
+    /// Failure to report after given attempt, or delay to wait before repeating request
+    fn next(&self, attempt: u32, failure: &Failure) -> Option<Duration> {
+        if !failure.transient || attempt >= self.max_attempts {
+            return None;
+        }
+        let delay = self.delay(attempt, failure.retry_after);
+        log::warn!(
+            "Request failed ({}), retrying in {delay:.1?} (attempt {} of {})",
+            failure.cause,
+            attempt + 1,
+            self.max_attempts
+        );
+        Some(delay)
+    }
+
+    /// Make blocking request until it succeeds or attempts are exhausted
+    pub fn run<T, E>(
+        &self,
+        provider: DataSource,
+        ticker: Option<&str>,
+        failure: impl Fn(&E) -> Failure,
+        mut request: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, Error> {
+        let mut attempt = 1;
+        loop {
+            match request() {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    let failure = failure(&e);
+                    match self.next(attempt, &failure) {
+                        Some(delay) => std::thread::sleep(delay),
+                        None => return Err(failure.into_error(provider, ticker)),
+                    }
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Make async request until it succeeds or attempts are exhausted. Waiting does not block
+    /// thread of async runtime, so requests of other companies proceed meanwhile
+    pub async fn run_async<T, E, F>(
+        &self,
+        provider: DataSource,
+        ticker: Option<&str>,
+        failure: impl Fn(&E) -> Failure,
+        mut request: impl FnMut() -> F,
+    ) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match request().await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    let failure = failure(&e);
+                    match self.next(attempt, &failure) {
+                        Some(delay) => tokio::time::sleep(delay).await,
+                        None => return Err(failure.into_error(provider, ticker)),
+                    }
+                }
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn test_retry_transient_failures() -> Result<(), String> {
+        let mut attempts = 0;
+        let result = policy().run(
+            DataSource::Polygon,
+            Some("ABR"),
+            |status: &u16| Failure::from_status(*status, format!("status {status}")),
+            || {
+                attempts += 1;
+                if attempts < 3 {
+                    Err(503)
+                } else {
+                    Ok(attempts)
+                }
+            },
+        );
+        assert_eq!(result, Ok(3));
+
+        // Exhausted retries are reported
+        let mut attempts = 0;
+        let result: Result<(), Error> = policy().run(
+            DataSource::Polygon,
+            Some("ABR"),
+            |status: &u16| Failure::from_status(*status, format!("status {status}")),
+            || {
+                attempts += 1;
+                Err(429)
+            },
+        );
+        assert_eq!(attempts, 3);
+        assert_eq!(
+            result,
+            Err(Error::RateLimited {
+                provider: DataSource::Polygon,
+                ticker: Some("ABR".to_owned()),
+            })
+        );
+
+        // Client errors are not repeated
+        let mut attempts = 0;
+        let result: Result<(), Error> = policy().run(
+            DataSource::Yahoo,
+            None,
+            |status: &u16| Failure::from_status(*status, format!("status {status}")),
+            || {
+                attempts += 1;
+                Err(404)
+            },
+        );
+        assert_eq!(attempts, 1);
+        assert!(matches!(
+            result,
+            Err(Error::Network {
+                status: Some(404),
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_async() -> Result<(), String> {
+        let mut attempts = 0;
+        let result = crate::fetch::runtime()
+            .map_err(|e| e.to_string())?
+            .block_on(policy().run_async(
+                DataSource::Polygon,
+                Some("ABR"),
+                |status: &u16| Failure::from_status(*status, format!("status {status}")),
+                || {
+                    attempts += 1;
+                    let attempt = attempts;
+                    async move {
+                        if attempt < 3 {
+                            Err(500)
+                        } else {
+                            Ok(attempt)
+                        }
+                    }
+                },
+            ));
+        assert_eq!(result, Ok(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_backoff_delay() -> Result<(), String> {
+        let policy = RetryPolicy::default();
+        for retry in 1..10 {
+            let delay = policy.delay(retry, None);
+            let backoff = Duration::from_secs(2u64.pow(retry - 1)).min(policy.max_delay);
+            assert!(delay <= backoff, "{delay:?} > {backoff:?}");
+        }
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(30))),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(3600))),
+            policy.max_delay
+        );
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+        Ok(())
+    }
+}
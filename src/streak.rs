@@ -0,0 +1,152 @@
+//! Dividend streaks and classification of companies into lists of dividend growers.
+//!
+//! Streaks are counted back from the most recent complete year, using annual sums of
+//! dividends. Years without any dividend count as zero, so they end every streak.
+
+use std::collections::BTreeMap;
+
+/// Lists of companies increasing dividends, by number of consecutive years of growth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DividendList {
+    /// 5-9 years of growth
+    Challenger,
+    /// 10-24 years of growth
+    Contender,
+    /// 25 or more years of growth
+    Champion,
+}
+
+impl DividendList {
+    /// List company with given number of consecutive years of dividend growth belongs to
+    pub fn from_years_of_growth(years: i64) -> Option<Self> {
+        match years {
+            25.. => Some(DividendList::Champion),
+            10..=24 => Some(DividendList::Contender),
+            5..=9 => Some(DividendList::Challenger),
+            _ => None,
+        }
+    }
+
+    /// Name of the list, the same as name of sheet of dividend list spreadsheet
+    pub fn name(&self) -> &'static str {
+        match self {
+            DividendList::Champion => "Champions",
+            DividendList::Contender => "Contenders",
+            DividendList::Challenger => "Challengers",
+        }
+    }
+
+    /// List of given name, `None` for unknown names like "All"
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            DividendList::Champion,
+            DividendList::Contender,
+            DividendList::Challenger,
+        ]
+        .into_iter()
+        .find(|list| list.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Consecutive years of dividend history up to the most recent complete year
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Streaks {
+    /// Years in which annual dividend was higher than in the year before
+    pub growth: i64,
+    /// Years in which annual dividend was not lower than in the year before
+    pub no_cut: i64,
+    /// Years in which any dividend was paid
+    pub payment: i64,
+}
+
+impl Streaks {
+    /// List company belongs to according to its years of growth
+    pub fn list(&self) -> Option<DividendList> {
+        DividendList::from_years_of_growth(self.growth)
+    }
+}
+
+/// Streaks of given annual dividends ending at `last_year`. Dividends of later years are not
+/// taken into account
+pub fn streaks(annual_div: &BTreeMap<i32, f64>, last_year: i32) -> Streaks {
+    let first_year = match annual_div.keys().next() {
+        Some(year) => *year,
+        None => return Streaks::default(),
+    };
+    let div = |year: i32| annual_div.get(&year).copied().unwrap_or(0.0);
+
+    let mut streaks = Streaks::default();
+    let (mut growing, mut not_cut) = (true, true);
+    for year in (first_year..=last_year).rev() {
+        let (current, previous) = (div(year), div(year - 1));
+        if current <= 0.0 {
+            break;
+        }
+        streaks.payment += 1;
+        // Year before the first payment does not count as growth
+        let paid_before = previous > 0.0;
+        growing = growing && paid_before && current > previous;
+        not_cut = not_cut && paid_before && current >= previous;
+        streaks.growth += growing as i64;
+        streaks.no_cut += not_cut as i64;
+    }
+    streaks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaks() -> Result<(), String> {
+        let annual_div = BTreeMap::from([
+            (2015, 0.5),
+            (2016, 0.8),
+            // Dividend suspended in 2017
+            (2018, 1.0),
+            (2019, 1.1),
+            (2020, 1.1),
+            (2021, 1.2),
+            (2022, 1.3),
+            (2023, 1.4),
+        ]);
+        assert_eq!(
+            streaks(&annual_div, 2023),
+            Streaks {
+                growth: 3,
+                no_cut: 5,
+                payment: 6,
+            }
+        );
+        // Later years are ignored
+        assert_eq!(streaks(&annual_div, 2020).growth, 0);
+        assert_eq!(streaks(&annual_div, 2020).no_cut, 2);
+        // No dividend in the last year ends all streaks
+        assert_eq!(streaks(&annual_div, 2024), Streaks::default());
+        assert_eq!(streaks(&BTreeMap::new(), 2024), Streaks::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dividend_list() -> Result<(), String> {
+        assert_eq!(DividendList::from_years_of_growth(4), None);
+        assert_eq!(
+            DividendList::from_years_of_growth(5),
+            Some(DividendList::Challenger)
+        );
+        assert_eq!(
+            DividendList::from_years_of_growth(24),
+            Some(DividendList::Contender)
+        );
+        assert_eq!(
+            DividendList::from_years_of_growth(25),
+            Some(DividendList::Champion)
+        );
+        assert_eq!(
+            DividendList::from_name("contenders"),
+            Some(DividendList::Contender)
+        );
+        assert_eq!(DividendList::from_name("All"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+//! Raw requests to providers.
+//!
+//! Providers build requests and parse bodies of responses, while `Transport` only delivers
+//! them. Live requests are sent over HTTP by `HttpTransport`; recorded and replayed ones
+//! (see `crate::recording`) go through the very same parsing code of provider.
+
+use reqwest::header::{COOKIE, SET_COOKIE};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::fetch::{self, RateLimiter};
+use crate::retry::{Failure, RetryPolicy};
+use crate::{DataSource, Error};
+
+const YAHOO_COOKIE_URL: &str = "https://fc.yahoo.com";
+const YAHOO_CRUMB_URL: &str = "https://query1.finance.yahoo.com/v1/test/getcrumb";
+
+/// Request for data of provider. Its name tells responses of the same company apart when they
+/// are recorded, so it stays the same whatever the URL is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// Company data is requested for, None for data of whole market e.g. list of companies
+    pub symbol: Option<String>,
+    pub name: String,
+    pub url: String,
+    /// Whether provider serves response only within session (Yahoo quote summary)
+    pub session: bool,
+}
+
+impl Request {
+    /// Request for data of given company
+    pub fn ticker(symbol: &str, name: &str, url: String) -> Self {
+        Self {
+            symbol: Some(symbol.to_owned()),
+            name: name.to_owned(),
+            url,
+            session: false,
+        }
+    }
+
+    /// Request for data of whole market
+    pub fn market(name: &str, url: String) -> Self {
+        Self {
+            symbol: None,
+            name: name.to_owned(),
+            url,
+            session: false,
+        }
+    }
+
+    /// Request sent within session of provider
+    pub fn in_session(self) -> Self {
+        Self {
+            session: true,
+            ..self
+        }
+    }
+
+    /// Request for given page (2 for the one following the first) of paginated response
+    pub fn page(&self, page: usize, url: String) -> Self {
+        Self {
+            name: format!("{}-{page}", self.name),
+            url,
+            ..self.clone()
+        }
+    }
+}
+
+/// Delivery of requests to provider. Transports are shared by threads fetching data of
+/// different companies
+pub trait Transport: Send + Sync {
+    /// Provider requests are sent to
+    fn source(&self) -> DataSource;
+
+    /// Body of response to given request
+    fn get(&self, request: &Request) -> Result<String, Error>;
+}
+
+/// Cookie and crumb of Yahoo session
+#[derive(Debug, Clone)]
+struct Session {
+    cookie: String,
+    crumb: String,
+}
+
+/// Transport sending requests over HTTP with client shared by the whole process
+pub struct HttpTransport {
+    source: DataSource,
+    client: &'static reqwest::Client,
+    runtime: &'static tokio::runtime::Runtime,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    retry: RetryPolicy,
+    /// Key sent with every request
+    api_key: Option<String>,
+    /// Session started by the first request needing it
+    session: Mutex<Option<Session>>,
+}
+
+impl HttpTransport {
+    pub fn new(source: DataSource) -> Result<Self, Error> {
+        Ok(Self {
+            source,
+            client: fetch::http_client()?,
+            runtime: fetch::runtime()?,
+            rate_limiter: None,
+            retry: RetryPolicy::default(),
+            api_key: None,
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Transport of requests to Polygon.io, authorized with POLYGON_AUTH_KEY
+    pub fn polygon() -> Result<Self, Error> {
+        let api_key = std::env::var("POLYGON_AUTH_KEY").map_err(|_| {
+            Error::Config("POLYGON_AUTH_KEY has to be set to use Polygon.io provider".to_owned())
+        })?;
+        Ok(Self {
+            api_key: Some(api_key),
+            ..Self::new(DataSource::Polygon)?
+        })
+    }
+
+    /// Transport waiting for permission of given limiter before every request
+    pub fn rate_limited(self, rate_limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            rate_limiter: Some(rate_limiter),
+            ..self
+        }
+    }
+
+    /// Transport repeating failed requests according to given policy
+    pub fn with_retry(self, retry: RetryPolicy) -> Self {
+        Self { retry, ..self }
+    }
+
+    /// Send request built anew for every attempt, repeating it according to retry policy.
+    /// Every attempt waits for permission of rate limiter
+    async fn send(
+        &self,
+        symbol: Option<&str>,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<String, Error> {
+        let attempt = move || {
+            let mut request = build();
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+            async move {
+                if let Some(rate_limiter) = &self.rate_limiter {
+                    rate_limiter.acquire().await;
+                }
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| Failure::from_reqwest(&e))?;
+                if let Some(failure) = Failure::from_response(&response) {
+                    return Err(failure);
+                }
+                response.text().await.map_err(|e| Failure::from_reqwest(&e))
+            }
+        };
+        self.retry
+            .run_async(self.source, symbol, Failure::clone, attempt)
+            .await
+    }
+
+    /// Session of Yahoo: cookie set by its landing page and crumb issued for that cookie
+    async fn session(&self) -> Result<Session, Error> {
+        let started = self
+            .session
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if let Some(session) = started {
+            return Ok(session);
+        }
+        // Only cookie of response matters, not its status
+        let response = self
+            .client
+            .get(YAHOO_COOKIE_URL)
+            .send()
+            .await
+            .map_err(|e| Error::Network {
+                provider: self.source,
+                ticker: None,
+                status: None,
+                cause: e.to_string(),
+            })?;
+        let cookie = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok()?.split(';').next())
+            .collect::<Vec<_>>()
+            .join("; ");
+        if cookie.is_empty() {
+            return Err(Error::missing("cookie of session").with_provider(self.source));
+        }
+        let crumb = self
+            .send(None, || {
+                self.client.get(YAHOO_CRUMB_URL).header(COOKIE, &cookie)
+            })
+            .await?;
+        let session = Session { cookie, crumb };
+        *self.session.lock().unwrap_or_else(PoisonError::into_inner) = Some(session.clone());
+        Ok(session)
+    }
+
+    async fn get_async(&self, request: &Request) -> Result<String, Error> {
+        let symbol = request.symbol.as_deref();
+        if !request.session {
+            return self.send(symbol, || self.client.get(&request.url)).await;
+        }
+        let session = self.session().await?;
+        self.send(symbol, || {
+            self.client
+                .get(&request.url)
+                .query(&[("crumb", &session.crumb)])
+                .header(COOKIE, &session.cookie)
+        })
+        .await
+    }
+}
+
+impl Transport for HttpTransport {
+    fn source(&self) -> DataSource {
+        self.source
+    }
+
+    fn get(&self, request: &Request) -> Result<String, Error> {
+        log::info!("{}: GET {}", self.source, request.url);
+        self.runtime.block_on(self.get_async(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// Answer connections to local port with given raw HTTP responses, one per connection.
+    /// Returns URL of the server
+    fn serve(responses: Vec<&'static str>) -> Result<String, String> {
+        let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
+        let url = format!(
+            "http://{}/quote",
+            listener.local_addr().map_err(|e| e.to_string())?
+        );
+        std::thread::spawn(move || {
+            for response in responses {
+                let mut stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(_) => return,
+                };
+                // Request has no body, so it ends with its headers
+                let mut request = vec![];
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        Ok(url)
+    }
+
+    const TOO_MANY_REQUESTS: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+
+    #[test]
+    fn test_retry_after_of_response() -> Result<(), String> {
+        let url = serve(vec![TOO_MANY_REQUESTS, OK])?;
+        // Backoff alone would wait for up to an hour
+        let transport = HttpTransport::new(DataSource::Polygon)
+            .map_err(|e| e.to_string())?
+            .with_retry(RetryPolicy {
+                max_attempts: 2,
+                base_delay: Duration::from_secs(3600),
+                max_delay: Duration::from_secs(3600),
+            });
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(transport.get(&Request::ticker("ABR", "quote", url)));
+        });
+        let body = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .map_err(|_| "Retry-After of response was not honored")?;
+        assert_eq!(body, Ok("{}".to_owned()));
+
+        // Rate limiting is reported once attempts are exhausted
+        let url = serve(vec![TOO_MANY_REQUESTS])?;
+        let transport = HttpTransport::new(DataSource::Polygon)
+            .map_err(|e| e.to_string())?
+            .with_retry(RetryPolicy::no_retries());
+        assert_eq!(
+            transport.get(&Request::ticker("ABR", "quote", url)),
+            Err(Error::RateLimited {
+                provider: DataSource::Polygon,
+                ticker: Some("ABR".to_owned()),
+            })
+        );
+        Ok(())
+    }
+}
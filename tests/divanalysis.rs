@@ -0,0 +1,61 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Run divanalysis on responses of tests/fixtures/replay and return its standard output
+fn divanalysis(args: &[&str]) -> Result<String, String> {
+    let replay = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+    let output = Command::new(env!("CARGO_BIN_EXE_divanalysis"))
+        .arg("--replay")
+        .arg(replay)
+        .args(["--no-cache", "--as-of", "2023-06-30"])
+        .args(args)
+        .output()
+        .map_err(|e| e.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() {
+        return Err(format!(
+            "divanalysis {args:?} failed: {stdout}{}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(stdout)
+}
+
+#[test]
+fn test_company_from_replay() -> Result<(), String> {
+    let stdout = divanalysis(&["--provider", "yahoo", "--company", "abr"])?;
+    assert!(stdout.contains("ABR"));
+    assert!(stdout.contains("12.5"));
+    assert!(!stdout.contains("Unable to acquire data"));
+
+    // The same company recorded from Polygon.io
+    let stdout = divanalysis(&["--provider", "polygon", "--company", "ABR"])?;
+    assert!(stdout.contains("ABR"));
+    assert!(stdout.contains("12.5"));
+
+    // Share price is converted with replayed exchange rate of 4.0
+    let stdout = divanalysis(&[
+        "--provider",
+        "yahoo",
+        "--company",
+        "ABR",
+        "--currency",
+        "pln",
+    ])?;
+    assert!(stdout.contains("50.0"));
+    Ok(())
+}
+
+#[test]
+fn test_crawl_from_replay() -> Result<(), String> {
+    let stdout = divanalysis(&["--provider", "polygon", "--list-all"])?;
+    assert!(stdout.contains("ABM Industries Incorporated"));
+    assert!(stdout.contains("Arbor Realty Trust"));
+
+    // Companies without recorded responses are reported, not fatal
+    let stdout = divanalysis(&["--provider", "polygon"])?;
+    assert!(stdout.contains("ABR"));
+    assert!(stdout.contains("Unable to acquire data of 1 companies:"));
+    assert!(stdout.contains("Polygon: missing recorded quote response for ABM"));
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_forecast_from_replay() -> Result<(), String> {
+    let replay = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+    // Plots are made with gnuplot into working directory
+    let dir = std::env::temp_dir().join(format!("divforecasting-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let output = Command::new(env!("CARGO_BIN_EXE_divforecasting"))
+        .current_dir(&dir)
+        .env("RUST_LOG", "info")
+        .arg("--replay")
+        .arg(replay)
+        .args(["--provider", "yahoo", "--no-cache", "--as-of", "2023-06-30"])
+        .args(["--company", "ABR", "--currency", "PLN"])
+        .output()
+        .map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_dir_all(&dir);
+    let log = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.status.success(), "{log}");
+    assert!(!log.contains("Skipping forecast"), "{log}");
+
+    // Share price is converted into currency of capital with replayed exchange rate of 4.0
+    assert!(log.contains("Forcasting stock: ABR with params: share price(50)"));
+    assert!(log.contains("Frequency(4)"));
+    Ok(())
+}
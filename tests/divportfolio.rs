@@ -0,0 +1,30 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_portfolio_from_replay() -> Result<(), String> {
+    let replay = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+    let output = Command::new(env!("CARGO_BIN_EXE_divportfolio"))
+        .arg("--replay")
+        .arg(replay)
+        .args(["--provider", "yahoo", "--no-cache", "--as-of", "2023-06-30"])
+        .output()
+        .map_err(|e| e.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("ABEV"));
+    assert!(stdout.contains("AHOG.DE"));
+
+    // Dividends of previous year are converted with replayed exchange rates
+    assert!(stdout.contains("Kurs USD/PLN: 4.0000"));
+    assert!(stdout.contains("Kurs EUR/PLN: 4.4000"));
+    assert!(stdout.contains("01 : 77.08 PLN"));
+    assert!(stdout.contains("12 : 283.86 PLN"));
+    assert!(stdout.contains("05 : 1260.93 PLN"));
+    assert!(stdout.contains("Combined portfolio annual income [PLN]"));
+    Ok(())
+}